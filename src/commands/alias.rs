@@ -0,0 +1,104 @@
+use super::argparser::ArgParser;
+use super::cmds::CommandOutput;
+use crate::utils::alias::{
+    clear_aliases, get_alias, get_aliases, is_valid_name, remove_alias, set_alias,
+};
+use crate::utils::log;
+
+pub const HELP_TXT: &str = r#"
+Usage: alias [name[='command']]...
+
+Defines shortcuts for commands. Aliases are saved and available the next time you play.
+- alias                 : List all aliases.
+- alias <name>          : Show the alias <name>.
+- alias ll='ls -l'      : Make `ll` run `ls -l`. Arguments after `ll` are kept.
+
+Some sekai come with their own aliases. Use `unalias` to remove an alias.
+"#;
+
+pub const UNALIAS_HELP_TXT: &str = r#"
+Usage: unalias [-a] <name>...
+
+Removes aliases.
+- unalias <name>  : Remove the alias <name>.
+- unalias -a      : Remove all aliases.
+"#;
+
+fn format_alias(name: &str, value: &str) -> String {
+    format!("alias {name}='{value}'")
+}
+
+pub fn alias(args: &[&str]) -> CommandOutput {
+    if args.iter().any(|arg| *arg == "-h" || *arg == "--help") {
+        return CommandOutput::ok(HELP_TXT);
+    }
+
+    if args.is_empty() {
+        let aliases = get_aliases();
+        if aliases.is_empty() {
+            return CommandOutput::ok("No aliases defined. Try 'help alias' for more information.");
+        }
+        return CommandOutput::ok(
+            aliases
+                .iter()
+                .map(|(name, value)| format_alias(name, value))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+    }
+
+    // The shell already removed the quotes: `ll='ls -l'` is one argument
+    let (mut output, mut errors) = (Vec::new(), Vec::new());
+    for word in args {
+        match word.split_once('=') {
+            Some((name, value)) => {
+                if !is_valid_name(name) {
+                    errors.push(format!("alias: '{name}': invalid alias name"));
+                } else if value.trim().is_empty() {
+                    errors.push(format!("alias: '{name}': alias cannot be empty"));
+                } else {
+                    let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+                    log::log_info("alias", &format!("Defined alias {name}='{value}'"));
+                    set_alias(name, &value);
+                }
+            }
+            None => match get_alias(word) {
+                Some(value) => output.push(format_alias(word, &value)),
+                None => errors.push(format!("alias: {word}: not found")),
+            },
+        }
+    }
+    CommandOutput::lines(output, errors)
+}
+
+pub fn unalias(args: &[&str]) -> CommandOutput {
+    let mut parser = ArgParser::new(&["-a"]);
+    let args_string: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+
+    match parser.parse(&args_string, "unalias") {
+        Ok(_) => {
+            if parser.has_flag("-a") {
+                clear_aliases();
+                return CommandOutput::ok("All aliases removed.");
+            }
+            let pos_args = parser.get_positional_args();
+            if pos_args.is_empty() {
+                return CommandOutput::err(
+                    "unalias: missing alias name\nTry 'help unalias' for more information.",
+                );
+            }
+            let errors = pos_args
+                .iter()
+                .filter(|name| !remove_alias(name))
+                .map(|name| format!("unalias: {name}: not found"))
+                .collect();
+            CommandOutput::lines(Vec::new(), errors)
+        }
+        Err(e) => match &e[..] {
+            "help" => CommandOutput::ok(UNALIAS_HELP_TXT),
+            _ => CommandOutput::err(
+                "unalias: unknown flag\nTry 'help unalias' for more information.",
+            ),
+        },
+    }
+}
@@ -1,18 +1,170 @@
 use super::*;
-use crate::utils::auth::get_current_username;
-use crate::utils::prompt::UserPrompter;
+use crate::metainfo::hidden::Visibility;
+use crate::metainfo::objectives;
+use crate::metainfo::triggers;
+use crate::utils::alias::{expand_chain, get_function};
+use crate::utils::audit::{self, AuditEntry};
+use crate::utils::caller::Caller;
+use crate::utils::events::{self, CommandEvent, LineEvent, Notice, ShellEvent};
+use crate::utils::find_root::home_relative;
+use crate::utils::prompt::{RecordingPrompter, UserPrompter};
+use crate::utils::session;
+use crate::utils::shell_history;
+use crate::utils::style;
+use crate::utils::variables::expand_vars;
+use serde::Serialize;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Once;
 
 /// CommandResult enum to represent the result of a command execution
 pub enum CommandResult {
-    Output(String),
-    ChangeDirectory(PathBuf, String),
+    Output(CommandOutput),
+    ChangeDirectory(PathBuf, CommandOutput),
     Clear,
     Exit,
     NotFound,
 }
 
+impl CommandResult {
+    /// Exit status of the command. 0 on success, 127 if the command does not exist.
+    pub fn status(&self) -> i32 {
+        match self {
+            CommandResult::Output(output) | CommandResult::ChangeDirectory(_, output) => {
+                output.status
+            }
+            CommandResult::Clear | CommandResult::Exit => 0,
+            CommandResult::NotFound => STATUS_NOT_FOUND,
+        }
+    }
+}
+
+/// Exit status of a command that does not exist, as in other shells.
+pub const STATUS_NOT_FOUND: i32 = 127;
+
+/// What a command printed, and whether it succeeded.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CommandOutput {
+    pub status: i32,
+    pub stdout: String,
+    pub stderr: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<Payload>,
+    /// Whether the screen is cleared before showing it, after a `clear` in a chain.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub clear: bool,
+    /// Short news worth a notification, like objectives met. Already part of `stdout`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notifications: Vec<String>,
+}
+
+/// Structured data returned alongside the text of some commands.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Payload {
+    /// Entries listed by `ls`.
+    Listing {
+        files: Vec<String>,
+        directories: Vec<String>,
+    },
+    /// Flag obtained by solving a level with `solve`.
+    Flag { level: String, flag: String },
+    /// Level or chest opened with `unlock`.
+    Lock { path: String },
+    /// File shown by `read`. Front-ends may show long ones in a pager.
+    Document { path: String },
+}
+
+impl CommandOutput {
+    pub fn ok(stdout: impl Into<String>) -> Self {
+        CommandOutput {
+            stdout: stdout.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn err(stderr: impl Into<String>) -> Self {
+        CommandOutput {
+            status: 1,
+            stderr: stderr.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Output of a command that handles several arguments, some of which may fail.
+    pub fn lines(stdout: Vec<String>, stderr: Vec<String>) -> Self {
+        CommandOutput {
+            status: if stderr.is_empty() { 0 } else { 1 },
+            stdout: stdout.join("\n"),
+            stderr: stderr.join("\n"),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_payload(mut self, payload: Payload) -> Self {
+        self.payload = Some(payload);
+        self
+    }
+
+    /// Adds what subscribers told about the events of the command to stdout, and the news
+    /// to the notifications.
+    fn tell(&mut self, notices: Vec<Notice>) {
+        for notice in notices {
+            if !self.stdout.is_empty() {
+                self.stdout.push('\n');
+            }
+            match notice {
+                Notice::Line(line) => self.stdout.push_str(&line),
+                Notice::News(line) => {
+                    self.stdout.push_str(&style::bold(&line));
+                    self.notifications.push(line);
+                }
+            }
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.status == 0
+    }
+
+    /// Everything the command printed, stdout first. Errors are styled as such.
+    pub fn text(&self) -> String {
+        match (self.stdout.is_empty(), self.stderr.is_empty()) {
+            (_, true) => self.stdout.clone(),
+            (true, false) => style::error(&self.stderr),
+            (false, false) => format!("{}\n{}", self.stdout, style::error(&self.stderr)),
+        }
+    }
+
+    /// Appends the output of a following command.
+    fn append(&mut self, other: CommandOutput) {
+        for (own, new) in [
+            (&mut self.stdout, other.stdout),
+            (&mut self.stderr, other.stderr),
+        ] {
+            if !new.is_empty() {
+                if !own.is_empty() {
+                    own.push('\n');
+                }
+                own.push_str(&new);
+            }
+        }
+        self.status = other.status;
+        self.payload = other.payload.or(self.payload.take());
+        self.notifications.extend(other.notifications);
+    }
+}
+
+/// Commands that either print their result or fail with a message.
+impl From<Result<String, String>> for CommandOutput {
+    fn from(result: Result<String, String>) -> Self {
+        match result {
+            Ok(stdout) => Self::ok(stdout),
+            Err(stderr) => Self::err(stderr),
+        }
+    }
+}
+
 pub static RESTRICTED_FILES: [&str; 5] = [
     ".dir_info",
     "info.json",
@@ -47,69 +199,349 @@ pub fn check_dir_info(path: &Path) -> bool {
     RESTRICTED_FILES.iter().any(|&file| path_str.contains(file))
 }
 
-/// Command manager that processes commands and processed to return appropriate outputs
+/// Command manager that processes commands and processed to return appropriate outputs.
+/// Commands can be chained with `&&` (run if the previous one succeeded), `||` (run if it failed)
+/// and `;` (run anyway). The chain is split out of quotes and after alias expansion, so aliases
+/// may chain commands.
 pub fn cmd_manager(
     parts: &[&str],
     current_dir: &PathBuf,
     root_dir: &PathBuf,
     prompter: &mut dyn UserPrompter,
 ) -> CommandResult {
+    subscribe_subsystems();
+    let commands = expand_chain(&parts.join(" "));
+    if let [(command, None)] = &commands[..] {
+        return run_command(command, current_dir, root_dir, prompter);
+    }
+
+    let mut dir = current_dir.clone();
+    let mut output = CommandOutput::default();
+    let mut operator = None;
+    for (command, next_operator) in &commands {
+        let previous = operator;
+        operator = *next_operator;
+        let skip = match previous {
+            Some("&&") => !output.is_success(),
+            Some("||") => output.is_success(),
+            _ => false,
+        };
+        if skip {
+            continue;
+        }
+        if command.trim().is_empty() {
+            // Like other shells, a line may end with `;`
+            if previous == Some(";") && next_operator.is_none() {
+                continue;
+            }
+            let near = next_operator.or(previous).unwrap_or(";");
+            output.append(CommandOutput::err(format!("syntax error near `{near}`")));
+            break;
+        }
+
+        match run_command(command, &dir, root_dir, prompter) {
+            CommandResult::Output(result) => output.append(result),
+            CommandResult::ChangeDirectory(new_dir, result) => {
+                dir = new_dir;
+                output.append(result);
+            }
+            // What came before is cleared, what comes after is shown on the cleared screen
+            CommandResult::Clear => {
+                output = CommandOutput {
+                    clear: true,
+                    ..Default::default()
+                }
+            }
+            CommandResult::Exit => return CommandResult::Exit,
+            CommandResult::NotFound => output.append(CommandOutput {
+                status: STATUS_NOT_FOUND,
+                stderr: format!(
+                    "{}: command not found",
+                    command.split_whitespace().next().unwrap_or_default()
+                ),
+                ..Default::default()
+            }),
+        }
+    }
+
+    if &dir != current_dir {
+        CommandResult::ChangeDirectory(dir, output)
+    } else {
+        CommandResult::Output(output)
+    }
+}
+
+/// Triggers and objectives follow the commands through the event bus. They subscribe once,
+/// before the first command runs, whichever shell runs it.
+fn subscribe_subsystems() {
+    static SUBSCRIBED: Once = Once::new();
+    SUBSCRIBED.call_once(|| {
+        events::subscribe(triggers::on_event);
+        events::subscribe(objectives::on_event);
+    });
+}
+
+/// Runs a line typed by the player, and publishes it with the answers to its prompts and
+/// what it printed, for recordings.
+pub fn run_line(
+    input: &str,
+    current_dir: &PathBuf,
+    root_dir: &PathBuf,
+    prompter: &mut dyn UserPrompter,
+) -> CommandResult {
+    let parts: Vec<&str> = input.split_whitespace().collect();
+    let mut recorder = RecordingPrompter::new(prompter);
+    let result = cmd_manager(&parts, current_dir, root_dir, &mut recorder);
+    let new_dir = match &result {
+        CommandResult::ChangeDirectory(new_dir, _) => new_dir,
+        _ => current_dir,
+    };
+    let line = LineEvent {
+        command: input.to_string(),
+        dir: home_relative(current_dir, root_dir),
+        answers: recorder.answers,
+        status: result.status(),
+        output: session::result_text(&result),
+        new_dir: home_relative(new_dir, root_dir),
+    };
+    events::publish(
+        &shell_history::current_user(),
+        root_dir,
+        ShellEvent::LineEntered(line),
+    );
+    result
+}
+
+/// Runs a single command, without `&&`, `||` or `;`, once its aliases are expanded.
+fn run_command(
+    command: &str,
+    current_dir: &PathBuf,
+    root_dir: &PathBuf,
+    prompter: &mut dyn UserPrompter,
+) -> CommandResult {
+    // Expand `$VAR`s and quotes
+    let words = expand_vars(command, current_dir, root_dir);
+    let parts: Vec<&str> = words.iter().map(String::as_str).collect();
     if parts.is_empty() {
         return CommandResult::NotFound;
     }
+    // The commands of a function are run, and recorded, each on their own
+    if let Some(body) = get_function(parts[0]) {
+        return function::call(
+            parts[0],
+            &body,
+            &parts[1..],
+            current_dir,
+            root_dir,
+            prompter,
+        );
+    }
+
+    let mut result = dispatch(&parts, current_dir, root_dir, prompter);
+    let event = CommandEvent {
+        parts: parts.iter().map(|part| part.to_string()).collect(),
+        dir: home_relative(current_dir, root_dir),
+        new_dir: match &result {
+            CommandResult::ChangeDirectory(new_dir, _) => home_relative(new_dir, root_dir),
+            _ => home_relative(current_dir, root_dir),
+        },
+        status: result.status(),
+    };
+    events::publish(
+        &shell_history::current_user(),
+        root_dir,
+        ShellEvent::CommandExecuted(event),
+    );
+    let notices = events::take_notices();
+    if let CommandResult::Output(output) | CommandResult::ChangeDirectory(_, output) = &mut result {
+        output.tell(notices);
+    }
+    audit(&parts, &result);
+    result
+}
+
+/// Records an audited command of a web request or a headless run, with its caller, in the
+/// audit log.
+fn audit(parts: &[&str], result: &CommandResult) {
+    let (Some(action), Some(caller)) = (audit::audited_command(parts), Caller::request()) else {
+        return;
+    };
+    let (success, message) = match result {
+        CommandResult::Output(output) | CommandResult::ChangeDirectory(_, output) => {
+            (output.is_success(), style::strip(&output.stderr))
+        }
+        _ => (result.status() == 0, String::new()),
+    };
+    audit::record(AuditEntry::new(
+        &caller.user,
+        caller.ip,
+        action,
+        &audit::command_detail(parts),
+        success,
+        &message,
+    ));
+}
+
+/// Runs a command once aliases and variables are expanded. Commands that need to know
+/// what the player has revealed or opened share one `Visibility`, read once.
+fn dispatch(
+    parts: &[&str],
+    current_dir: &PathBuf,
+    root_dir: &PathBuf,
+    prompter: &mut dyn UserPrompter,
+) -> CommandResult {
+    let output = |output: CommandOutput| CommandResult::Output(output);
+    let result = |result: Result<String, String>| CommandResult::Output(result.into());
 
     match parts[0] {
-        "echo" => CommandResult::Output(echo(&parts[1..])),
-        "whoami" => match get_current_username() {
-            Some(name) => CommandResult::Output(format!("Current user: {name}")),
-            None => {
-                CommandResult::Output("Current user: [Not logged in] (Default User)".to_string())
+        "echo" => output(CommandOutput::ok(echo(&parts[1..]))),
+        "whoami" => match Caller::current().user {
+            name if name == shell_history::GUEST_USER => output(CommandOutput::ok(
+                "Current user: [Not logged in] (Default User)",
+            )),
+            name => output(CommandOutput::ok(format!("Current user: {name}"))),
+        },
+        "go" => match go(&parts[1..], current_dir, root_dir, &Visibility::current()) {
+            Ok((new_dir, msg)) => CommandResult::ChangeDirectory(new_dir, CommandOutput::ok(msg)),
+            Err(e) => output(CommandOutput::err(e)),
+        },
+        "ls" => output(ls(
+            &parts[1..],
+            current_dir,
+            root_dir,
+            &Visibility::current(),
+        )),
+        "read" => match read(&parts[1..], current_dir, root_dir, &Visibility::current()) {
+            Ok(content) => {
+                let target = normalize_path(&current_dir.join(parts[1]));
+                let path = display_relative_path(&target, root_dir);
+                output(CommandOutput::ok(content).with_payload(Payload::Document { path }))
             }
+            Err(e) => output(CommandOutput::err(e)),
         },
-        "go" => {
-            let (new_dir, msg) = go(&parts[1..], current_dir, root_dir);
-            CommandResult::ChangeDirectory(new_dir, msg)
-        }
-        "ls" => CommandResult::Output(ls(&parts[1..], current_dir, root_dir)),
-        "read" => CommandResult::Output(read(&parts[1..], current_dir, root_dir)),
-        "copy" => {
-            let msg = copy::copy(&parts[1..], current_dir, root_dir, prompter);
-            CommandResult::Output(msg)
-        }
-        "tap" => {
-            let msg = tap(&parts[1..], current_dir, root_dir);
-            CommandResult::Output(msg)
-        }
-        "del" => {
-            let msg = del(&parts[1..], current_dir, root_dir, prompter);
-            CommandResult::Output(msg)
-        }
-        "whereami" => CommandResult::Output(whereami(current_dir, root_dir)),
+        "copy" => result(copy::copy(
+            &parts[1..],
+            current_dir,
+            root_dir,
+            prompter,
+            &Visibility::current(),
+        )),
+        "tap" => result(tap(
+            &parts[1..],
+            current_dir,
+            root_dir,
+            &Visibility::current(),
+        )),
+        "del" => result(del(
+            &parts[1..],
+            current_dir,
+            root_dir,
+            prompter,
+            &Visibility::current(),
+        )),
+        "whereami" => output(CommandOutput::ok(whereami(current_dir, root_dir))),
         "help" => {
             if parts.len() > 1 {
                 match help::get_command_help(parts[1]) {
-                    Some(msg) => CommandResult::Output(msg.to_string()),
-                    None => CommandResult::Output(format!("No help available for '{}'", parts[1])),
+                    Some(msg) => output(CommandOutput::ok(msg)),
+                    None => output(CommandOutput::err(format!(
+                        "No help available for '{}'",
+                        parts[1]
+                    ))),
                 }
             } else {
-                CommandResult::Output(help(""))
+                output(CommandOutput::ok(help("")))
+            }
+        }
+        "history" => result(history(&parts[1..], &shell_history::current_user())),
+        "alias" => output(alias(&parts[1..])),
+        "unalias" => output(unalias(&parts[1..])),
+        "function" => output(function::function(&parts[1..])),
+        "unfunction" => output(function::unfunction(&parts[1..])),
+        "set" => result(set(&parts[1..], current_dir, root_dir)),
+        "unset" => output(unset(&parts[1..])),
+        "env" => result(env(&parts[1..], current_dir, root_dir)),
+        "take" => result(take(
+            &parts[1..],
+            current_dir,
+            root_dir,
+            &Visibility::current(),
+        )),
+        "drop" => result(drop_item(&parts[1..], current_dir, root_dir)),
+        "inventory" => result(inventory(&parts[1..])),
+        "talk" => result(talk(
+            &parts[1..],
+            current_dir,
+            root_dir,
+            &Visibility::current(),
+        )),
+        "objectives" => result(objectives(&parts[1..], root_dir)),
+        "run" => {
+            let (result, new_dir) = run(&parts[1..], current_dir, root_dir, &Visibility::current());
+            if &new_dir != current_dir {
+                CommandResult::ChangeDirectory(new_dir, result)
+            } else {
+                CommandResult::Output(result)
             }
         }
         "clear" => CommandResult::Clear,
         "exit" => match exit(prompter) {
             (true, _) => CommandResult::Exit,
-            (false, msg) => CommandResult::Output(msg),
+            (false, msg) => output(CommandOutput::ok(msg)),
         },
-        "restore" => CommandResult::Output(restore::restore(&parts[1..], root_dir, prompter)),
-        "save" => CommandResult::Output(save::save(&parts[1..], root_dir)),
-        "solve" => {
-            let msg = solve(&parts[1..], current_dir, root_dir, prompter);
-            CommandResult::Output(msg)
-        }
-        "unlock" => {
-            let msg = unlock(&parts[1..], current_dir, root_dir, prompter);
-            CommandResult::Output(msg)
-        }
+        "restore" => output(restore::restore(&parts[1..], root_dir, prompter)),
+        "save" => output(save::save(&parts[1..], root_dir)),
+        "solve" => output(solve(
+            &parts[1..],
+            current_dir,
+            root_dir,
+            prompter,
+            &Visibility::current(),
+        )),
+        "unlock" => output(unlock(
+            &parts[1..],
+            current_dir,
+            root_dir,
+            prompter,
+            &Visibility::current(),
+        )),
         _ => CommandResult::NotFound,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::prompt::DummyPrompter;
+    use crate::utils::test_utils::setup_test_dir;
+
+    #[test]
+    fn test_chained_commands() {
+        let (_temp_dir, root_path) = setup_test_dir(true);
+        let run = |line: &str| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            cmd_manager(&parts, &root_path, &root_path, &mut DummyPrompter)
+        };
+
+        match run("go subdir1 && ls") {
+            CommandResult::ChangeDirectory(dir, output) => {
+                assert_eq!(dir, root_path.join("subdir1"));
+                assert!(output.is_success());
+                assert!(matches!(output.payload, Some(Payload::Listing { .. })));
+            }
+            _ => panic!("expected a directory change"),
+        }
+
+        let result = run("go nowhere && echo unreachable");
+        assert_eq!(result.status(), 1);
+        let CommandResult::Output(output) = result else {
+            panic!("expected output");
+        };
+        assert!(output.stderr.starts_with("go: "));
+        assert!(output.stdout.is_empty());
+
+        assert_eq!(run("nope || echo fallback").status(), 0);
+        assert_eq!(run("echo hi && nope").status(), STATUS_NOT_FOUND);
+        assert_eq!(run("nope").status(), STATUS_NOT_FOUND);
+    }
+}
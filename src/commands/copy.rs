@@ -2,6 +2,7 @@ use super::argparser::ArgParser;
 use super::cmds::normalize_path;
 use super::display_relative_path;
 use crate::commands::cmds::check_dir_info;
+use crate::metainfo::hidden::Visibility;
 use crate::metainfo::info_reader::*;
 use crate::metainfo::lock_perm;
 use crate::metainfo::valid_sekai::create_dir_info;
@@ -371,7 +372,8 @@ pub fn copy(
     current_dir: &Path,
     root_dir: &Path,
     prompter: &mut dyn UserPrompter,
-) -> String {
+    visibility: &Visibility,
+) -> Result<String, String> {
     let valid_flags = vec![
         "-x",
         "--cut",
@@ -405,7 +407,7 @@ pub fn copy(
                 .collect();
 
             if paths.len() != 2 {
-                return "copy: Requires exactly two paths (source and destination)".to_string();
+                return Err("copy: Requires exactly two paths (source and destination)".to_string());
             }
 
             // handle source and destination paths, and flags
@@ -417,58 +419,56 @@ pub fn copy(
             // If restricted file/directory used in paths, return error
             for pth in [&src, &dest] {
                 if check_dir_info(pth) {
-                    return "copy: Cannot copy/refer restricted file or directory. Operation Not Allowed."
-                        .to_string();
+                    return Err(
+                        "copy: Cannot copy/refer restricted file or directory. Operation Not Allowed."
+                            .to_string(),
+                    );
                 }
             }
 
             // Prompt for force confirmation
             if force && !prompter.confirm("Are you sure you want to force overwrite files?") {
-                return "Operation of force overwriting cancelled. No files copied/moved."
-                    .to_string();
+                return Ok(
+                    "Operation of force overwriting cancelled. No files copied/moved.".to_string(),
+                );
             }
 
             // Validate paths and perform operations
-            match validate_paths(src, dest, current_dir, root_dir) {
-                Ok((src_path, dest_path)) => {
-                    // Operation allowed only if paths are not locked
-                    for pth in [&src_path, &dest_path] {
-                        if let Err(e) = lock_perm::operation_locked_perm(
-                            pth,
-                            "copy",
-                            "Cannot copy/move locked file/directory. Unlock it first.",
-                        ) {
-                            return e;
-                        }
-                    }
-                    let result = if cut {
-                        move_item(&src_path, &dest_path, root_dir, recursive, force)
-                    } else if src_path.is_dir() && !recursive {
-                        Err(Error::other("Cannot copy directory without -r flag"))
-                    } else if src_path.is_dir() {
-                        copy_directory(&src_path, &dest_path, root_dir, force, false)
-                    } else {
-                        copy_file(&src_path, &dest_path, root_dir, force, false).map(|_| {
-                            format!(
-                                "Copied {} to {}",
-                                display_relative_path(&src_path, root_dir),
-                                display_relative_path(&dest_path, root_dir)
-                            )
-                        })
-                    };
-
-                    match result {
-                        Ok(msg) => msg,
-                        Err(e) => format!("copy: {e}"),
-                    }
-                }
-                Err(e) => e,
+            let (src_path, dest_path) = validate_paths(src, dest, current_dir, root_dir)?;
+            // Operation allowed only if paths are not locked
+            for pth in [&src_path, &dest_path] {
+                lock_perm::operation_locked_perm(
+                    pth,
+                    root_dir,
+                    &visibility.progress,
+                    "copy",
+                    "Cannot copy/move locked file/directory. Unlock it first.",
+                )?;
             }
+            let result = if cut {
+                move_item(&src_path, &dest_path, root_dir, recursive, force)
+            } else if src_path.is_dir() && !recursive {
+                Err(Error::other("Cannot copy directory without -r flag"))
+            } else if src_path.is_dir() {
+                copy_directory(&src_path, &dest_path, root_dir, force, false)
+            } else {
+                copy_file(&src_path, &dest_path, root_dir, force, false).map(|_| {
+                    format!(
+                        "Copied {} to {}",
+                        display_relative_path(&src_path, root_dir),
+                        display_relative_path(&dest_path, root_dir)
+                    )
+                })
+            };
+
+            result.map_err(|e| format!("copy: {e}"))
         }
         Err(e) => match &e[..] {
-            "help" => HELP_TXT.to_string(),
-            "unknown" => "copy: unknown flag\nTry 'help copy' for more information.".to_string(),
-            _ => "Error parsing arguments. Try 'help copy' for more information.".to_string(),
+            "help" => Ok(HELP_TXT.to_string()),
+            "unknown" => {
+                Err("copy: unknown flag\nTry 'help copy' for more information.".to_string())
+            }
+            _ => Err("Error parsing arguments. Try 'help copy' for more information.".to_string()),
         },
     }
 }
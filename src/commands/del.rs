@@ -1,6 +1,7 @@
 use super::argparser::ArgParser;
 use super::cmds::{check_dir_info, normalize_path};
 use super::display_relative_path;
+use crate::metainfo::hidden::Visibility;
 use crate::metainfo::info_reader::del_obj_from_info;
 use crate::metainfo::lock_perm;
 use crate::utils::{log, prompt::UserPrompter};
@@ -23,19 +24,19 @@ Examples:
 "#;
 
 /// Delete a file at the given path
-pub fn delete_file(path: &Path, root_dir: &Path) -> String {
+pub fn delete_file(path: &Path, root_dir: &Path) -> Result<String, String> {
     if !path.exists() {
-        return format!(
+        return Err(format!(
             "del: {}: No such file",
             display_relative_path(path, root_dir)
-        );
+        ));
     }
 
     if path.is_dir() {
-        return format!(
+        return Err(format!(
             "del: {}: Is a directory (use -d flag)",
             display_relative_path(path, root_dir)
-        );
+        ));
     }
 
     match std::fs::remove_file(path) {
@@ -45,25 +46,32 @@ pub fn delete_file(path: &Path, root_dir: &Path) -> String {
             let result = del_obj_from_info(path, obj_name).map_err(|e| e.to_string()); // Convert InfoError to String
 
             log::log_result("del", result, "Deleting object from info.json");
-            format!("Deleted file: {}", display_relative_path(path, root_dir))
+            Ok(format!(
+                "Deleted file: {}",
+                display_relative_path(path, root_dir)
+            ))
         }
-        Err(e) => format!("del: {}: {}", display_relative_path(path, root_dir), e),
+        Err(e) => Err(format!(
+            "del: {}: {}",
+            display_relative_path(path, root_dir),
+            e
+        )),
     }
 }
 
 /// Delete a directory at the given path
-pub fn delete_directory(path: &Path, root_dir: &Path, force: bool) -> String {
+pub fn delete_directory(path: &Path, root_dir: &Path, force: bool) -> Result<String, String> {
     if !path.exists() {
-        return format!(
+        return Err(format!(
             "del: {}: No such directory",
             display_relative_path(path, root_dir)
-        );
+        ));
     }
     if !path.is_dir() {
-        return format!(
+        return Err(format!(
             "del: {}: Not a directory",
             display_relative_path(path, root_dir)
-        );
+        ));
     }
 
     // Check if directory only contains .dir_info
@@ -85,12 +93,16 @@ pub fn delete_directory(path: &Path, root_dir: &Path, force: bool) -> String {
                 let result = del_obj_from_info(path, obj_name).map_err(|e| e.to_string()); // Convert InfoError to String
 
                 log::log_result("del", result, "Deleting object from info.json");
-                format!(
+                Ok(format!(
                     "Deleted directory: {}",
                     display_relative_path(path, root_dir)
-                )
+                ))
             }
-            Err(e) => format!("del: {}: {}", display_relative_path(path, root_dir), e),
+            Err(e) => Err(format!(
+                "del: {}: {}",
+                display_relative_path(path, root_dir),
+                e
+            )),
         };
     }
 
@@ -102,11 +114,15 @@ pub fn delete_directory(path: &Path, root_dir: &Path, force: bool) -> String {
     };
 
     match result {
-        Ok(_) => format!(
+        Ok(_) => Ok(format!(
             "Deleted directory: {}",
             display_relative_path(path, root_dir)
-        ),
-        Err(e) => format!("del: {}: {}", display_relative_path(path, root_dir), e),
+        )),
+        Err(e) => Err(format!(
+            "del: {}: {}",
+            display_relative_path(path, root_dir),
+            e
+        )),
     }
 }
 
@@ -148,7 +164,8 @@ pub fn del(
     current_dir: &Path,
     root_dir: &Path,
     prompter: &mut dyn UserPrompter,
-) -> String {
+    visibility: &Visibility,
+) -> Result<String, String> {
     let mut parser = ArgParser::new(&["-d", "--dir", "-f", "--force"]);
 
     let args_string: Vec<String> = args.iter().map(|s| s.to_string()).collect();
@@ -170,45 +187,45 @@ pub fn del(
                 .unwrap_or(&"");
 
             if destination.is_empty() {
-                return "del: No destination specified. Use 'del --help' for usage.".to_string();
+                return Err(
+                    "del: No destination specified. Use 'del --help' for usage.".to_string()
+                );
             }
             if check_dir_info(Path::new(destination)) {
-                return "del: Cannot delete/refer restricted file or directory. Operation Not Allowed."
-                    .to_string();
+                return Err(
+                    "del: Cannot delete/refer restricted file or directory. Operation Not Allowed."
+                        .to_string(),
+                );
             }
 
             if !prompter.confirm(&format!(
                 "Are you sure you want to delete '{destination}'? This action cannot be undone."
             )) {
-                return "Deletion cancelled by user.".to_string();
+                return Ok("Deletion cancelled by user.".to_string());
             }
 
             let destination_path = Path::new(destination);
-            match validate_deletion_path(destination_path, current_dir, root_dir) {
-                Ok(full_path) => {
-                    // Operation permitted only if not locked
-                    if let Err(e) = lock_perm::operation_locked_perm(
-                        &full_path,
-                        "del",
-                        "Cannot delete locked file/directory. Unlock it first.",
-                    ) {
-                        return e;
-                    }
-
-                    let force = args.contains(&"-f") || args.contains(&"--force");
-                    if args.contains(&"-d") || args.contains(&"--dir") || full_path.is_dir() {
-                        delete_directory(&full_path, root_dir, force)
-                    } else {
-                        delete_file(&full_path, root_dir)
-                    }
-                }
-                Err(e) => e,
+            let full_path = validate_deletion_path(destination_path, current_dir, root_dir)?;
+            // Operation permitted only if not locked
+            lock_perm::operation_locked_perm(
+                &full_path,
+                root_dir,
+                &visibility.progress,
+                "del",
+                "Cannot delete locked file/directory. Unlock it first.",
+            )?;
+
+            let force = args.contains(&"-f") || args.contains(&"--force");
+            if args.contains(&"-d") || args.contains(&"--dir") || full_path.is_dir() {
+                delete_directory(&full_path, root_dir, force)
+            } else {
+                delete_file(&full_path, root_dir)
             }
         }
         Err(e) => match &e[..] {
-            "help" => HELP_TXT.to_string(),
-            "unknown" => "del: unknown flag\nTry 'help del' for more information.".to_string(),
-            _ => "Error parsing arguments. Try 'help del' for more information.".to_string(),
+            "help" => Ok(HELP_TXT.to_string()),
+            "unknown" => Err("del: unknown flag\nTry 'help del' for more information.".to_string()),
+            _ => Err("Error parsing arguments. Try 'help del' for more information.".to_string()),
         },
     }
 }
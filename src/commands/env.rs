@@ -0,0 +1,95 @@
+use super::cmds::CommandOutput;
+use crate::utils::log;
+use crate::utils::variables::{BUILTIN_VARS, get_builtin, get_vars, set_var, unset_var};
+use std::path::Path;
+
+pub const HELP_TXT: &str = r#"
+Usage: env
+
+Lists all variables and their values. Use a variable in any command with $NAME or ${NAME}.
+
+Built-in variables (read-only):
+- HOME  : Your home location.
+- PWD   : Your current location.
+- USER  : Your username.
+- LEVEL : The level you are currently in, if any.
+"#;
+
+pub const SET_HELP_TXT: &str = r#"
+Usage: set <name> <value> | set <name>=<value>
+
+Sets a variable for this session. Use it later with $<name>.
+- set               : List all variables.
+- set KEY 42        : Set KEY to 42.
+- set GREETING=hi   : Set GREETING to hi.
+"#;
+
+pub const UNSET_HELP_TXT: &str = r#"
+Usage: unset <name>...
+
+Removes variables set with `set`.
+"#;
+
+pub fn env(args: &[&str], current_dir: &Path, root_dir: &Path) -> Result<String, String> {
+    if args.iter().any(|arg| *arg == "-h" || *arg == "--help") {
+        return Ok(HELP_TXT.to_string());
+    }
+    if !args.is_empty() {
+        return Err("env: too many arguments\nTry 'help env' for more information.".to_string());
+    }
+
+    let mut lines: Vec<String> = BUILTIN_VARS
+        .iter()
+        .map(|name| {
+            let value = get_builtin(name, current_dir, root_dir).unwrap_or_default();
+            format!("{name}={value}")
+        })
+        .collect();
+    lines.extend(
+        get_vars()
+            .iter()
+            .map(|(name, value)| format!("{name}={value}")),
+    );
+    Ok(lines.join("\n"))
+}
+
+pub fn set(args: &[&str], current_dir: &Path, root_dir: &Path) -> Result<String, String> {
+    let (name, value) = match args {
+        [] => return env(args, current_dir, root_dir),
+        ["-h" | "--help", ..] => return Ok(SET_HELP_TXT.to_string()),
+        [assignment] => match assignment.split_once('=') {
+            Some((name, value)) => (name.to_string(), value.to_string()),
+            None => (assignment.to_string(), String::new()),
+        },
+        [name, value @ ..] => (name.to_string(), value.join(" ")),
+    };
+
+    set_var(&name, &value).map_err(|e| format!("set: {e}"))?;
+    log::log_debug("set", &format!("{name}={value}"));
+    Ok(String::new())
+}
+
+pub fn unset(args: &[&str]) -> CommandOutput {
+    if args.is_empty() {
+        return CommandOutput::err(
+            "unset: missing variable name\nTry 'help unset' for more information.",
+        );
+    }
+    if args.iter().any(|arg| *arg == "-h" || *arg == "--help") {
+        return CommandOutput::ok(UNSET_HELP_TXT);
+    }
+
+    let errors = args
+        .iter()
+        .filter_map(|name| {
+            if BUILTIN_VARS.contains(name) {
+                Some(format!("unset: {name}: read-only variable"))
+            } else if !unset_var(name) {
+                Some(format!("unset: {name}: not set"))
+            } else {
+                None
+            }
+        })
+        .collect();
+    CommandOutput::lines(Vec::new(), errors)
+}
@@ -0,0 +1,168 @@
+use super::cmds::{CommandOutput, CommandResult, cmd_manager};
+use super::run::ScriptDepth;
+use crate::utils::alias::{
+    get_function, get_functions, is_valid_name, remove_function, set_function,
+};
+use crate::utils::log;
+use crate::utils::prompt::UserPrompter;
+use crate::utils::tab_completion::COMMANDS;
+use std::path::{Path, PathBuf};
+
+pub const HELP_TXT: &str = r#"
+Usage: function [name ['commands']]
+
+Defines your own commands, running other ones. Functions are saved like aliases.
+- function                         : List all functions.
+- function <name>                  : Show the function <name>.
+- function up 'go ..; ls $1'       : Make `up` go up and list the place given to it.
+
+In the commands, $1 to $9 are the arguments given to the function, and $@ all of them.
+Separate commands with `;`, `&&` or `||`. Use `unfunction` to remove a function.
+"#;
+
+pub const UNFUNCTION_HELP_TXT: &str = r#"
+Usage: unfunction <name>...
+
+Removes functions.
+"#;
+
+fn format_function(name: &str, body: &str) -> String {
+    format!("function {name} '{body}'")
+}
+
+pub fn function(args: &[&str]) -> CommandOutput {
+    match args {
+        ["-h" | "--help"] => CommandOutput::ok(HELP_TXT),
+        [] => {
+            let functions = get_functions();
+            if functions.is_empty() {
+                return CommandOutput::ok(
+                    "No functions defined. Try 'help function' for more information.",
+                );
+            }
+            CommandOutput::ok(
+                functions
+                    .iter()
+                    .map(|(name, body)| format_function(name, body))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            )
+        }
+        [name] => match get_function(name) {
+            Some(body) => CommandOutput::ok(format_function(name, &body)),
+            None => CommandOutput::err(format!("function: {name}: not found")),
+        },
+        [name, body @ ..] => {
+            if !is_valid_name(name) {
+                return CommandOutput::err(format!("function: '{name}': invalid function name"));
+            }
+            if COMMANDS.iter().any(|spec| spec.name == *name) {
+                return CommandOutput::err(format!("function: '{name}': is a command"));
+            }
+            let body = body.join(" ");
+            log::log_info("function", &format!("Defined function {name}"));
+            set_function(name, body.trim());
+            CommandOutput::ok("")
+        }
+    }
+}
+
+pub fn unfunction(args: &[&str]) -> CommandOutput {
+    match args {
+        [] => CommandOutput::err(
+            "unfunction: missing function name\nTry 'help unfunction' for more information.",
+        ),
+        ["-h" | "--help"] => CommandOutput::ok(UNFUNCTION_HELP_TXT),
+        names => {
+            let errors = names
+                .iter()
+                .filter(|name| !remove_function(name))
+                .map(|name| format!("unfunction: {name}: not found"))
+                .collect();
+            CommandOutput::lines(Vec::new(), errors)
+        }
+    }
+}
+
+/// Quotes an argument so that it stays one word, as given, once put in a command line.
+fn quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && !arg
+            .chars()
+            .any(|c| c.is_whitespace() || "'\"\\$&|;".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r#"'"'"'"#))
+    }
+}
+
+/// Puts the arguments in place of `$1` to `$9` and `$@`. Missing ones are left out.
+fn substitute(body: &str, args: &[&str]) -> String {
+    let mut line = String::new();
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some('$')) => {
+                line.push_str("\\$");
+                chars.next();
+            }
+            ('$', Some('@')) => {
+                chars.next();
+                line.push_str(
+                    &args
+                        .iter()
+                        .map(|arg| quote(arg))
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                );
+            }
+            ('$', Some(&digit @ '1'..='9')) => {
+                chars.next();
+                let index = digit as usize - '1' as usize;
+                line.push_str(&args.get(index).map(|arg| quote(arg)).unwrap_or_default());
+            }
+            _ => line.push(c),
+        }
+    }
+    line
+}
+
+/// Runs a function with its arguments, like a command line typed by the player.
+pub fn call(
+    name: &str,
+    body: &str,
+    args: &[&str],
+    current_dir: &Path,
+    root_dir: &Path,
+    prompter: &mut dyn UserPrompter,
+) -> CommandResult {
+    let Some(_depth) = ScriptDepth::enter() else {
+        return CommandResult::Output(CommandOutput::err(format!(
+            "{name}: functions are nested too deeply"
+        )));
+    };
+    let line = substitute(body, args);
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    cmd_manager(
+        &parts,
+        &PathBuf::from(current_dir),
+        &PathBuf::from(root_dir),
+        prompter,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_substitute() {
+        assert_eq!(
+            substitute("go $1 && ls $2; echo $@ \\$1", &["room 1", "-a"]),
+            "go 'room 1' && ls -a; echo 'room 1' -a \\$1"
+        );
+        assert_eq!(substitute("read $3", &[]), "read ");
+        assert_eq!(quote("it's"), r#"'it'"'"'s'"#);
+    }
+}
@@ -1,8 +1,13 @@
 use super::argparser::ArgParser;
 use super::cmds::check_dir_info;
 use super::whereami::display_relative_path;
+use crate::metainfo::hidden::Visibility;
 use crate::metainfo::{info_reader, lock_perm};
+use crate::utils::events::{self, ShellEvent};
+use crate::utils::find_root::home_relative;
 use crate::utils::log;
+use crate::utils::shell_history::current_user;
+use crate::utils::style::{self, Color};
 use std::path::{Path, PathBuf};
 
 pub const HELP_TXT: &str = r#"
@@ -16,16 +21,19 @@ Navigate to different directories:
 
 "#;
 
-pub fn navigate(destination: &str, current_dir: &PathBuf, root_dir: &Path) -> (PathBuf, String) {
+/// Finds the room at `destination`, and what the player is told on entering it.
+pub fn navigate(
+    destination: &str,
+    current_dir: &Path,
+    root_dir: &Path,
+    visibility: &Visibility,
+) -> Result<(PathBuf, String), String> {
     let new_path = match destination {
         "HOME" | "home" => root_dir.to_path_buf(),
         ".." | "back" => {
             if current_dir == root_dir {
                 log::log_warning("go", "Attempted to go back from root directory");
-                return (
-                    current_dir.clone(),
-                    "You are at the root. Cannot go back further".to_string(),
-                );
+                return Err("You are at the root. Cannot go back further".to_string());
             }
             current_dir.parent().unwrap().to_path_buf()
         }
@@ -37,12 +45,9 @@ pub fn navigate(destination: &str, current_dir: &PathBuf, root_dir: &Path) -> (P
                         "Attempted to go to/refers a restricted directory: {destination}. Operation Not Permitted."
                     ),
                 );
-                return (
-                    current_dir.clone(),
-                    format!(
-                        "go: Attempted to go to/refers a restricted directory: {destination}. Operation Not Permitted"
-                    ),
-                );
+                return Err(format!(
+                    "go: Attempted to go to/refers a restricted directory: {destination}. Operation Not Permitted"
+                ));
             } else {
                 current_dir.join(destination)
             }
@@ -57,10 +62,7 @@ pub fn navigate(destination: &str, current_dir: &PathBuf, root_dir: &Path) -> (P
                 "go",
                 &format!("No such directory, path: {}", new_path.display()),
             );
-            return (
-                current_dir.clone(),
-                format!("go: {destination}: No such directory"),
-            );
+            return Err(format!("go: {destination}: No such directory"));
         }
     };
 
@@ -73,10 +75,19 @@ pub fn navigate(destination: &str, current_dir: &PathBuf, root_dir: &Path) -> (P
                 canonical_path.display()
             ),
         );
-        return (
-            current_dir.clone(),
-            "go: Access denied: Cannot go outside root".to_string(),
+        return Err("go: Access denied: Cannot go outside root".to_string());
+    }
+
+    // Hidden places stay unknown until they are revealed
+    if visibility.is_hidden(&canonical_path, root_dir) {
+        log::log_info(
+            "go",
+            &format!(
+                "Attempted to go to a hidden path: {}",
+                canonical_path.display()
+            ),
         );
+        return Err(format!("go: {destination}: No such directory"));
     }
 
     if !canonical_path.is_dir() {
@@ -88,10 +99,9 @@ pub fn navigate(destination: &str, current_dir: &PathBuf, root_dir: &Path) -> (P
                     canonical_path.display()
                 ),
             );
-            return (
-                current_dir.clone(),
-                format!("go: {destination}: Is a file (try 'read {destination}')"),
-            );
+            return Err(format!(
+                "go: {destination}: Is a file (try 'read {destination}')"
+            ));
         }
 
         log::log_warning(
@@ -101,39 +111,46 @@ pub fn navigate(destination: &str, current_dir: &PathBuf, root_dir: &Path) -> (P
                 canonical_path.display()
             ),
         );
-        return (
-            current_dir.clone(),
-            format!("go: {destination}: Not a directory"),
-        );
+        return Err(format!("go: {destination}: Not a directory"));
     }
 
     // Check if directory is locked
-    if let Err(e) = lock_perm::operation_locked_perm(
+    lock_perm::operation_locked_perm(
         &canonical_path,
+        root_dir,
+        &visibility.progress,
         "go",
         "Cannot enter locked directory. Unlock it first",
-    ) {
-        return (current_dir.clone(), e);
-    }
+    )?;
 
     // Get directory info if available
     let info_path = canonical_path.join(".dir_info/info.json");
+    let location = style::colored(
+        &display_relative_path(&canonical_path, root_dir),
+        Color::Cyan,
+    );
     let message = match info_reader::read_validate_info(&info_path) {
         Ok(info) => format!(
-            "You have entered {}\n\nAbout:\n{}",
-            display_relative_path(&canonical_path, root_dir),
+            "You have entered {location}\n\n{}\n{}",
+            style::bold("About:"),
             info.about.trim_matches('"')
         ),
         Err(_) => format!(
-            "You have entered {}\n\nNo additional information available.",
-            display_relative_path(&canonical_path, root_dir)
+            "You have entered {location}\n\n{}",
+            style::dim("No additional information available.")
         ),
     };
 
-    (canonical_path, message)
+    Ok((canonical_path, message))
 }
 
-pub fn go(args: &[&str], current_dir: &PathBuf, root_dir: &Path) -> (PathBuf, String) {
+/// Returns the room to go to and its description, or why the player cannot go there.
+pub fn go(
+    args: &[&str],
+    current_dir: &Path,
+    root_dir: &Path,
+    visibility: &Visibility,
+) -> Result<(PathBuf, String), String> {
     let mut parser = ArgParser::new(&[]);
 
     let args_string: Vec<String> = args.iter().map(|s| s.to_string()).collect();
@@ -153,31 +170,34 @@ pub fn go(args: &[&str], current_dir: &PathBuf, root_dir: &Path) -> (PathBuf, St
             let pos_args = parser.get_positional_args();
 
             if pos_args.is_empty() {
-                return (
-                    current_dir.clone(),
+                return Err(
                     "go: missing directory operand\nTry 'help go' for more information."
                         .to_string(),
                 );
             }
             if pos_args.len() > 1 {
-                return (
-                    current_dir.clone(),
+                return Err(
                     "go: too many arguments\nTry 'help go' for more information.".to_string(),
                 );
             }
             let target = pos_args[0].as_str();
-            navigate(target, current_dir, root_dir)
+            let (new_dir, message) = navigate(target, current_dir, root_dir, visibility)?;
+            if new_dir != current_dir {
+                events::publish(
+                    &current_user(),
+                    root_dir,
+                    ShellEvent::DirectoryChanged {
+                        from: home_relative(current_dir, root_dir),
+                        to: home_relative(&new_dir, root_dir),
+                    },
+                );
+            }
+            Ok((new_dir, message))
         }
         Err(e) => match &e[..] {
-            "help" => (current_dir.clone(), HELP_TXT.to_string()),
-            "unknown" => (
-                current_dir.clone(),
-                "go: unknown flag\nTry 'help go' for more information.".to_string(),
-            ),
-            _ => (
-                current_dir.clone(),
-                "Error parsing arguments. Try 'help go' for more information.".to_string(),
-            ),
+            "help" => Ok((current_dir.to_path_buf(), HELP_TXT.to_string())),
+            "unknown" => Err("go: unknown flag\nTry 'help go' for more information.".to_string()),
+            _ => Err("Error parsing arguments. Try 'help go' for more information.".to_string()),
         },
     }
 }
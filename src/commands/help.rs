@@ -1,4 +1,5 @@
 use super::*;
+use crate::utils::style;
 
 pub fn get_command_help(command: &str) -> Option<&'static str> {
     match command {
@@ -16,6 +17,20 @@ pub fn get_command_help(command: &str) -> Option<&'static str> {
         "clear" => Some("clear: Clears the screen."),
         "restore" => Some(restore::HELP_TEXT),
         "save" => Some(save::HELP_TEXT),
+        "history" => Some(history::HELP_TXT),
+        "alias" => Some(alias::HELP_TXT),
+        "unalias" => Some(alias::UNALIAS_HELP_TXT),
+        "function" => Some(function::HELP_TXT),
+        "unfunction" => Some(function::UNFUNCTION_HELP_TXT),
+        "set" => Some(env::SET_HELP_TXT),
+        "unset" => Some(env::UNSET_HELP_TXT),
+        "env" => Some(env::HELP_TXT),
+        "run" => Some(run::HELP_TXT),
+        "inventory" => Some(inventory::HELP_TXT),
+        "take" => Some(inventory::TAKE_HELP_TXT),
+        "drop" => Some(inventory::DROP_HELP_TXT),
+        "talk" => Some(talk::HELP_TXT),
+        "objectives" => Some(objectives::HELP_TXT),
         _ => Some("No help available for this command. Check if the command is valid."),
     }
 }
@@ -37,13 +52,45 @@ Welcome to DBD Deemak Help. You can use the following commands:
 - help: Displays this help message.
 - exit: Exits the program.
 - clear: Clears the screen.
+- history: Shows the commands you have run. Use !! or !<n> to repeat one.
+- alias <name>='<command>': Creates a shortcut for a command. Use unalias to remove it.
+- function <name> '<commands>': Defines a command running others, with $1, $2... as arguments.
+- set <name> <value>: Sets a variable. Use it in any command as $<name>.
+- unset <name>: Removes a variable.
+- env: Lists all variables, including $HOME, $PWD, $USER and $LEVEL.
+- run <script>: Runs the commands in a script file.
 - restore: Restores the Sekai to last saved version or to starting point( if forced)
 - save: Saves your current progress of the Sekai.
 -solve: after completing a level, use this command to submit your answer and obtain the flag.
 -unlock: use flag to unlock levels and chests.
+- take <item>: Picks up an item. Some locks open with an item instead of a flag.
+- drop <item>: Puts down an item you carry.
+- inventory: Lists the items you carry.
+- talk <someone>: Talks to someone. Answer their questions with `talk <someone> <number>`.
+- objectives: Lists the objectives of the sekai and the ones you completed.
 - help <command>: Displays help for the specified command.
+
+Chain commands with `&&` to run the next one only if the previous succeeded,
+with `||` to run it only if the previous failed, e.g. `go room_1 && ls`,
+or with `;` to run it anyway.
 "#;
-        help_text.to_string()
+        // Highlight the command of each `- command: description` entry
+        help_text
+            .lines()
+            .map(|line| {
+                match line
+                    .strip_prefix('-')
+                    .and_then(|entry| entry.split_once(':'))
+                {
+                    Some((usage, description)) => {
+                        format!("-{}:{description}", style::bold(usage))
+                    }
+                    None if line.starts_with("Welcome") => style::bold(line),
+                    None => line.to_string(),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
     } else {
         get_command_help(cmd)
             .unwrap_or("No help available for this command.")
@@ -0,0 +1,58 @@
+use super::argparser::ArgParser;
+use crate::utils::log;
+use crate::utils::shell_history::{clear_user_history, get_user_history};
+
+pub const HELP_TXT: &str = r#"
+Usage: history [n] [-c | --clear]
+
+Shows the commands you have run in this sekai, oldest first.
+- history         : Show the whole history.
+- history <n>     : Show only the last n commands.
+- history -c      : Clear your history.
+
+History can be reused in any command:
+- !!              : The last command.
+- !<n>            : Command number n, as shown by `history`.
+- !-<n>           : The n-th last command.
+- !<text>         : The most recent command starting with <text>.
+Press Ctrl+R in the shell to search your history.
+"#;
+
+pub fn history(args: &[&str], user: &str) -> Result<String, String> {
+    let mut parser = ArgParser::new(&["-c", "--clear"]);
+    let args_string: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+
+    match parser.parse(&args_string, "history") {
+        Ok(_) => {
+            if parser.has_flag("-c") || parser.has_flag("--clear") {
+                clear_user_history(user);
+                log::log_info("history", &format!("Cleared history of {user}"));
+                return Ok("History cleared.".to_string());
+            }
+
+            let pos_args = parser.get_positional_args();
+            let entries = get_user_history(user);
+            let count = match pos_args.as_slice() {
+                [] => entries.len(),
+                [n] => match n.parse::<usize>() {
+                    Ok(n) => n.min(entries.len()),
+                    Err(_) => return Err(format!("history: {n}: numeric argument required")),
+                },
+                _ => return Err("history: too many arguments".to_string()),
+            };
+
+            let width = entries.len().to_string().len();
+            Ok(entries
+                .iter()
+                .enumerate()
+                .skip(entries.len() - count)
+                .map(|(i, cmd)| format!("{:>width$}  {cmd}", i + 1))
+                .collect::<Vec<_>>()
+                .join("\n"))
+        }
+        Err(e) => match &e[..] {
+            "help" => Ok(HELP_TXT.to_string()),
+            _ => Err("history: unknown flag\nTry 'help history' for more information.".to_string()),
+        },
+    }
+}
@@ -0,0 +1,171 @@
+use super::cmds::{check_dir_info, normalize_path};
+use super::display_relative_path;
+use crate::metainfo::hidden::Visibility;
+use crate::metainfo::lock_perm::operation_locked_perm;
+use crate::utils::find_root::home_relative;
+use crate::utils::inventory::{Inventory, is_collectible};
+use crate::utils::shell_history::current_user;
+use crate::utils::{log, style};
+use std::path::{Path, PathBuf};
+
+pub const HELP_TXT: &str = r#"
+Usage: inventory [-a | --all]
+
+Lists the items you carry. Pick items up with `take` and put them down with `drop`.
+- inventory -a | --all  : Also lists the items you dropped, and where.
+"#;
+
+pub const TAKE_HELP_TXT: &str = r#"
+Usage: take <item>
+
+Picks up an item and puts it in your inventory. Only some objects can be taken.
+Some locks open with an item instead of a flag: carry it and use `unlock`.
+
+Examples:
+- take key
+- take room_3/key
+"#;
+
+pub const DROP_HELP_TXT: &str = r#"
+Usage: drop <item>
+
+Puts down an item you carry in the current room.
+"#;
+
+/// Resolves the single item argument of `take`/`drop` to its room and name.
+fn item_target(
+    cmd: &str,
+    args: &[&str],
+    current_dir: &Path,
+    root_dir: &Path,
+) -> Result<(PathBuf, String), String> {
+    let [arg] = args else {
+        return Err(format!(
+            "{cmd}: expected exactly one item\nTry 'help {cmd}' for more information."
+        ));
+    };
+    let target = normalize_path(&current_dir.join(arg));
+    let (Some(dir), Some(name)) = (target.parent(), target.file_name()) else {
+        return Err(format!("{cmd}: {arg}: not an item"));
+    };
+    if !dir.starts_with(root_dir) || check_dir_info(&target) {
+        return Err(format!("{cmd}: {arg}: Operation not permitted"));
+    }
+    Ok((dir.to_path_buf(), name.to_string_lossy().into_owned()))
+}
+
+pub fn take(
+    args: &[&str],
+    current_dir: &Path,
+    root_dir: &Path,
+    visibility: &Visibility,
+) -> Result<String, String> {
+    if matches!(args, ["-h" | "--help", ..]) {
+        return Ok(TAKE_HELP_TXT.to_string());
+    }
+    let (dir, name) = item_target("take", args, current_dir, root_dir)?;
+    operation_locked_perm(
+        &dir,
+        root_dir,
+        &visibility.progress,
+        "take",
+        "Unlock it before taking things from it.",
+    )?;
+
+    let user = current_user();
+    let obj_path = dir.join(&name);
+    let collectible = obj_path.is_file()
+        && is_collectible(&obj_path)
+        && !visibility.is_hidden(&obj_path, root_dir);
+    Inventory::update(&user, |inventory| {
+        inventory.take(&home_relative(&dir, root_dir), &name, collectible)
+    })
+    .map_err(|e| format!("take: {e}"))?;
+    log::log_info(
+        "take",
+        &format!("{user} took {}", display_relative_path(&obj_path, root_dir)),
+    );
+    Ok(format!("You take the {name}."))
+}
+
+pub fn drop_item(args: &[&str], current_dir: &Path, root_dir: &Path) -> Result<String, String> {
+    if matches!(args, ["-h" | "--help", ..]) {
+        return Ok(DROP_HELP_TXT.to_string());
+    }
+    let [name] = args else {
+        return Err(
+            "drop: expected exactly one item\nTry 'help drop' for more information.".to_string(),
+        );
+    };
+
+    let user = current_user();
+    let exists = current_dir.join(name).exists();
+    Inventory::update(&user, |inventory| {
+        inventory.drop_item(&home_relative(current_dir, root_dir), name, exists)
+    })
+    .map_err(|e| format!("drop: {e}"))?;
+    log::log_info(
+        "drop",
+        &format!(
+            "{user} dropped {name} in {}",
+            display_relative_path(current_dir, root_dir)
+        ),
+    );
+    Ok(format!("You drop the {name}."))
+}
+
+pub fn inventory(args: &[&str]) -> Result<String, String> {
+    let all = match args {
+        [] => false,
+        ["-a" | "--all"] => true,
+        ["-h" | "--help", ..] => return Ok(HELP_TXT.to_string()),
+        _ => {
+            return Err(
+                "inventory: unknown arguments\nTry 'help inventory' for more information."
+                    .to_string(),
+            );
+        }
+    };
+
+    let inventory = Inventory::load(&current_user());
+    let home = |path: &str| {
+        if path.is_empty() {
+            "HOME".to_string()
+        } else {
+            format!("HOME/{path}")
+        }
+    };
+    let mut lines = vec![style::bold("You carry:")];
+    let carried: Vec<String> = inventory
+        .carried()
+        .map(|item| {
+            let (room, _) = item.origin.rsplit_once('/').unwrap_or_default();
+            format!(
+                "   {} {}",
+                item.name,
+                style::dim(&format!("(found in {})", home(room)))
+            )
+        })
+        .collect();
+    if carried.is_empty() {
+        lines.push(style::dim("   (nothing)"));
+    }
+    lines.extend(carried);
+
+    if all {
+        lines.push(style::bold("Dropped:"));
+        let dropped: Vec<String> = inventory
+            .items
+            .iter()
+            .filter_map(|item| {
+                let room = item.room.as_deref()?;
+                Some(format!("   {} in {}", item.name, home(room)))
+            })
+            .collect();
+        if dropped.is_empty() {
+            lines.push(style::dim("   (nothing)"));
+        }
+        lines.extend(dropped);
+    }
+    Ok(lines.join("\n"))
+}
@@ -1,8 +1,11 @@
 use super::argparser::ArgParser;
-use super::cmds::{RESTRICTED_FILES, check_dir_info};
+use super::cmds::{CommandOutput, Payload, RESTRICTED_FILES, check_dir_info};
 use super::whereami::display_relative_path;
+use crate::metainfo::hidden::Visibility;
 use crate::metainfo::lock_perm;
+use crate::utils::find_root::home_relative;
 use crate::utils::log;
+use crate::utils::style::{self, Color};
 use std::path::Path;
 
 pub const HELP_TXT: &str = r#"
@@ -16,8 +19,13 @@ Example:
 "#;
 
 /// Lists all files and directories in the given path, excluding .dir_info and info.json
+/// and the objects hidden from the player
 /// Returns a tuple of (files, directories) as String vectors with lock status
-pub fn list_directory_entries(target_path: &Path, root_dir: &Path) -> (Vec<String>, Vec<String>) {
+pub fn list_directory_entries(
+    target_path: &Path,
+    root_dir: &Path,
+    visibility: &Visibility,
+) -> (Vec<String>, Vec<String>) {
     let entries = match std::fs::read_dir(target_path) {
         Ok(entries) => entries,
         Err(_) => return (Vec::new(), Vec::new()),
@@ -25,11 +33,18 @@ pub fn list_directory_entries(target_path: &Path, root_dir: &Path) -> (Vec<Strin
 
     let mut files = Vec::new();
     let mut directories = Vec::new();
+    if visibility.is_hidden(target_path, root_dir) {
+        return (Vec::new(), Vec::new());
+    }
+    let hidden = visibility.hidden_in(target_path, root_dir);
 
     for entry in entries {
         let Ok(entry) = entry else { continue };
         let path = entry.path();
         let name = entry.file_name().to_string_lossy().into_owned();
+        if hidden.contains(&name) {
+            continue;
+        }
 
         // if name == ".dir_info" || name == "info.json" {
         //     continue;
@@ -50,7 +65,13 @@ pub fn list_directory_entries(target_path: &Path, root_dir: &Path) -> (Vec<Strin
     (files, directories)
 }
 
-pub fn ls(args: &[&str], current_dir: &Path, root_dir: &Path) -> String {
+/// Lists a directory. The listed entries are returned as a `Payload::Listing`.
+pub fn ls(
+    args: &[&str],
+    current_dir: &Path,
+    root_dir: &Path,
+    visibility: &Visibility,
+) -> CommandOutput {
     let args_string: Vec<String> = args.iter().map(|s| s.to_string()).collect();
     let mut parser = ArgParser::new(&["--all", "-a"]);
 
@@ -60,7 +81,9 @@ pub fn ls(args: &[&str], current_dir: &Path, root_dir: &Path) -> String {
 
             // Handle directory argument
             if positional_args.len() > 1 {
-                return "ls: too many arguments\nTry 'help ls' for more information.".to_string();
+                return CommandOutput::err(
+                    "ls: too many arguments\nTry 'help ls' for more information.",
+                );
             }
 
             let target_path = if positional_args.is_empty() {
@@ -68,11 +91,22 @@ pub fn ls(args: &[&str], current_dir: &Path, root_dir: &Path) -> String {
             } else {
                 let dir_name = positional_args[0];
 
-                // Check if directory is locked
+                // Hidden places read as missing, even when locked
                 let dir_path = current_dir.join(dir_name);
-                if let Ok((_, is_locked)) = lock_perm::read_lock_perm(&dir_path) {
+                if visibility.is_hidden(&dir_path, root_dir) {
+                    return CommandOutput::err(format!(
+                        "ls: cannot access '{dir_name}': No such file or directory"
+                    ));
+                }
+
+                // Check if directory is locked
+                if let Ok((_, is_locked)) =
+                    lock_perm::read_player_lock_perm(&dir_path, root_dir, &visibility.progress)
+                {
                     if is_locked {
-                        return format!("{dir_name} is locked. To list contents, unlock it first.");
+                        return CommandOutput::err(format!(
+                            "{dir_name} is locked. To list contents, unlock it first."
+                        ));
                     }
                 }
 
@@ -83,23 +117,27 @@ pub fn ls(args: &[&str], current_dir: &Path, root_dir: &Path) -> String {
                             "Attempted to list restricted directory: {dir_name} Operation Not Permitted"
                         ),
                     );
-                    return format!(
+                    return CommandOutput::err(format!(
                         "Attempted to list restricted directory: {dir_name} Operation Not Permitted"
-                    );
+                    ));
                 }
 
                 let joined = current_dir.join(dir_name);
                 if joined.starts_with(root_dir) {
                     joined
                 } else {
-                    return "ls: Access denied outside root directory".to_string();
+                    return CommandOutput::err("ls: Access denied outside root directory");
                 }
             };
 
             let (mut files_vec, mut directories_vec) =
-                list_directory_entries(&target_path, root_dir);
+                list_directory_entries(&target_path, root_dir, visibility);
+            // Items taken or dropped by the player
+            visibility
+                .inventory
+                .adjust_listing(&home_relative(&target_path, root_dir), &mut files_vec);
 
-            if !args.contains(&"--all") || !args.contains(&"-a") {
+            if !args.contains(&"--all") && !args.contains(&"-a") {
                 // Remove all hidden files and directories starting with '.'
                 let is_hidden = |name: &str| name.starts_with('.');
                 files_vec.retain(|f| !is_hidden(f));
@@ -113,54 +151,72 @@ pub fn ls(args: &[&str], current_dir: &Path, root_dir: &Path) -> String {
                     } else {
                         e.to_string()
                     };
-                    return format!(
+                    return CommandOutput::err(format!(
                         "ls: cannot access '{}': {}",
                         display_relative_path(&target_path, root_dir),
                         error_msg
-                    );
+                    ));
                 }
             }
-            // Check lock status
             // Check lock status and format display names
+            let locked_marker = format!(" {}", style::colored("(locked)", Color::Yellow));
+            let none = style::dim("   (none)");
             let files = if files_vec.is_empty() {
-                "   (none)\n".to_string()
+                format!("{none}\n")
             } else {
                 files_vec
                     .iter()
                     .map(|f| {
-                        let is_locked = match lock_perm::read_lock_perm(&current_dir.join(f)) {
+                        let is_locked = match lock_perm::read_player_lock_perm(
+                            &current_dir.join(f),
+                            root_dir,
+                            &visibility.progress,
+                        ) {
                             Ok((_, locked)) => locked,
                             Err(_) => false,
                         };
-                        format!("   {}{}\n", f, if is_locked { " (locked)" } else { "" })
+                        format!("   {}{}\n", f, if is_locked { &locked_marker } else { "" })
                     })
                     .collect()
             };
 
             let directories = if directories_vec.is_empty() {
-                "   (none)\n".to_string()
+                format!("{none}\n")
             } else {
                 directories_vec
                     .iter()
                     .map(|d| {
                         let dir_name = d.trim_end_matches('/');
-                        let is_locked = match lock_perm::read_lock_perm(&current_dir.join(dir_name))
-                        {
+                        let is_locked = match lock_perm::read_player_lock_perm(
+                            &current_dir.join(dir_name),
+                            root_dir,
+                            &visibility.progress,
+                        ) {
                             Ok((_, locked)) => locked,
                             Err(_) => false,
                         };
-                        format!("   {}{}\n", d, if is_locked { " (locked)" } else { "" })
+                        let d = style::colored(d, Color::Blue);
+                        format!("   {}{}\n", d, if is_locked { &locked_marker } else { "" })
                     })
                     .collect()
             };
 
-            format!("\nObjects:\n{files}\nFrom inside here, you can go to:\n{directories}")
+            CommandOutput::ok(format!(
+                "\n{}\n{files}\n{}\n{directories}",
+                style::bold("Objects:"),
+                style::bold("From inside here, you can go to:")
+            ))
+            .with_payload(Payload::Listing {
+                files: files_vec,
+                directories: directories_vec,
+            })
         }
         Err(e) => match &e[..] {
-            "help" => HELP_TXT,
-            "unknown" => "ls: unknown flag\nTry 'help ls' for more information.",
-            _ => "Error parsing arguments. Try 'help ls' for more information.",
-        }
-        .to_string(),
+            "help" => CommandOutput::ok(HELP_TXT),
+            "unknown" => {
+                CommandOutput::err("ls: unknown flag\nTry 'help ls' for more information.")
+            }
+            _ => CommandOutput::err("Error parsing arguments. Try 'help ls' for more information."),
+        },
     }
 }
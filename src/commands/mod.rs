@@ -40,3 +40,26 @@ pub use solve::solve;
 
 mod unlock;
 pub use unlock::unlock;
+
+mod history;
+pub use history::history;
+
+mod alias;
+pub use alias::{alias, unalias};
+
+mod function;
+
+mod env;
+pub use env::{env, set, unset};
+
+pub mod run;
+pub use run::run;
+
+mod inventory;
+pub use inventory::{drop_item, inventory, take};
+
+mod talk;
+pub use talk::talk;
+
+mod objectives;
+pub use objectives::objectives;
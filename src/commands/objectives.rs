@@ -0,0 +1,55 @@
+use crate::metainfo::objectives::{current, read_objectives};
+use crate::utils::progress::Progress;
+use crate::utils::shell_history::current_user;
+use crate::utils::style;
+use std::path::Path;
+
+pub const HELP_TXT: &str = r#"
+Usage: objectives
+
+Lists the objectives of this sekai and the ones you have completed. Objectives are checked
+as you play, and you are told when you complete one.
+"#;
+
+pub fn objectives(args: &[&str], root_dir: &Path) -> Result<String, String> {
+    match args {
+        [] => {}
+        ["-h" | "--help", ..] => return Ok(HELP_TXT.to_string()),
+        _ => {
+            return Err(
+                "objectives: unknown arguments\nTry 'help objectives' for more information."
+                    .to_string(),
+            );
+        }
+    }
+
+    let objectives = read_objectives(root_dir);
+    if objectives.is_empty() {
+        return Ok("This sekai has no objectives. Explore as you like!".to_string());
+    }
+    let progress = Progress::load(&current_user());
+    let next = current(&objectives, &progress).map(|objective| &objective.id);
+    let done = objectives
+        .iter()
+        .filter(|objective| progress.objectives.contains(&objective.id))
+        .count();
+
+    let mut lines = vec![style::bold(&format!(
+        "Objectives ({done}/{} completed):",
+        objectives.len()
+    ))];
+    for objective in &objectives {
+        let line = if progress.objectives.contains(&objective.id) {
+            style::dim(&format!("   [x] {}", objective.task))
+        } else if Some(&objective.id) == next {
+            style::bold(&format!("   [ ] {}", objective.task))
+        } else {
+            format!("   [ ] {}", objective.task)
+        };
+        lines.push(line);
+    }
+    if next.is_none() {
+        lines.push("All objectives complete!".to_string());
+    }
+    Ok(lines.join("\n"))
+}
@@ -1,6 +1,10 @@
-use super::cmds::check_dir_info;
+use super::cmds::{check_dir_info, normalize_path};
 use super::whereami::display_relative_path;
+use crate::metainfo::hidden::Visibility;
 use crate::metainfo::lock_perm;
+use crate::utils::events::{self, ShellEvent};
+use crate::utils::find_root::home_relative;
+use crate::utils::shell_history::current_user;
 use std::fs;
 use std::path::Path;
 
@@ -8,48 +12,70 @@ pub const HELP_TXT: &str = r#"
 Usage: read <object_name>
 
 Read the contents of a file and display it.
+In the window, files longer than the screen open in a pager:
+Space/b to turn pages, arrow keys to scroll, q to quit.
 "#;
 
 /// Read and display file contents (similar to 'cat' command)
-pub fn read(args: &[&str], current_dir: &Path, root_dir: &Path) -> String {
+pub fn read(
+    args: &[&str],
+    current_dir: &Path,
+    root_dir: &Path,
+    visibility: &Visibility,
+) -> Result<String, String> {
     if args.is_empty() {
-        return "read: missing file operand".to_string();
+        return Err("read: missing file operand".to_string());
     }
 
-    let file_path = current_dir.join(args[0]);
+    let file_path = normalize_path(&current_dir.join(args[0]));
 
     // Check if file is doesn't refer a restricted one
     if check_dir_info(&file_path) {
-        return format!(
+        return Err(format!(
             "read: Attempted to read/refer restricted files: {} Operation Not Permitted",
             display_relative_path(&file_path, root_dir)
-        );
+        ));
     }
 
     // Security check - must stay within root directory
     if !file_path.starts_with(root_dir) {
-        return "read: Access denied outside root directory".to_string();
+        return Err("read: Access denied outside root directory".to_string());
     }
 
+    // Hidden objects read as missing until they are revealed
+    if visibility.is_hidden(&file_path, root_dir) {
+        return Err(format!(
+            "read: {}: No such file",
+            display_relative_path(&file_path, root_dir)
+        ));
+    }
+
+    // Items taken or dropped by the player, as `ls` lists them
+    let Some(file_path) = visibility.inventory.locate(&file_path, root_dir) else {
+        return Err(format!(
+            "read: {}: No such file",
+            display_relative_path(&file_path, root_dir)
+        ));
+    };
+
     // Check if path is a directory
     if file_path.is_dir() {
-        return format!(
+        return Err(format!(
             "read: {}: Is a directory",
             display_relative_path(&file_path, root_dir)
-        );
+        ));
     }
 
     // Check if locked or not
-    if let Err(e) = lock_perm::operation_locked_perm(
+    lock_perm::operation_locked_perm(
         &file_path,
+        root_dir,
+        &visibility.progress,
         "read",
         "Cannot read locked file. Unlock it first",
-    ) {
-        return e;
-    }
-    match fs::read_to_string(&file_path) {
-        Ok(content) => content,
-        Err(e) => format!(
+    )?;
+    let content = fs::read_to_string(&file_path).map_err(|e| {
+        format!(
             "read: {}: {}",
             display_relative_path(&file_path, root_dir),
             match e.kind() {
@@ -57,6 +83,14 @@ pub fn read(args: &[&str], current_dir: &Path, root_dir: &Path) -> String {
                 std::io::ErrorKind::PermissionDenied => "Permission denied",
                 _ => "Could not read file",
             }
-        ),
-    }
+        )
+    })?;
+    events::publish(
+        &current_user(),
+        root_dir,
+        ShellEvent::FileRead {
+            path: home_relative(&file_path, root_dir),
+        },
+    );
+    Ok(content)
 }
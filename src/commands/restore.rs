@@ -1,17 +1,25 @@
 use super::argparser::ArgParser;
+use super::cmds::CommandOutput;
 use crate::rns::restore_comp::{backup_sekai, can_restore, can_save, restore_sekai};
+use crate::utils::events::{self, ShellEvent};
 use crate::utils::log;
+use crate::utils::progress::{reset_player, restore_player};
 use crate::utils::prompt::UserPrompter;
+use crate::utils::shell_history::current_user;
 use std::path::PathBuf;
 
 pub const HELP_TEXT: &str = r#"
 Usage: restore
 
-- restore                     : Restore the Sekai directory from the last saved state. 
+- restore                     : Restore the Sekai directory, your progress and inventory from the last saved state. 
 - restore -f | --force        : This means all your progress is gone, and you will have to start over from scratch.
 "#;
 
-pub fn restore(args: &[&str], root_path: &PathBuf, prompter: &mut dyn UserPrompter) -> String {
+pub fn restore(
+    args: &[&str],
+    root_path: &PathBuf,
+    prompter: &mut dyn UserPrompter,
+) -> CommandOutput {
     let mut parser = ArgParser::new(&["-f", "--force"]);
     let args_string: Vec<String> = args.iter().map(|s| s.to_string()).collect();
 
@@ -22,7 +30,7 @@ pub fn restore(args: &[&str], root_path: &PathBuf, prompter: &mut dyn UserPrompt
             if !pos_args.is_empty() {
                 err_msg += "Too many positional arguments provided. No arguments expected.";
                 log::log_info("restore", err_msg.as_str());
-                return err_msg;
+                return CommandOutput::err(err_msg);
             }
             // Ask for confirmation
 
@@ -30,7 +38,7 @@ pub fn restore(args: &[&str], root_path: &PathBuf, prompter: &mut dyn UserPrompt
                 if !prompter
                     .confirm("Are you sure you want to restore? This will erase all progress.")
                 {
-                    return "Restore cancelled by user.".to_string();
+                    return CommandOutput::ok("Restore cancelled by user.");
                 }
                 if can_restore(root_path) {
                     // Restore file already exists.
@@ -42,9 +50,15 @@ pub fn restore(args: &[&str], root_path: &PathBuf, prompter: &mut dyn UserPrompt
                         err_msg +=
                             "Failed to restore Sekai. Please check the logs for more details.";
                         log::log_error("restore", err_msg.as_str());
-                        return err_msg;
+                        return CommandOutput::err(err_msg);
                     }
-                    "Sekai restored successfully.\n".to_string()
+                    if let Err(e) = reset_player(&current_user()) {
+                        err_msg += "Failed to erase your progress. Please check the logs for more details.";
+                        log::log_error("restore", &format!("{err_msg} {e}"));
+                        return CommandOutput::err(err_msg);
+                    }
+                    events::publish(&current_user(), root_path, ShellEvent::Restored);
+                    CommandOutput::ok("Sekai restored successfully.\n")
                 } else {
                     err_msg += "No restore file found. ";
                     // If restore file is not found, backup the current state
@@ -52,14 +66,14 @@ pub fn restore(args: &[&str], root_path: &PathBuf, prompter: &mut dyn UserPrompt
                     if backup_sekai("restore", root_path).is_err() {
                         err_msg += "Failed to backup current state. Please check the logs for more details.";
                         log::log_error("restore", err_msg.as_str());
-                        return err_msg;
+                        return CommandOutput::err(err_msg);
                     }
-                    err_msg + "Backup created successfully."
+                    CommandOutput::err(err_msg + "Backup created successfully.")
                 }
             } else {
                 if !prompter.confirm("Are you sure you want to restore to the last saved version?")
                 {
-                    return "Restore cancelled by user.".to_string();
+                    return CommandOutput::ok("Restore cancelled by user.");
                 }
                 log::log_info("restore", "SAVE  PARSED");
                 // Restore operation
@@ -70,19 +84,33 @@ pub fn restore(args: &[&str], root_path: &PathBuf, prompter: &mut dyn UserPrompt
                         err_msg +=
                             "Failed to restore Sekai. Please check the logs for more details.";
                         log::log_error("save", err_msg.as_str());
-                        return err_msg;
+                        return CommandOutput::err(err_msg);
+                    }
+                    if let Err(e) = restore_player(&current_user()) {
+                        err_msg += "Failed to restore your progress. Please check the logs for more details.";
+                        log::log_error("restore", &format!("{err_msg} {e}"));
+                        return CommandOutput::err(err_msg);
                     }
-                    "Sekai restored successfully from previously saved progress.\n".to_string()
+                    events::publish(&current_user(), root_path, ShellEvent::Restored);
+                    CommandOutput::ok(
+                        "Sekai restored successfully from previously saved progress.\n",
+                    )
                 } else {
-                    (err_msg + "No restore file found. Please save your progress first with `save` command.")
-                        .to_string()
+                    CommandOutput::err(
+                        err_msg
+                            + "No restore file found. Please save your progress first with `save` command.",
+                    )
                 }
             }
         }
         Err(e) => match &e[..] {
-            "help" => HELP_TEXT.to_string(),
-            "unknown" => "restore: unknown flag\nTry 'help save' for more information.".to_string(),
-            _ => "Error parsing arguments. Try 'help restore' for more information.".to_string(),
+            "help" => CommandOutput::ok(HELP_TEXT),
+            "unknown" => {
+                CommandOutput::err("restore: unknown flag\nTry 'help save' for more information.")
+            }
+            _ => CommandOutput::err(
+                "Error parsing arguments. Try 'help restore' for more information.",
+            ),
         },
     }
 }
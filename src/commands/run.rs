@@ -0,0 +1,300 @@
+use super::cmds::{CommandOutput, CommandResult, check_dir_info, cmd_manager, normalize_path};
+use super::whereami::display_relative_path;
+use crate::metainfo::hidden::Visibility;
+use crate::metainfo::lock_perm;
+use crate::utils::log;
+use crate::utils::prompt::ScriptPrompter;
+use std::cell::Cell;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const HELP_TXT: &str = r#"
+Usage: run <script>
+
+Runs the commands in a script file, one per line, as if you typed them.
+- Lines starting with `#` are comments.
+- Lines starting with `>` answer the prompts of the command above them, in order.
+
+Example:
+    # go to the first room and read the note
+    go room_1
+    read note.txt
+    restore
+    > y
+"#;
+
+/// Scripts and functions may run other scripts and functions, but only this deep.
+const MAX_SCRIPT_DEPTH: usize = 8;
+
+thread_local! {
+    /// Scripts and functions running on this thread. Web requests run on their own
+    /// threads, so the scripts of one user never count for another.
+    static SCRIPT_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Counts a script or function as running until it is dropped, even if a command panics.
+pub(super) struct ScriptDepth;
+
+impl ScriptDepth {
+    pub(super) fn enter() -> Option<Self> {
+        let depth = SCRIPT_DEPTH.get();
+        if depth >= MAX_SCRIPT_DEPTH {
+            return None;
+        }
+        SCRIPT_DEPTH.set(depth + 1);
+        Some(ScriptDepth)
+    }
+}
+
+impl Drop for ScriptDepth {
+    fn drop(&mut self) {
+        SCRIPT_DEPTH.set(SCRIPT_DEPTH.get() - 1);
+    }
+}
+
+/// A command from a script, with the answers to its prompts.
+#[derive(Debug, PartialEq)]
+pub struct ScriptStep {
+    pub line_no: usize,
+    pub command: String,
+    pub answers: Vec<String>,
+}
+
+/// How a script run ended.
+#[derive(Debug, PartialEq)]
+pub enum ScriptStatus {
+    Completed,
+    Exited,
+    Failed(String),
+}
+
+/// Result of running a script.
+pub struct ScriptRun {
+    pub output: Vec<String>,
+    pub current_dir: PathBuf,
+    pub status: ScriptStatus,
+}
+
+/// Parses a script into steps. Blank lines and `#` comments are skipped.
+pub fn parse_script(contents: &str) -> Result<Vec<ScriptStep>, String> {
+    let mut steps: Vec<ScriptStep> = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(answer) = line.strip_prefix('>') {
+            match steps.last_mut() {
+                Some(step) => step.answers.push(answer.trim().to_string()),
+                None => return Err(format!("line {}: answer without a command", i + 1)),
+            }
+        } else {
+            steps.push(ScriptStep {
+                line_no: i + 1,
+                command: line.to_string(),
+                answers: Vec::new(),
+            });
+        }
+    }
+    Ok(steps)
+}
+
+/// Runs the contents of a script through `cmd_manager`, starting in `current_dir`.
+/// Stops at the first unknown command or at `exit`.
+pub fn run_script(contents: &str, current_dir: &Path, root_dir: &Path) -> ScriptRun {
+    let mut run = ScriptRun {
+        output: Vec::new(),
+        current_dir: current_dir.to_path_buf(),
+        status: ScriptStatus::Completed,
+    };
+
+    let steps = match parse_script(contents) {
+        Ok(steps) => steps,
+        Err(e) => {
+            run.status = ScriptStatus::Failed(e);
+            return run;
+        }
+    };
+
+    let Some(_depth) = ScriptDepth::enter() else {
+        run.status = ScriptStatus::Failed("scripts are nested too deeply".to_string());
+        return run;
+    };
+
+    let root_dir = root_dir.to_path_buf();
+    let mut prompter = ScriptPrompter::new();
+    for step in steps {
+        log::log_debug("run", &format!("line {}: {}", step.line_no, step.command));
+        run.output.push(format!("> {}", step.command));
+        prompter.push_answers(step.answers);
+
+        let parts: Vec<&str> = step.command.split_whitespace().collect();
+        let result = cmd_manager(&parts, &run.current_dir, &root_dir, &mut prompter);
+        run.output.append(&mut prompter.transcript);
+
+        let unused = prompter.clear_answers();
+        if unused > 0 {
+            log::log_warning(
+                "run",
+                &format!("line {}: {unused} unused answer(s)", step.line_no),
+            );
+        }
+
+        match result {
+            CommandResult::Output(output) => run.output.push(output.text()),
+            CommandResult::ChangeDirectory(new_dir, output) => {
+                run.current_dir = new_dir;
+                run.output.push(output.text());
+            }
+            CommandResult::Clear => {}
+            CommandResult::Exit => {
+                run.status = ScriptStatus::Exited;
+                break;
+            }
+            CommandResult::NotFound => {
+                run.status = ScriptStatus::Failed(format!(
+                    "line {}: command not found: {}",
+                    step.line_no,
+                    parts.first().unwrap_or(&"")
+                ));
+                break;
+            }
+        }
+    }
+
+    run.output.retain(|line| !line.is_empty());
+    run
+}
+
+/// Runs a script file from the sekai. Returns the output and the directory the script ended in.
+/// The run fails if the script could not be run or stopped early.
+pub fn run(
+    args: &[&str],
+    current_dir: &Path,
+    root_dir: &Path,
+    visibility: &Visibility,
+) -> (CommandOutput, PathBuf) {
+    let current = current_dir.to_path_buf();
+    match args {
+        [] => return (CommandOutput::err("run: missing script operand"), current),
+        ["-h" | "--help"] => return (CommandOutput::ok(HELP_TXT), current),
+        [_] => {}
+        _ => return (CommandOutput::err("run: too many arguments"), current),
+    }
+
+    let script_path = normalize_path(&current_dir.join(args[0]));
+    if check_dir_info(&script_path) {
+        return (
+            CommandOutput::err(format!(
+                "run: Attempted to read/refer restricted files: {} Operation Not Permitted",
+                display_relative_path(&script_path, root_dir)
+            )),
+            current,
+        );
+    }
+    if !script_path.starts_with(root_dir) {
+        return (
+            CommandOutput::err("run: Access denied outside root directory"),
+            current,
+        );
+    }
+    // Hidden scripts read as missing until they are revealed
+    if visibility.is_hidden(&script_path, root_dir) {
+        return (
+            CommandOutput::err(format!(
+                "run: {}: No such file",
+                display_relative_path(&script_path, root_dir)
+            )),
+            current,
+        );
+    }
+    // Items taken or dropped by the player, as `ls` lists them
+    let Some(script_path) = visibility.inventory.locate(&script_path, root_dir) else {
+        return (
+            CommandOutput::err(format!(
+                "run: {}: No such file",
+                display_relative_path(&script_path, root_dir)
+            )),
+            current,
+        );
+    };
+    if script_path.is_dir() {
+        return (
+            CommandOutput::err(format!(
+                "run: {}: Is a directory",
+                display_relative_path(&script_path, root_dir)
+            )),
+            current,
+        );
+    }
+    if let Err(e) = lock_perm::operation_locked_perm(
+        &script_path,
+        root_dir,
+        &visibility.progress,
+        "run",
+        "Cannot run locked script. Unlock it first",
+    ) {
+        return (CommandOutput::err(e), current);
+    }
+
+    let contents = match fs::read_to_string(&script_path) {
+        Ok(contents) => contents,
+        Err(_) => {
+            return (
+                CommandOutput::err(format!(
+                    "run: {}: No such file",
+                    display_relative_path(&script_path, root_dir)
+                )),
+                current,
+            );
+        }
+    };
+
+    let run = run_script(&contents, current_dir, root_dir);
+    let mut output = CommandOutput::ok(run.output.join("\n"));
+    match run.status {
+        ScriptStatus::Completed => {}
+        ScriptStatus::Exited => {
+            output.status = 1;
+            output.stderr = "run: script stopped at `exit`".to_string();
+        }
+        ScriptStatus::Failed(e) => {
+            output.status = 1;
+            output.stderr = format!("run: {e}");
+        }
+    }
+    (output, run.current_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::setup_test_dir;
+
+    #[test]
+    fn test_parse_script() {
+        let steps = parse_script("# comment\n\ngo subdir1\nrestore\n> y\n").unwrap();
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].command, "go subdir1");
+        assert_eq!(steps[1].line_no, 4);
+        assert_eq!(steps[1].answers, vec!["y".to_string()]);
+        assert!(parse_script("> y\nls").is_err());
+    }
+
+    #[test]
+    fn test_run_script() {
+        let (_temp_dir, root_path) = setup_test_dir(true);
+        let run = run_script("go subdir1\nread file2.txt\nnope", &root_path, &root_path);
+
+        assert_eq!(run.current_dir, root_path.join("subdir1"));
+        assert!(
+            run.output
+                .iter()
+                .any(|line| line.contains("hello from file2"))
+        );
+        assert_eq!(
+            run.status,
+            ScriptStatus::Failed("line 3: command not found: nope".to_string())
+        );
+    }
+}
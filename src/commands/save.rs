@@ -1,16 +1,21 @@
 use super::argparser::ArgParser;
+use super::cmds::CommandOutput;
 use crate::rns::restore_comp::backup_sekai;
+use crate::utils::events::{self, ShellEvent};
 use crate::utils::log;
+use crate::utils::progress::save_player;
+use crate::utils::shell_history::current_user;
 use std::path::PathBuf;
 
 pub const HELP_TEXT: &str = r#"
 Usage: save
 
 Saves the progress of your sekai progress. This means all your progress is saved till and you can restore it later.
+Your solved levels, opened locks and inventory are saved along with the Sekai.
 - save  : Save your current progress of the Sekai.
 "#;
 
-pub fn save(args: &[&str], root_path: &PathBuf) -> String {
+pub fn save(args: &[&str], root_path: &PathBuf) -> CommandOutput {
     let mut parser = ArgParser::new(&[]);
     let args_string: Vec<String> = args.iter().map(|s| s.to_string()).collect();
 
@@ -22,19 +27,27 @@ pub fn save(args: &[&str], root_path: &PathBuf) -> String {
             if !pos_args.is_empty() {
                 err_msg += "Too many positional arguments provided. No arguments expected.";
                 log::log_info("save", err_msg.as_str());
-                return err_msg;
+                return CommandOutput::err(err_msg);
             }
             if backup_sekai("save", root_path).is_err() {
                 err_msg += "Failed to save Sekai. Please check the logs for more details.";
                 log::log_error("save", err_msg.as_str());
-                return err_msg;
+                return CommandOutput::err(err_msg);
             }
-            "Sekai saved successfully \n".to_string()
+            if let Err(e) = save_player(&current_user()) {
+                err_msg += "Failed to save your progress. Please check the logs for more details.";
+                log::log_error("save", &format!("{err_msg} {e}"));
+                return CommandOutput::err(err_msg);
+            }
+            events::publish(&current_user(), root_path, ShellEvent::Saved);
+            CommandOutput::ok("Sekai saved successfully \n")
         }
         Err(e) => match &e[..] {
-            "help" => HELP_TEXT.to_string(),
+            "help" => CommandOutput::ok(HELP_TEXT),
 
-            _ => "Error parsing arguments. Try 'help save' for more information.".to_string(),
+            _ => {
+                CommandOutput::err("Error parsing arguments. Try 'help save' for more information.")
+            }
         },
     }
 }
@@ -1,14 +1,21 @@
 use super::argparser::ArgParser;
-use super::cmds::normalize_path;
+use super::cmds::{CommandOutput, Payload, normalize_path};
+use crate::metainfo::hidden::Visibility;
 use crate::metainfo::info_reader::read_get_obj_info;
-use crate::metainfo::lock_perm::read_lock_perm;
+use crate::metainfo::lock_perm::read_player_lock_perm;
 use crate::rns::security::{characterise_enc_key, decrypt, encrypt};
-use crate::utils::{auth::get_current_username, log, prompt::UserPrompter};
+use crate::utils::caller::Caller;
+use crate::utils::events::{self, ShellEvent};
+use crate::utils::find_root::home_relative;
+use crate::utils::progress::Progress;
+use crate::utils::rate_limit::{self, Action};
+use crate::utils::{log, prompt::UserPrompter};
 use std::path::Path;
 pub const HELP_TEXT: &str = r#"
-Usage: solve [OPTIONS] <LEVEL_NAME> <
+Usage: solve [OPTIONS] <LEVEL_NAME>
 
 Use Solve to enter your answer to a problem 
+The answer is asked for, so that it is not kept in your history.
 Options:
 
 Examples:
@@ -19,7 +26,8 @@ pub fn solve(
     current_dir: &Path,
     root_dir: &Path,
     prompter: &mut dyn UserPrompter,
-) -> String {
+    visibility: &Visibility,
+) -> CommandOutput {
     //only 1 argumen :path to level
     let mut parser = ArgParser::new(&[]);
     let args_string: Vec<String> = args.iter().map(|s| s.to_string()).collect();
@@ -36,14 +44,14 @@ pub fn solve(
         Ok(_) => {
             let pos_args = parser.get_positional_args();
             if pos_args.len() > 1 {
-                err_msg += "Too many positional arguments provided. Only 1 argument expected.";
+                err_msg += "Too many positional arguments provided. Expected path to level.";
                 log::log_info("solve", err_msg.as_str());
-                return err_msg;
+                return CommandOutput::err(err_msg);
             }
             if pos_args.is_empty() {
                 err_msg += "No positional argument provided. Expected path to level.";
                 log::log_info("solve", err_msg.as_str());
-                return err_msg;
+                return CommandOutput::err(err_msg);
             }
             //now we know only 1 argument is there
             //test for valid level name
@@ -51,24 +59,26 @@ pub fn solve(
             if !target.exists() {
                 err_msg += "Invalid path given";
                 log::log_info("solve", err_msg.as_str());
-                return err_msg;
+                return CommandOutput::err(err_msg);
             }
             //validated path. now check if it is a protected thing
-            if let Ok((is_level, is_locked)) = read_lock_perm(&target) {
+            if let Ok((is_level, is_locked)) =
+                read_player_lock_perm(&target, root_dir, &visibility.progress)
+            {
                 if !is_level {
                     err_msg += "This is not a level. Cannot solve.";
                     log::log_info("solve", err_msg.as_str());
-                    return err_msg;
+                    return CommandOutput::err(err_msg);
                 }
                 if is_locked {
                     err_msg += "Level is locked. You must first unlock it.";
                     log::log_info("solve", err_msg.as_str());
-                    return err_msg;
+                    return CommandOutput::err(err_msg);
                 }
             } else {
                 err_msg += "Failed to read lock permissions for the level.";
                 log::log_error("solve", err_msg.as_str());
-                return err_msg;
+                return CommandOutput::err(err_msg);
             }
 
             let Ok(level_name) = target
@@ -78,38 +88,58 @@ pub fn solve(
             else {
                 err_msg += "Failed to get level name from path.";
                 log::log_error("solve", err_msg.as_str());
-                return err_msg;
+                return CommandOutput::err(err_msg);
             };
             log::log_info("solve", &format!("Level name: {level_name}"));
+            let caller = Caller::current();
+            let username = caller.user.as_str();
+            // Answers are not counted as failures: a wrong one cannot be told from a right one
+            // here, as it only gives a wrong flag. That flag is what `unlock` limits, and a
+            // player locked out of it waits here too
+            if let Err(msg) =
+                rate_limit::check(Action::Flag, &rate_limit::keys(username, caller.ip))
+            {
+                err_msg += &msg;
+                log::log_info("solve", err_msg.as_str());
+                return CommandOutput::err(err_msg);
+            }
             let user_input =
-                prompter.input(&format!("> Enter your answer for level '{level_name}': "));
-            if user_input.is_empty() {
+                prompter.secret(&format!("> Enter your answer for level '{level_name}': "));
+            if user_input.trim().is_empty() {
                 err_msg += "No input provided. Cannot solve.";
                 log::log_info("solve", err_msg.as_str());
-                err_msg
+                CommandOutput::err(err_msg)
             } else {
-                let username = get_current_username().unwrap_or("default_user");
                 let user_flag = check_solve_input(user_input, &target, level_name, username);
                 match user_flag {
                     Ok(flag) => {
-                        log::log_info(
-                            "solve",
-                            &format!("Successfully generated User flag: {flag}"),
-                        );
-                        format!("User flag: {flag}")
+                        log::log_debug("solve", &format!("Solved {level_name}"));
+                        let level = home_relative(&target, root_dir);
+                        Progress::update(username, |progress| {
+                            progress.solved.insert(level.clone(), flag.clone())
+                        });
+                        events::publish(username, root_dir, ShellEvent::LevelSolved { level });
+                        CommandOutput::ok(format!("User flag: {flag}")).with_payload(
+                            Payload::Flag {
+                                level: level_name.to_string(),
+                                flag,
+                            },
+                        )
                     }
                     Err(e) => {
                         err_msg += &format!("Error solving {level_name}: {e}");
                         log::log_error("solve", err_msg.as_str());
-                        err_msg
+                        CommandOutput::err(err_msg)
                     }
                 }
             }
         }
 
         Err(e) => match &e[..] {
-            "help" => HELP_TEXT.to_string(),
-            _ => "Error parsing arguments. Try 'help solve' for more information.".to_string(),
+            "help" => CommandOutput::ok(HELP_TEXT),
+            _ => CommandOutput::err(
+                "Error parsing arguments. Try 'help solve' for more information.",
+            ),
         },
     }
 }
@@ -121,8 +151,6 @@ fn check_solve_input(
     username: &str,
 ) -> Result<String, String> {
     let info_path = path_to_level.parent().unwrap().join(".dir_info/info.json");
-    println!("info_path: {}", info_path.display());
-    println!("level_name: {level_name}");
     if let Some(text_decrypt_me) = read_get_obj_info(&info_path, level_name)
         .unwrap()
         .properties
@@ -130,11 +158,8 @@ fn check_solve_input(
         .and_then(|v| v.as_str())
         .map(|s| s.to_string())
     {
-        println!("text_decrypt_me: {text_decrypt_me}");
-        println!("Username: {username}");
         let user_inp_enc_key = characterise_enc_key(username, level_name);
         let decrypted_user_input = decrypt(&user_inp_enc_key, &user_input);
-        println!("decrypted _user input: {decrypted_user_input}");
 
         //run some extra tests on decrypted user input
         //use this to decrypt textfile
@@ -142,7 +167,6 @@ fn check_solve_input(
             &characterise_enc_key(level_name, &decrypted_user_input),
             &text_decrypt_me,
         );
-        println!("decrypted _decrypt me : {decrypted_decrypt_me}");
         let user_flag: String = encrypt(
             &characterise_enc_key(
                 &format!("{}_{}", username, username.len()),
@@ -153,7 +177,6 @@ fn check_solve_input(
         Ok(user_flag)
     } else {
         //unable to read decrypt_me property
-        println!("Unable to read decrypt_me property from info.json for level: {level_name}");
         Err("Unable to read decrypt_me property from info.json".to_string())
     }
 }
@@ -0,0 +1,159 @@
+use super::cmds::{check_dir_info, normalize_path};
+use crate::metainfo::dialogue::{Turn, load_dialogue};
+use crate::metainfo::hidden::Visibility;
+use crate::metainfo::lock_perm::operation_locked_perm;
+use crate::utils::find_root::home_relative;
+use crate::utils::inventory::Inventory;
+use crate::utils::progress::Progress;
+use crate::utils::shell_history::current_user;
+use crate::utils::{log, style};
+use std::path::Path;
+
+pub const HELP_TXT: &str = r#"
+Usage: talk <someone> [choice]
+
+Talks to someone. When they ask you something, answer with the number of your choice.
+They remember where you left the conversation.
+
+Examples:
+- talk friend        # Start talking, or hear the question again
+- talk friend 2      # Answer with the second choice
+"#;
+
+pub fn talk(
+    args: &[&str],
+    current_dir: &Path,
+    root_dir: &Path,
+    visibility: &Visibility,
+) -> Result<String, String> {
+    let (target, choice) = match args {
+        ["-h" | "--help", ..] => return Ok(HELP_TXT.to_string()),
+        [target] => (*target, None),
+        [target, choice] => match choice.parse::<usize>() {
+            Ok(choice) => (*target, Some(choice)),
+            Err(_) => {
+                return Err(format!(
+                    "talk: {choice}: answer with the number of a choice"
+                ));
+            }
+        },
+        _ => {
+            return Err(
+                "talk: expected someone to talk to\nTry 'help talk' for more information."
+                    .to_string(),
+            );
+        }
+    };
+
+    let npc_path = normalize_path(&current_dir.join(target));
+    if !npc_path.starts_with(root_dir)
+        || check_dir_info(&npc_path)
+        || !npc_path.exists()
+        || visibility.is_hidden(&npc_path, root_dir)
+    {
+        return Err(format!("talk: {target}: There is no one here by that name"));
+    }
+    operation_locked_perm(
+        &npc_path,
+        root_dir,
+        &visibility.progress,
+        "talk",
+        "Unlock it first.",
+    )?;
+    let dialogue = load_dialogue(&npc_path).map_err(|e| format!("talk: {target}: {e}"))?;
+    let npc = npc_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let key = home_relative(&npc_path, root_dir);
+
+    let user = current_user();
+    Progress::update(&user, |progress| {
+        let mut lines = Vec::new();
+        let (node, turn) = match progress.conversations.get(&key) {
+            Some(node) if choice.is_some() => (node.clone(), Turn::default()),
+            Some(node) => {
+                return Ok(show_turn(
+                    &npc,
+                    &dialogue.view(node, &progress.flags),
+                    lines,
+                ));
+            }
+            None => {
+                let turn = dialogue.enter(&dialogue.start, &progress.flags);
+                let node = turn.node.clone();
+                apply_turn(&turn, &key, &user, progress);
+                match (node, choice) {
+                    (Some(node), Some(_)) => (node, turn),
+                    _ => return Ok(show_turn(&npc, &turn, lines)),
+                }
+            }
+        };
+        // Answering right away still shows what was said first
+        lines.extend(turn_lines(&npc, &turn));
+
+        let choice = choice.unwrap_or_default();
+        let (text, turn) = dialogue
+            .choose(&node, choice, &progress.flags)
+            .map_err(|e| format!("talk: {e}"))?;
+        log::log_info("talk", &format!("{user} answered {npc}: {text}"));
+        progress
+            .decisions
+            .insert(format!("{key}#{node}"), text.clone());
+        lines.push(style::dim(&format!("> {text}")));
+        apply_turn(&turn, &key, &user, progress);
+        Ok(show_turn(&npc, &turn, lines))
+    })
+}
+
+/// Records a turn in the player's progress and hands over its items.
+fn apply_turn(turn: &Turn, key: &str, user: &str, progress: &mut Progress) {
+    progress.flags.extend(turn.set.iter().cloned());
+    match &turn.node {
+        Some(node) => progress.conversations.insert(key.to_string(), node.clone()),
+        None => progress.conversations.remove(key),
+    };
+    if turn.give.is_empty() {
+        return;
+    }
+    let _ = Inventory::update(user, |inventory| {
+        for item in &turn.give {
+            inventory.receive(item, key);
+        }
+        Ok(())
+    });
+}
+
+/// What the NPC says and gives in a turn, without the choices.
+fn turn_lines(npc: &str, turn: &Turn) -> Vec<String> {
+    let mut lines: Vec<String> = turn
+        .said
+        .iter()
+        .map(|said| format!("{}: {said}", style::bold(npc)))
+        .collect();
+    lines.extend(
+        turn.give
+            .iter()
+            .map(|item| format!("{npc} gives you the {item}.")),
+    );
+    lines.extend(
+        turn.hints
+            .iter()
+            .map(|hint| style::dim(&format!("Hint: {hint}"))),
+    );
+    lines
+}
+
+/// Adds a turn to the lines of the conversation, with the choices or its end.
+fn show_turn(npc: &str, turn: &Turn, mut lines: Vec<String>) -> String {
+    lines.extend(turn_lines(npc, turn));
+    if turn.node.is_some() {
+        for (i, choice) in turn.choices.iter().enumerate() {
+            lines.push(format!("   {}. {choice}", i + 1));
+        }
+        lines.push(style::dim(&format!("Answer with `talk {npc} <number>`.")));
+    } else {
+        lines.push(style::dim(&format!("{npc} has nothing more to say.")));
+    }
+    lines.join("\n")
+}
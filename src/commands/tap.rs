@@ -1,6 +1,7 @@
 use super::argparser::ArgParser;
 use super::cmds::{check_dir_info, normalize_path};
 use super::display_relative_path;
+use crate::metainfo::hidden::Visibility;
 use crate::metainfo::info_reader::add_obj_to_info;
 use crate::metainfo::valid_sekai::create_dir_info;
 use crate::utils::log;
@@ -20,12 +21,18 @@ Examples:
 - tap new_dir/    # usage of trailing slash to create a directory
 "#;
 
-pub fn create_file(destination: &str, current_dir: &Path, root_dir: &Path) -> String {
+pub fn create_file(
+    destination: &str,
+    current_dir: &Path,
+    root_dir: &Path,
+) -> Result<String, String> {
     let new_path: &Path = &current_dir.join(destination);
 
     // Check if the path already exists
     if new_path.exists() {
-        return format!("tap: {destination}: File or directory already exists");
+        return Err(format!(
+            "tap: {destination}: File or directory already exists"
+        ));
     }
 
     // Create the file or directory
@@ -36,24 +43,28 @@ pub fn create_file(destination: &str, current_dir: &Path, root_dir: &Path) -> St
             let result = add_obj_to_info(new_path, obj_name, None).map_err(|e| e.to_string()); // Convert InfoError to String
 
             log::log_result("tap", result, "Adding object to info.json");
-            format!(
+            Ok(format!(
                 "Created file: {}",
                 display_relative_path(new_path, root_dir)
-            )
+            ))
         }
-        Err(e) => format!("tap: {destination}: {e}"),
+        Err(e) => Err(format!("tap: {destination}: {e}")),
     }
 }
 
-pub fn create_directory(destination: &str, current_dir: &Path, root_dir: &Path) -> String {
+pub fn create_directory(
+    destination: &str,
+    current_dir: &Path,
+    root_dir: &Path,
+) -> Result<String, String> {
     let new_path: &PathBuf = &current_dir.join(destination);
 
     // Check if the path already exists
     if new_path.exists() {
-        return format!(
+        return Err(format!(
             "tap: {}: Directory already exists",
             display_relative_path(new_path, root_dir)
-        );
+        ));
     }
 
     // Create the directory
@@ -61,10 +72,10 @@ pub fn create_directory(destination: &str, current_dir: &Path, root_dir: &Path)
         Ok(_) => {
             // create .dir_info automatically
             if !create_dir_info(new_path, false) {
-                return format!(
+                return Err(format!(
                     "tap: Failed to create .dir_info: {}",
                     display_relative_path(new_path, root_dir)
-                );
+                ));
             }
 
             // Add the object to info.json
@@ -72,12 +83,16 @@ pub fn create_directory(destination: &str, current_dir: &Path, root_dir: &Path)
             let result = add_obj_to_info(new_path, obj_name, None).map_err(|e| e.to_string()); // Convert InfoError to String
 
             log::log_result("tap", result, "Adding object to info.json");
-            format!(
+            Ok(format!(
                 "Created directory: {}",
                 display_relative_path(new_path, root_dir)
-            )
+            ))
         }
-        Err(e) => format!("tap: {}: {}", display_relative_path(new_path, root_dir), e),
+        Err(e) => Err(format!(
+            "tap: {}: {}",
+            display_relative_path(new_path, root_dir),
+            e
+        )),
     }
 }
 
@@ -86,6 +101,7 @@ fn handle_destination(
     destination: &Path,
     current_dir: &Path,
     root_dir: &Path,
+    visibility: &Visibility,
 ) -> Result<PathBuf, String> {
     // Get absolute path by joining with current_dir and normalizing
     let mut new_path = current_dir.join(destination);
@@ -100,6 +116,14 @@ fn handle_destination(
             destination.display()
         ));
     }
+    // Hidden objects, and what is inside them, must not give themselves away
+    if visibility.is_hidden(&new_path, root_dir) {
+        log::log_warning("tap", "Attempted to create a hidden object or inside one");
+        return Err(format!(
+            "tap: {}: Operation not permitted",
+            destination.display()
+        ));
+    }
     // Check if path already exists
     if new_path.exists() {
         log::log_warning("tap", "File or directory already exists");
@@ -113,7 +137,12 @@ fn handle_destination(
 }
 
 // Check if the destination is within the root directory
-pub fn tap(args: &[&str], current_dir: &Path, root_dir: &Path) -> String {
+pub fn tap(
+    args: &[&str],
+    current_dir: &Path,
+    root_dir: &Path,
+    visibility: &Visibility,
+) -> Result<String, String> {
     let valid_flags = ["-d", "--dir", "-h", "--help"];
     let mut parser = ArgParser::new(&valid_flags);
 
@@ -135,21 +164,22 @@ pub fn tap(args: &[&str], current_dir: &Path, root_dir: &Path) -> String {
                 .unwrap_or(&"") as &str;
 
             if destination.is_empty() {
-                return "tap: No destination specified. Use 'tap --help' for usage.".to_string();
+                return Err(
+                    "tap: No destination specified. Use 'tap --help' for usage.".to_string()
+                );
             }
             if check_dir_info(Path::new(destination)) {
-                return "tap: Cannot create/refer restricted files or directory. Operation Not Allowed."
-                    .to_string();
+                return Err(
+                    "tap: Cannot create/refer restricted files or directory. Operation Not Allowed."
+                        .to_string(),
+                );
             }
             // handle destination path
             let destination_path = Path::new(destination);
-            if handle_destination(destination_path, current_dir, root_dir).is_err() {
-                // the error string will be printed
-                return handle_destination(destination_path, current_dir, root_dir).unwrap_err();
-            } else {
-                // This is relative valid path
-                destination = destination_path.to_str().unwrap();
-            }
+            // the error string will be printed
+            handle_destination(destination_path, current_dir, root_dir, visibility)?;
+            // This is relative valid path
+            destination = destination_path.to_str().unwrap();
 
             if args.contains(&"-d") || args.contains(&"--dir") {
                 destination = destination.trim_end_matches('/');
@@ -163,9 +193,9 @@ pub fn tap(args: &[&str], current_dir: &Path, root_dir: &Path) -> String {
             }
         }
         Err(e) => match &e[..] {
-            "help" => HELP_TXT.to_string(),
-            "unknown" => "tap: unknown flag\nTry 'help tap' for more information.".to_string(),
-            _ => "Error parsing arguments. Try 'help tap' for more information.".to_string(),
+            "help" => Ok(HELP_TXT.to_string()),
+            "unknown" => Err("tap: unknown flag\nTry 'help tap' for more information.".to_string()),
+            _ => Err("Error parsing arguments. Try 'help tap' for more information.".to_string()),
         },
     }
 }
@@ -1,10 +1,17 @@
 use super::argparser::ArgParser;
-use super::cmds::normalize_path;
+use super::cmds::{CommandOutput, Payload, normalize_path};
+use super::display_relative_path;
+use crate::metainfo::hidden::Visibility;
 use crate::metainfo::info_reader::read_get_obj_info;
-use crate::metainfo::lock_perm::operation_locked_perm;
-use crate::metainfo::read_lock_perm;
+use crate::metainfo::lock_perm::{operation_locked_perm, read_player_lock_perm};
 use crate::rns::security::{argonhash, characterise_enc_key, decrypt, encrypt};
-use crate::utils::{auth::get_current_user, log, prompt::UserPrompter};
+use crate::utils::caller::Caller;
+use crate::utils::events::{self, ShellEvent};
+use crate::utils::find_root::home_relative;
+use crate::utils::progress::Progress;
+use crate::utils::rate_limit::{self, Action};
+use crate::utils::shell_history::{GUEST_USER, current_user};
+use crate::utils::{auth::load_users, log, prompt::UserPrompter};
 use argon2::password_hash::SaltString;
 use std::path::Path;
 
@@ -12,6 +19,8 @@ pub const HELP_TXT: &str = r#"
 Usage: unlock [OPTIONS] <LEVEL/CHEST_NAME>
 
 after obtaining a flag for a level you can use this command to unlock the level by using the flag 
+The flag is asked for, so that it is not kept in your history.
+Some locks open with an item instead: carry it (see `take`) and unlock without a flag.
 Options:
     -l, --level       Move instead of copy (cut/paste)
     -c, --chest       Unlock 
@@ -25,7 +34,8 @@ pub fn unlock(
     current_dir: &Path,
     root_dir: &Path,
     prompter: &mut dyn UserPrompter,
-) -> String {
+    visibility: &Visibility,
+) -> CommandOutput {
     //one argument giving path to the chest/level to be unlocked
     let mut parser = ArgParser::new(&["-l", "--level", "-c", "--chest"]);
     let args_string: Vec<String> = args.iter().map(|s| s.to_string()).collect();
@@ -40,22 +50,25 @@ pub fn unlock(
     );
     match parser.parse(&args_string, "unlock") {
         Ok(_) => {
-            let user_info = match get_current_user() {
-                Some(info) => info,
-                None => {
-                    err_msg += "User not authenticated. Please log in.";
-                    log::log_error("unlock", err_msg.as_str());
-                    return err_msg;
-                }
+            // The flag is checked against the account of the user the command runs for
+            let caller = Caller::current();
+            let Some(user) = load_users()
+                .into_iter()
+                .find(|user| user.username == caller.user && user.username != GUEST_USER)
+            else {
+                err_msg += "User not authenticated. Please log in.";
+                log::log_error("unlock", err_msg.as_str());
+                return CommandOutput::err(err_msg);
             };
-            let username = &user_info.username;
-            let user_salt_hex = &user_info.salt;
+            let username = &user.username;
+            let user_salt_hex = &user.salt;
 
             let pos_args = parser.get_positional_args();
             if pos_args.len() != 1 {
-                err_msg += "Exactly one positional argument -giving path to directory/file to be unlocked -is expected.";
+                err_msg +=
+                    "Expected exactly one argument: the path to the directory/file to be unlocked.";
                 log::log_info("unlock", err_msg.as_str());
-                return err_msg;
+                return CommandOutput::err(err_msg);
             }
             //now we know only 1 argument is there
             //validate path existence
@@ -63,24 +76,32 @@ pub fn unlock(
             if !target.exists() {
                 err_msg += "Invalid path given";
                 log::log_info("unlock", err_msg.as_str());
-                return err_msg;
+                return CommandOutput::err(err_msg);
             }
             //validated path. now check if it is accessible
             if let Err(msg) = operation_locked_perm(
                 target.parent().unwrap(),
+                root_dir,
+                &visibility.progress,
                 "unlock",
                 "you cannot try to unlock a chest/level nested inside a locked directory/level",
             ) {
                 err_msg += msg.as_str();
                 log::log_info("unlock", err_msg.as_str());
-                return err_msg;
+                return CommandOutput::err(err_msg);
             }
             //now check if it is a protected thing
-            if let Ok((is_level, is_locked)) = read_lock_perm(&target) {
+            if let Ok((is_level, is_locked)) =
+                read_player_lock_perm(&target, root_dir, &visibility.progress)
+            {
                 if !is_locked {
                     err_msg += "target is not locked, you can try accessing it directly.";
                     log::log_info("unlock", err_msg.as_str());
-                    return err_msg;
+                    return CommandOutput::err(err_msg);
+                }
+                //locks opened with an item instead of a flag
+                if let Some(item) = required_item(&target) {
+                    return unlock_with_item(&target, root_dir, &item, visibility);
                 }
                 //since it protected and open for unlocking read level/chest id
 
@@ -95,7 +116,7 @@ pub fn unlock(
                         locked_obj_name.err().unwrap()
                     );
                     log::log_error("unlock", err_msg.as_str());
-                    return err_msg;
+                    return CommandOutput::err(err_msg);
                 }
                 let locked_obj_name = locked_obj_name.unwrap();
 
@@ -109,7 +130,7 @@ pub fn unlock(
                         locked_obj_info.err().unwrap()
                     );
                     log::log_error("unlock", err_msg.as_str());
-                    return err_msg;
+                    return CommandOutput::err(err_msg);
                 }
                 let locked_obj_info = locked_obj_info.unwrap();
                 let obj_salt = &locked_obj_info.properties["obj_salt"]
@@ -121,7 +142,7 @@ pub fn unlock(
                         obj_salt.as_ref().err().unwrap()
                     );
                     log::log_error("unlock", err_msg.as_str());
-                    return err_msg;
+                    return CommandOutput::err(err_msg);
                 }
                 let obj_salt = obj_salt.as_ref().unwrap();
                 //reads decrypt_me from info.json
@@ -133,12 +154,24 @@ pub fn unlock(
                         "Failed to get encrypted flag for the level/chest: {locked_obj_name}"
                     );
                     log::log_error("unlock", err_msg.as_str());
-                    return err_msg;
+                    return CommandOutput::err(err_msg);
                 }
                 let decrypt_me = decrypt_me.as_ref().unwrap();
+                let limit_keys = rate_limit::keys(username, caller.ip);
+                if let Err(msg) = rate_limit::check(Action::Flag, &limit_keys) {
+                    err_msg += &msg;
+                    log::log_info("unlock", err_msg.as_str());
+                    return CommandOutput::err(err_msg);
+                }
                 // take flag
                 let user_flag =
-                    prompter.input(format!("Enter the flag for {locked_obj_name}:").as_str());
+                    prompter.secret(format!("Enter the flag for {locked_obj_name}:").as_str());
+                // No flag is no guess
+                if user_flag.trim().is_empty() {
+                    err_msg += "No flag given. Cannot unlock.";
+                    log::log_info("unlock", err_msg.as_str());
+                    return CommandOutput::err(err_msg);
+                }
                 let compare_me = &locked_obj_info.properties["compare_me"]
                     .as_str()
                     .ok_or_else(|| "Invalid 'compare_me' property in info.json".to_string());
@@ -146,12 +179,12 @@ pub fn unlock(
                     err_msg +=
                         &format!("Failed to get compare_me for the level/chest: {locked_obj_name}");
                     log::log_error("unlock", err_msg.as_str());
-                    return err_msg;
+                    return CommandOutput::err(err_msg);
                 }
                 let compare_me = compare_me.as_ref().unwrap();
 
-                if is_level {
-                    if check_level(
+                let unlocked = if is_level {
+                    check_level(
                         user_flag,
                         locked_obj_name,
                         obj_salt,
@@ -159,44 +192,96 @@ pub fn unlock(
                         compare_me,
                         username,
                         user_salt_hex,
-                    ) {
-                        //update obj_info_lock_perm
-                        "{} is unlocked".to_string()
-                    } else {
-                        err_msg += "Invalid flag. Try again.";
-                        log::log_info("unlock", err_msg.as_str());
-                        err_msg
-                    }
+                    )
                 } else {
                     //is chest
-                    if check_chest(
+                    check_chest(
                         user_flag,
                         locked_obj_name,
                         obj_salt,
                         compare_me,
                         user_salt_hex,
-                    ) {
-                        //update obj_info_lock_perm
-                        " Chest {} is unlocked".to_string()
+                    )
+                };
+                let lockout = rate_limit::record(Action::Flag, &limit_keys, unlocked);
+                if unlocked {
+                    //the lock stays open for this player
+                    let message = if is_level {
+                        format!("{locked_obj_name} is unlocked")
                     } else {
-                        err_msg += "Invalid flag. Try again.";
-                        log::log_info("unlock", err_msg.as_str());
-                        err_msg
+                        format!("Chest {locked_obj_name} is unlocked")
+                    };
+                    opened(&target, root_dir, message)
+                } else {
+                    err_msg += "Invalid flag. Try again.";
+                    if let Some(lockout) = lockout {
+                        err_msg += &format!(" {lockout}");
                     }
+                    log::log_info("unlock", err_msg.as_str());
+                    CommandOutput::err(err_msg)
                 }
             } else {
                 err_msg += "Unable to read lock status of the given target. Cannot unlock.";
                 log::log_info("unlock", err_msg.as_str());
-                err_msg
+                CommandOutput::err(err_msg)
             }
         }
         Err(e) => match &e[..] {
-            "help" => HELP_TXT.to_string(),
-            _ => "Error parsing arguments. Try 'help unlock' for more information.".to_string(),
+            "help" => CommandOutput::ok(HELP_TXT),
+            _ => CommandOutput::err(
+                "Error parsing arguments. Try 'help unlock' for more information.",
+            ),
         },
     }
 }
 
+/// Item that opens the lock of `target`, from its `requires` property in info.json.
+fn required_item(target: &Path) -> Option<String> {
+    let name = target.file_name()?.to_str()?;
+    let info = read_get_obj_info(&target.parent()?.join(".dir_info/info.json"), name).ok()?;
+    info.properties
+        .get("requires")?
+        .as_str()
+        .map(|item| item.to_string())
+}
+
+/// Opens the lock of `target` for the player for good.
+fn opened(target: &Path, root_dir: &Path, message: String) -> CommandOutput {
+    let path = home_relative(target, root_dir);
+    Progress::update(&current_user(), |progress| {
+        progress.unlocked.insert(path.clone())
+    });
+    events::publish(
+        &current_user(),
+        root_dir,
+        ShellEvent::ObjectUnlocked { path },
+    );
+    CommandOutput::ok(message).with_payload(Payload::Lock {
+        path: display_relative_path(target, root_dir),
+    })
+}
+
+/// Opens a lock that needs an item, if the player carries it.
+fn unlock_with_item(
+    target: &Path,
+    root_dir: &Path,
+    item: &str,
+    visibility: &Visibility,
+) -> CommandOutput {
+    let name = target.file_name().unwrap_or_default().to_string_lossy();
+    if !visibility.inventory.carries(item) {
+        let msg = format!("unlock: {name} needs a {item}. Find it and take it first.");
+        log::log_info("unlock", &msg);
+        return CommandOutput::err(msg);
+    }
+    log::log_info("unlock", &format!("{name} unlocked with {item}"));
+    opened(
+        target,
+        root_dir,
+        format!("{name} is unlocked with the {item}"),
+    )
+}
+
 fn check_level(
     user_flag: String,
     level_name: &str,
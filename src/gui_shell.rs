@@ -1,11 +1,25 @@
-use crate::commands::cmds::{CommandResult, cmd_manager};
-use crate::commands::ls::list_directory_entries;
+use crate::commands::cmds::{self, CommandResult, Payload};
 use crate::keys::key_to_char;
 use crate::menu;
 use crate::menu::menu_options::MenuOption;
+use crate::metainfo::hidden::Visibility;
 use crate::metainfo::info_reader::read_validate_info;
+use crate::metainfo::objectives::current;
 use crate::utils::config::{self, FONT_OPTIONS};
-use crate::utils::tab_completion::{TabCompletionResult, process_tab_completion};
+use crate::utils::events;
+use crate::utils::keymap::{Action, KeyChord, Keymap};
+use crate::utils::layout::{Layout, font_size_for_monitor, save_zoom};
+use crate::utils::line_editor::LineEditor;
+use crate::utils::progress::Progress;
+use crate::utils::prompt::ScriptPrompter;
+use crate::utils::room_panel::RoomPanel;
+use crate::utils::scrollback::{self, Match, Pager};
+use crate::utils::session::{Pace, Replay};
+use crate::utils::style::{self, Style};
+use crate::utils::tab_completion::{
+    TabCompletionResult, completions, process_tab_completion, split_for_completion,
+};
+use crate::utils::theme::{Rgb, Theme, current_theme};
 use crate::utils::{find_root, shell_history, wrapit::wrapit};
 use crate::utils::{log, prompt::UserPrompter};
 use raylib::ffi::{
@@ -21,38 +35,80 @@ use std::{
     mem::take,
     os::raw::c_int,
     path::{Path, PathBuf},
+    sync::Arc,
+    sync::atomic::{AtomicBool, Ordering},
 };
 use textwrap::wrap;
 
+/// Search through the output (Ctrl+F).
+struct OutputSearch {
+    query: String,
+    matches: Vec<Match>,
+    /// Index into `matches` of the selected one.
+    current: Option<usize>,
+    /// Whether the query is being typed. The input line is put aside meanwhile.
+    editing: bool,
+    saved_input: String,
+}
+
 pub struct ShellScreen<'a> {
     rl: &'a mut RaylibHandle,
     thread: &'a RaylibThread,
-    input_buffer: String,
+    input: LineEditor,
     working_buffer: Option<String>,
     output_lines: Vec<String>,
     current_dir: PathBuf,
     root_dir: PathBuf,
     font: ffi::Font,
-    window_width: i32,
-    window_height: i32,
-    char_width: f32,
-    term_split_ratio: f32,
-    font_size: f32,
+    layout: Layout,
+    /// Monitor and DPI scale the layout was computed for.
+    monitor: i32,
+    dpi_scale: f32,
     scroll_offset: i32,
     active_prompt: Option<String>,
     history_index: Option<usize>,
-    cursor_pos: usize,
+    search_query: Option<String>, // Reverse incremental search (Ctrl+R)
+    search_index: Option<usize>,
     selection_start: Option<(usize, usize)>, // (line_index, char_index)
     selection_end: Option<(usize, usize)>,
     mouse_dragging: bool,
+    keymap: Keymap,
+    output_search: Option<OutputSearch>,
+    scroll_to_match: bool,
+    pager: Option<Pager>,
+    scrollback_limit: usize,
+    room_panel: RoomPanel,
+    progress: Progress,
+    /// Set by the event bus when the player did something, for the panel to show it.
+    panel_stale: Arc<AtomicBool>,
+    /// Id of the subscriber setting `panel_stale`.
+    subscription: usize,
+    /// Notifications shown over the side panel, with the time they go away.
+    notifications: Vec<(String, f64)>,
+    /// Session being replayed, with the time its next command is due.
+    replay: Option<(Replay, f64)>,
+    /// Answers to the prompts of the command being replayed.
+    replay_answers: Option<ScriptPrompter>,
 }
 
 impl UserPrompter for ShellScreen<'_> {
     fn confirm(&mut self, message: &str) -> bool {
-        self.prompt_yes_no(message)
+        match self.replay_answers.as_mut() {
+            Some(answers) => answers.confirm(message),
+            None => self.prompt_yes_no(message),
+        }
     }
     fn input(&mut self, message: &str) -> String {
-        self.prompt_input_text(message)
+        match self.replay_answers.as_mut() {
+            Some(answers) => answers.input(message),
+            None => self.prompt_input_text(message),
+        }
+    }
+}
+
+impl Drop for ShellScreen<'_> {
+    fn drop(&mut self) {
+        events::unsubscribe(self.subscription);
     }
 }
 
@@ -71,6 +127,9 @@ Official Github Repo: https://github.com/databasedIISc/deemak
 
 pub const INITIAL_MSG: &str = "Type commands and press Enter. Try `help` for more info.";
 
+/// How long a notification stays on screen.
+const NOTIFICATION_SECS: f64 = 5.0;
+
 impl<'a> ShellScreen<'a> {
     pub fn new_sekai(
         rl: &'a mut RaylibHandle,
@@ -98,43 +157,69 @@ impl<'a> ShellScreen<'a> {
         unsafe {
             SetExitKey(0i32); // No exit key
         }
-        let window_width = rl.get_screen_width();
-        let window_height = rl.get_screen_height();
-        let char_width = unsafe {
+        // The fonts are monospaced, so one measure gives the width of every character
+        let glyph_ratio = unsafe {
             let cstr = CString::new("W").unwrap();
-            MeasureTextEx(font, cstr.as_ptr(), font_size, 1.2).x
+            MeasureTextEx(font, cstr.as_ptr(), font_size, 1.2).x / font_size
         };
         let root_dir =
             find_root::get_home(&sekai_dir).expect("Could not find sekai home directory");
+        let config = config::load_config();
+        let layout = Layout::new(
+            rl.get_screen_width(),
+            rl.get_screen_height(),
+            font_size,
+            config.zoom,
+            glyph_ratio,
+        );
+        let dpi_scale = rl.get_window_scale_dpi().x;
+        let visibility = Visibility::current();
+        let panel_stale = Arc::new(AtomicBool::new(false));
+        let stale = panel_stale.clone();
+        let user = shell_history::current_user();
+        let subscription = events::subscribe(move |record| {
+            if record.user == user {
+                stale.store(true, Ordering::Relaxed);
+            }
+        });
         Self {
             rl,
             thread,
-            input_buffer: String::new(),
+            input: LineEditor::new(),
             output_lines: Vec::<String>::new(),
             working_buffer: None,
+            room_panel: RoomPanel::new(&root_dir, &root_dir, &visibility),
+            progress: visibility.progress,
+            panel_stale,
+            subscription,
+            notifications: Vec::new(),
+            replay: None,
+            replay_answers: None,
             root_dir: root_dir.clone(),
             current_dir: root_dir, // Both point to same path initially
             font,
-            font_size,
-            window_width,
-            window_height,
-            char_width,
-            term_split_ratio: 2.0 / 3.0,
+            layout,
+            monitor: get_current_monitor(),
+            dpi_scale,
             scroll_offset: 0,
             active_prompt: None,
             history_index: None,
-            cursor_pos: 0,
+            search_query: None,
+            search_index: None,
             selection_start: None,
             selection_end: None,
             mouse_dragging: false,
+            keymap: Keymap::from_config(&config.keybindings),
+            output_search: None,
+            scroll_to_match: false,
+            pager: None,
+            scrollback_limit: config.scrollback_lines,
         }
     }
 
     pub fn run(&mut self) {
         //add to output lines the banner
-        let limit: usize = ((self.window_width as f32 * (self.term_split_ratio - 0.12))
-            / self.char_width)
-            .floor() as usize;
+        let limit = self.layout.columns();
         let wrapped_banner = wrap(DEEMAK_BANNER, limit);
         let wrapped_initial = wrap(INITIAL_MSG, limit);
         self.output_lines
@@ -152,6 +237,15 @@ impl<'a> ShellScreen<'a> {
             unsafe { FIRST_RUN = false };
             self.output_lines
                 .extend(wrapped_home_about.into_iter().map(|c| c.into_owned()));
+            // Guided sekais, like the tutorial, start with their first objective
+            if let Some(objective) = current(&self.room_panel.objectives, &self.progress) {
+                let line = format!(
+                    "Objective: {}\nType `objectives` to see all of them.\n",
+                    objective.task
+                );
+                self.output_lines
+                    .extend(wrap(&line, limit).into_iter().map(|c| c.into_owned()));
+            }
         }
 
         while !self.window_should_close() {
@@ -160,6 +254,50 @@ impl<'a> ShellScreen<'a> {
         }
     }
 
+    /// Replays a recorded session in this shell, starting now.
+    pub fn start_replay(&mut self, replay: Replay) {
+        let message = match replay.pace {
+            Pace::Step => "Replaying a session. Press Enter for the next command.",
+            _ => "Replaying a session.",
+        };
+        self.output_lines.push(style::dim(message));
+        let due = self.rl.get_time() + replay.delay().as_secs_f64();
+        self.replay = Some((replay, due));
+    }
+
+    /// Runs the next command of the replayed session. In steps, only when `step` is set.
+    fn update_replay(&mut self, step: bool) {
+        let Some((replay, due)) = self.replay.as_mut() else {
+            return;
+        };
+        let now = self.rl.get_time();
+        let ready = match replay.pace {
+            Pace::Step => step,
+            Pace::Original => now >= *due,
+            Pace::Instant => true,
+        };
+        if !ready {
+            return;
+        }
+        let Some((entry, answers)) = replay.next_entry() else {
+            self.replay = None;
+            return;
+        };
+        *due = now + replay.delay().as_secs_f64();
+        let done = replay.is_done();
+        self.replay_answers = Some(answers);
+        self.process_shell_input(&entry.command);
+        if let Some(answers) = self.replay_answers.take() {
+            self.output_lines.extend(answers.transcript);
+        }
+        self.scroll_offset = 0;
+        if done {
+            self.replay = None;
+            self.output_lines
+                .push(style::dim("The replayed session is over."));
+        }
+    }
+
     pub fn window_should_close(&self) -> bool {
         self.rl.window_should_close()
     }
@@ -170,8 +308,8 @@ impl<'a> ShellScreen<'a> {
         let mouse_pos = self.rl.get_mouse_position();
 
         // Check if mouse is in the text area
-        let in_text_area = mouse_pos.x < self.window_width as f32 * self.term_split_ratio
-            && mouse_pos.y < self.window_height as f32;
+        let in_text_area =
+            mouse_pos.x < self.layout.split_x() && mouse_pos.y < self.layout.height as f32;
 
         if in_text_area
             && self
@@ -207,100 +345,101 @@ impl<'a> ShellScreen<'a> {
         // MOUSE END
 
         // Handle keyboard input
-        match self.rl.get_key_pressed() {
-            Some(KeyboardKey::KEY_ENTER) => {
-                let input = take(&mut self.input_buffer);
-                if !input.is_empty() {
-                    self.process_shell_input(&input);
-                    self.scroll_offset = 0;
-                    shell_history::add_to_history(&input);
-                    self.history_index = None;
-                    self.working_buffer = None; // Clear working buffer after command execution
-                } else {
-                    // If input is empty, just add a new line
-                    if !unsafe { FIRST_RUN } {
-                        self.output_lines.push("> ".to_string());
-                    } else {
-                        unsafe { FIRST_RUN = false };
-                    }
-                }
-                self.cursor_pos = 0; // Reset cursor position
+        if let Some(chord) = KeyChord::pressed(self.rl) {
+            if self.pager.is_some() {
+                self.update_pager(chord);
+            } else if self.output_search.as_ref().is_some_and(|s| s.editing) {
+                self.update_output_search(chord);
+            } else if self.search_query.is_some() {
+                self.update_history_search(chord);
+            } else if chord.key == KeyboardKey::KEY_ESCAPE && self.output_search.is_some() {
+                // Clear the highlighted matches
+                self.output_search = None;
+            } else if let Some(action) = self.keymap.action(&chord) {
+                self.run_action(action);
+            } else if !chord.ctrl
+                && let Some(c) = key_to_char(chord.key, chord.shift)
+            {
+                self.input.insert(c);
             }
-            Some(KeyboardKey::KEY_BACKSPACE) => {
-                if !self.input_buffer.is_empty() && self.cursor_pos > 0 {
-                    self.input_buffer.remove(self.cursor_pos - 1);
-                    self.cursor_pos -= 1;
-                }
+        }
+
+        self.update_layout();
+        self.update_replay(false);
+
+        // Handle scroll
+        let scroll_y = self.rl.get_mouse_wheel_move();
+        if scroll_y != 0.0 {
+            let pager_height = self.pager_height();
+            match self.pager.as_mut() {
+                Some(pager) => pager.scroll(-(scroll_y * 3.0) as isize, pager_height),
+                None => self.scroll_offset -= (scroll_y / 2.00) as i32,
             }
-            Some(KeyboardKey::KEY_TAB) => {
-                // Get current command parts
-                let parts: Vec<&str> = self.input_buffer.split_whitespace().collect();
-
-                if parts.len() > 1 {
-                    // Get the last part (what we're trying to complete)
-                    let last_part = parts.last().unwrap();
-
-                    // List directory contents
-                    let (files, dirs) = list_directory_entries(&self.current_dir, &self.root_dir);
-                    let all_matches = [dirs, files].concat();
-
-                    // Find matches
-                    let matches: Vec<String> = all_matches
-                        .iter()
-                        .filter(|&name| name.starts_with(last_part))
-                        .cloned()
-                        .collect();
-
-                    // Calculate terminal dimensions
-                    let term_width = ((self.window_width as f32 * (self.term_split_ratio - 0.12))
-                        / self.char_width)
-                        .floor() as usize;
-                    let term_height = (self.window_height / self.font_size as i32) as usize;
-
-                    // Process tab completion
-                    let result = process_tab_completion(
-                        parts,
-                        matches,
-                        term_width,
-                        term_height,
-                        &self.input_buffer,
-                        self.active_prompt.as_deref(),
-                    );
-
-                    match result {
-                        TabCompletionResult::SingleMatch(new_input) => {
-                            self.input_buffer = new_input;
-                        }
-                        TabCompletionResult::CommonPrefix(new_input) => {
-                            self.input_buffer = new_input;
-                        }
-                        TabCompletionResult::DisplayCompletions {
-                            current_line,
-                            completion_lines,
-                            should_display_all,
-                        } => {
-                            self.output_lines.push(current_line);
-                            if should_display_all {
-                                if self.prompt_yes_no(&format!(
-                                    "Display all {} possibilities? (y or n)",
-                                    completion_lines.len()
-                                )) {
-                                    self.output_lines.extend(completion_lines);
-                                }
-                            } else {
+        }
+
+        self.trim_scrollback();
+    }
+
+    /// Runs a shell action bound in the keymap.
+    fn run_action(&mut self, action: Action) {
+        match action {
+            Action::Submit => {
+                self.submit_input();
+            }
+            Action::Backspace => self.input.backspace(),
+            Action::Delete => self.input.delete(),
+            Action::Complete => {
+                // Get current command parts, the last one is what we're trying to complete
+                let parts = split_for_completion(self.input.line());
+                let matches = completions(&parts, &self.current_dir, &self.root_dir);
+
+                // Calculate terminal dimensions
+                let term_width = self.layout.columns();
+                let term_height = self.layout.rows() as usize;
+
+                // Process tab completion
+                let result = process_tab_completion(
+                    parts,
+                    matches,
+                    term_width,
+                    term_height,
+                    self.input.line(),
+                    self.active_prompt.as_deref(),
+                );
+
+                match result {
+                    TabCompletionResult::SingleMatch(new_input) => {
+                        self.input.set_line(new_input);
+                    }
+                    TabCompletionResult::CommonPrefix(new_input) => {
+                        self.input.set_line(new_input);
+                    }
+                    TabCompletionResult::DisplayCompletions {
+                        current_line,
+                        completion_lines,
+                        should_display_all,
+                    } => {
+                        self.output_lines.push(current_line);
+                        if should_display_all {
+                            if self.prompt_yes_no(&format!(
+                                "Display all {} possibilities? (y or n)",
+                                completion_lines.len()
+                            )) {
                                 self.output_lines.extend(completion_lines);
                             }
-                            self.scroll_offset = 0;
+                        } else {
+                            self.output_lines.extend(completion_lines);
                         }
-                        TabCompletionResult::NoAction => {}
+                        self.scroll_offset = 0;
                     }
+                    TabCompletionResult::NoAction => {}
                 }
-                self.cursor_pos = self.input_buffer.len(); // Move cursor to end after tab
+                self.input.move_end(); // Move cursor to end after tab
             }
-            Some(KeyboardKey::KEY_UP) => {
+            Action::HistoryPrev => {
                 // Save current buffer if we're starting history navigation
-                if self.history_index.is_none() && !self.input_buffer.is_empty() {
-                    self.working_buffer = Some(self.input_buffer.clone());
+                if self.history_index.is_none() && !self.input.is_empty() {
+                    self.working_buffer = Some(self.input.line().to_string());
                 }
 
                 let history = shell_history::get_history();
@@ -310,123 +449,385 @@ impl<'a> ShellScreen<'a> {
                         Some(index) => index,      // already at first item
                         None => history.len() - 1, // start from most recent
                     };
-                    self.input_buffer = history[new_index].clone();
+                    // Places the cursor at the end of the command
+                    self.input.set_line(history[new_index].clone());
                     self.history_index = Some(new_index);
                 }
-                self.cursor_pos = self.input_buffer.len(); //place at the end of the command 
             }
-            Some(KeyboardKey::KEY_DOWN) => {
+            Action::HistoryNext => {
                 if let Some(index) = self.history_index {
                     let history = shell_history::get_history();
                     if index < history.len() - 1 {
                         // Move to next item in history
                         let new_index = index + 1;
-                        self.input_buffer = history[new_index].clone();
+                        self.input.set_line(history[new_index].clone());
                         self.history_index = Some(new_index);
                     } else {
                         // Reached the end of history - restore working buffer
-                        self.input_buffer = self.working_buffer.take().unwrap_or_default();
+                        self.input
+                            .set_line(self.working_buffer.take().unwrap_or_default());
                         self.history_index = None;
                     }
-                    self.cursor_pos = self.input_buffer.len();
                 }
             }
-            Some(KeyboardKey::KEY_LEFT) => {
-                if self.cursor_pos > 0 {
-                    self.cursor_pos -= 1;
+            Action::CursorLeft => self.input.move_left(),
+            Action::CursorRight => self.input.move_right(),
+            Action::WordLeft => self.input.word_left(),
+            Action::WordRight => self.input.word_right(),
+            Action::LineStart => self.input.move_home(),
+            Action::LineEnd => self.input.move_end(),
+            Action::KillWord => self.input.kill_word(),
+            Action::KillToStart => self.input.kill_to_start(),
+            Action::KillToEnd => self.input.kill_to_end(),
+            Action::Yank => self.input.yank(),
+            Action::PageUp => self.scroll_offset -= self.page_height(),
+            Action::PageDown => {
+                self.scroll_offset = min(0, self.scroll_offset + self.page_height())
+            }
+            Action::Find => self.start_output_search(),
+            Action::FindNext => self.step_output_search(-1),
+            Action::FindPrevious => self.step_output_search(1),
+            Action::ZoomIn | Action::ZoomOut | Action::ZoomReset => self.zoom(action),
+            Action::ClearScreen => {
+                // Clear the output, keeping the line being typed
+                self.output_lines.clear();
+                self.output_lines.push(INITIAL_MSG.to_string());
+                self.output_search = None;
+                // Clear selection
+                self.selection_start = None;
+                self.selection_end = None;
+            }
+            Action::Interrupt => {
+                if let (Some(start), Some(end)) = (self.selection_start, self.selection_end) {
+                    // Copy selected text
+                    self.copy_selected_text(start, end);
+                } else {
+                    // Next prompt (original behavior)
+                    self.output_lines.push(format!("> {}", self.input.take()));
+                    self.working_buffer = None;
+                    self.scroll_offset = 0;
                 }
             }
-            Some(KeyboardKey::KEY_RIGHT) => {
-                if self.cursor_pos < self.input_buffer.len() {
-                    self.cursor_pos += 1;
+            Action::HistorySearch => {
+                // Start reverse incremental search through history
+                self.working_buffer = Some(self.input.line().to_string());
+                self.search_query = Some(String::new());
+                self.search_index = None;
+                self.refresh_history_search();
+            }
+            Action::Paste => {
+                // Paste from clipboard
+                let clipboard_text = self.rl.get_clipboard_text().unwrap_or_default();
+                if !clipboard_text.is_empty() {
+                    // Remove newlines and carriage returns
+                    let filtered_text = clipboard_text.replace(['\n', '\r'], "");
+                    self.input.insert_str(&filtered_text);
                 }
             }
-            Some(key) => {
-                let ctrl_pressed = self.rl.is_key_down(KeyboardKey::KEY_LEFT_CONTROL)
-                    || self.rl.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL);
-
-                if ctrl_pressed {
-                    match key {
-                        KeyboardKey::KEY_K => {
-                            // Clear input buffer and reset history index
-                            self.output_lines.clear();
-                            self.output_lines.push(INITIAL_MSG.to_string());
-                            self.working_buffer = None;
-                            self.cursor_pos = 0;
-                            // Clear selection
-                            self.selection_start = None;
-                            self.selection_end = None;
-                        }
-                        KeyboardKey::KEY_C => {
-                            if let (Some(start), Some(end)) =
-                                (self.selection_start, self.selection_end)
-                            {
-                                // Copy selected text
-                                self.copy_selected_text(start, end);
-                            } else {
-                                // Next prompt (original behavior)
-                                self.output_lines.push(format!("> {}", self.input_buffer));
-                                self.working_buffer = None;
-                                self.input_buffer.clear();
-                                self.scroll_offset = 0;
-                                self.cursor_pos = 0;
-                            }
-                        }
-                        KeyboardKey::KEY_V => {
-                            // Paste from clipboard
-                            let clipboard_text = self.rl.get_clipboard_text().unwrap_or_default();
-                            if !clipboard_text.is_empty() {
-                                // Remove newlines and carriage returns
-                                let filtered_text = clipboard_text.replace(['\n', '\r'], "");
-                                self.input_buffer
-                                    .insert_str(self.cursor_pos, &filtered_text);
-                                self.cursor_pos += filtered_text.len();
-                            }
-                        }
-                        _ => {}
-                    }
-                } else {
-                    // Handle regular key input when Ctrl is not pressed
-                    let shift = self.rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT)
-                        || self.rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+        }
+    }
 
-                    if let Some(c) = key_to_char(key, shift) {
-                        self.input_buffer.insert(self.cursor_pos, c);
-                        self.cursor_pos += 1;
+    /// Runs the current input line, expanding history references first.
+    fn submit_input(&mut self) {
+        let input = self.input.take();
+        if input.is_empty() && self.replay.is_some() {
+            self.update_replay(true);
+        } else if !input.is_empty() {
+            match shell_history::expand_history(&input, &shell_history::get_history()) {
+                Ok(expanded) => {
+                    shell_history::add_to_history(&expanded);
+                    self.process_shell_input(&expanded);
+                }
+                Err(e) => {
+                    self.output_lines.push(format!("> {input}"));
+                    self.output_lines.push(e);
+                }
+            }
+            self.scroll_offset = 0;
+            self.history_index = None;
+            self.working_buffer = None; // Clear working buffer after command execution
+        } else {
+            // If input is empty, just add a new line
+            if !unsafe { FIRST_RUN } {
+                self.output_lines.push("> ".to_string());
+            } else {
+                unsafe { FIRST_RUN = false };
+            }
+        }
+    }
+
+    /// Handles a key press while reverse incremental search is active.
+    fn update_history_search(&mut self, chord: KeyChord) {
+        let history = shell_history::get_history();
+
+        match self.keymap.action(&chord) {
+            // Search further back for the same query
+            Some(Action::HistorySearch) => {
+                let query = self.search_query.clone().unwrap_or_default();
+                let before = self.search_index.unwrap_or(history.len());
+                if let Some(index) = shell_history::reverse_search(&history, &query, before) {
+                    self.search_index = Some(index);
+                }
+                self.refresh_history_search();
+            }
+            // Abort the search and restore the original line
+            _ if chord.key == KeyboardKey::KEY_ESCAPE
+                || chord == KeyChord::ctrl(KeyboardKey::KEY_G) =>
+            {
+                self.end_history_search();
+                self.input
+                    .set_line(self.working_buffer.take().unwrap_or_default());
+            }
+            Some(Action::Backspace) => {
+                if let Some(query) = self.search_query.as_mut() {
+                    query.pop();
+                }
+                let query = self.search_query.clone().unwrap_or_default();
+                self.search_index = shell_history::reverse_search(&history, &query, history.len());
+                self.refresh_history_search();
+            }
+            // Accept the match and run it
+            Some(Action::Submit) => {
+                self.end_history_search();
+                self.submit_input();
+            }
+            _ => {
+                match key_to_char(chord.key, chord.shift) {
+                    Some(c) if !chord.ctrl => {
+                        let query = self.search_query.get_or_insert_with(String::new);
+                        query.push(c);
+                        let query = query.clone();
+                        self.search_index =
+                            shell_history::reverse_search(&history, &query, history.len());
+                        self.refresh_history_search();
                     }
+                    // Any other key accepts the match for editing
+                    _ => self.end_history_search(),
                 }
             }
-            None => {}
         }
+    }
 
-        // Handle window re-size
-        if self.rl.is_window_resized() {
-            self.window_width = self.rl.get_screen_width();
+    /// Updates the prompt and input line to show the current search match.
+    fn refresh_history_search(&mut self) {
+        let query = self.search_query.clone().unwrap_or_default();
+        let history = shell_history::get_history();
+        let found = self.search_index.and_then(|i| history.get(i));
+        let label = if found.is_none() && !query.is_empty() {
+            "failed reverse-i-search"
+        } else {
+            "reverse-i-search"
+        };
+        self.active_prompt = Some(format!("({label})`{query}':"));
+        match found {
+            Some(cmd) => self.input.set_line(cmd.clone()),
+            None => self.input.move_end(),
         }
+    }
 
-        // Handle scroll
-        let scroll_y = self.rl.get_mouse_wheel_move();
-        if scroll_y != 0.0 {
-            self.scroll_offset -= (scroll_y / 2.00) as i32;
+    /// Leaves reverse search, keeping the matched line in the input buffer.
+    fn end_history_search(&mut self) {
+        self.search_query = None;
+        self.search_index = None;
+        self.active_prompt = None;
+        self.history_index = None;
+        self.input.move_end();
+    }
+
+    /// Follows changes of the window size and moves to a monitor with another size or DPI.
+    fn update_layout(&mut self) {
+        let monitor = get_current_monitor();
+        let dpi_scale = self.rl.get_window_scale_dpi().x;
+        if monitor != self.monitor || dpi_scale != self.dpi_scale {
+            self.monitor = monitor;
+            self.dpi_scale = dpi_scale;
+            self.layout.base_font_size = font_size_for_monitor(get_monitor_width(monitor));
+        }
+        self.layout
+            .resize(self.rl.get_screen_width(), self.rl.get_screen_height());
+    }
+
+    /// Changes the zoom and saves it to the config.
+    fn zoom(&mut self, action: Action) {
+        match action {
+            Action::ZoomIn => self.layout.zoom_in(),
+            Action::ZoomOut => self.layout.zoom_out(),
+            _ => self.layout.reset_zoom(),
+        }
+        save_zoom(self.layout.zoom);
+    }
+
+    /// Lines scrolled by PageUp and PageDown.
+    fn page_height(&self) -> i32 {
+        max(1, self.layout.rows() - 3)
+    }
+
+    /// Lines of text shown by the pager, above its status line.
+    fn pager_height(&self) -> usize {
+        max(1, self.layout.rows() - 2) as usize
+    }
+
+    /// Drops the oldest output beyond the configured scrollback limit.
+    fn trim_scrollback(&mut self) {
+        if scrollback::trim_scrollback(&mut self.output_lines, self.scrollback_limit) > 0 {
+            // Line indices have moved
+            self.selection_start = None;
+            self.selection_end = None;
+            if let Some(search) = self.output_search.as_mut() {
+                search.matches = scrollback::find_matches(&self.output_lines, &search.query);
+                search.current = search.matches.len().checked_sub(1);
+            }
+        }
+    }
+
+    /// Shows `text` in the pager if it does not fit in the window.
+    fn open_pager(&mut self, title: &str, text: &str) {
+        let limit = self.layout.columns();
+        let lines: Vec<String> = text
+            .split('\n')
+            .flat_map(|line| style::wrap(line, limit))
+            .collect();
+        if lines.len() > self.pager_height() {
+            self.pager = Some(Pager::new(title, lines));
+        }
+    }
+
+    /// Handles a key press while the pager is open. The keys follow `less`.
+    fn update_pager(&mut self, chord: KeyChord) {
+        let height = self.pager_height();
+        let Some(pager) = self.pager.as_mut() else {
+            return;
+        };
+        match chord.key {
+            KeyboardKey::KEY_Q | KeyboardKey::KEY_ESCAPE => self.pager = None,
+            KeyboardKey::KEY_SPACE | KeyboardKey::KEY_PAGE_DOWN | KeyboardKey::KEY_F => {
+                pager.page_down(height)
+            }
+            KeyboardKey::KEY_B | KeyboardKey::KEY_PAGE_UP => pager.page_up(height),
+            KeyboardKey::KEY_DOWN | KeyboardKey::KEY_J | KeyboardKey::KEY_ENTER => {
+                pager.scroll(1, height)
+            }
+            KeyboardKey::KEY_UP | KeyboardKey::KEY_K => pager.scroll(-1, height),
+            KeyboardKey::KEY_G if chord.shift => pager.end(height),
+            KeyboardKey::KEY_HOME | KeyboardKey::KEY_G => pager.home(),
+            KeyboardKey::KEY_END => pager.end(height),
+            _ => {}
+        }
+    }
+
+    /// Starts searching the output. The input line is restored when the search ends.
+    fn start_output_search(&mut self) {
+        self.output_search = Some(OutputSearch {
+            query: String::new(),
+            matches: Vec::new(),
+            current: None,
+            editing: true,
+            saved_input: self.input.take(),
+        });
+        self.refresh_output_search();
+    }
+
+    /// Handles a key press while the search query is being typed.
+    fn update_output_search(&mut self, chord: KeyChord) {
+        match chord.key {
+            // Drop the search
+            KeyboardKey::KEY_ESCAPE => {
+                if let Some(search) = self.output_search.take() {
+                    self.input.set_line(search.saved_input);
+                }
+                self.active_prompt = None;
+            }
+            // Keep the matches highlighted, F3 and Shift+F3 move between them
+            KeyboardKey::KEY_ENTER => {
+                if let Some(search) = self.output_search.as_mut() {
+                    search.editing = false;
+                    self.input.set_line(take(&mut search.saved_input));
+                }
+                self.active_prompt = None;
+            }
+            _ => match self.keymap.action(&chord) {
+                Some(Action::Find | Action::FindNext | Action::HistoryPrev) => {
+                    self.step_output_search(-1)
+                }
+                Some(Action::FindPrevious | Action::HistoryNext) => self.step_output_search(1),
+                Some(action @ (Action::PageUp | Action::PageDown)) => self.run_action(action),
+                Some(Action::Backspace) => {
+                    if let Some(search) = self.output_search.as_mut() {
+                        search.query.pop();
+                    }
+                    self.refresh_output_search();
+                }
+                _ => {
+                    if let Some(c) = key_to_char(chord.key, chord.shift).filter(|_| !chord.ctrl)
+                        && let Some(search) = self.output_search.as_mut()
+                    {
+                        search.query.push(c);
+                        self.refresh_output_search();
+                    }
+                }
+            },
+        }
+    }
+
+    /// Searches the output again and selects the latest match.
+    fn refresh_output_search(&mut self) {
+        if let Some(search) = self.output_search.as_mut() {
+            search.matches = scrollback::find_matches(&self.output_lines, &search.query);
+            search.current = search.matches.len().checked_sub(1);
+            self.scroll_to_match = true;
         }
+        self.update_output_search_prompt();
+    }
+
+    /// Selects the match `delta` steps away, wrapping around. Negative steps go up.
+    fn step_output_search(&mut self, delta: isize) {
+        let Some(search) = self.output_search.as_mut() else {
+            self.start_output_search();
+            return;
+        };
+        // The output may have changed since the last step
+        let selected = search.current.and_then(|i| search.matches.get(i)).copied();
+        search.matches = scrollback::find_matches(&self.output_lines, &search.query);
+        let count = search.matches.len();
+        search.current = match selected.and_then(|m| search.matches.iter().position(|n| *n == m)) {
+            Some(index) => Some((index as isize + delta).rem_euclid(count as isize) as usize),
+            None => count.checked_sub(1),
+        };
+        self.scroll_to_match = true;
+        self.update_output_search_prompt();
+    }
+
+    fn update_output_search_prompt(&mut self) {
+        let Some(search) = self.output_search.as_ref().filter(|s| s.editing) else {
+            return;
+        };
+        let label = match search.current {
+            Some(index) => format!(
+                "find {}/{}",
+                search.matches.len() - index,
+                search.matches.len()
+            ),
+            None if search.query.is_empty() => "find".to_string(),
+            None => "failed find".to_string(),
+        };
+        self.active_prompt = Some(format!("({label})`{}':", search.query));
     }
 
     pub fn get_window_lines(&self) -> Vec<String> {
-        let char_width = self.char_width; // Assuming char_width is stored in the struct
-        let limit = ((self.window_width as f32 * (self.term_split_ratio - 0.12)) / char_width)
-            .floor() as usize;
+        let limit = self.layout.columns();
 
         let output_lines = &self.output_lines;
         let active_prompt = &self.active_prompt;
-        let input_buffer = &self.input_buffer;
+        let input_buffer = self.input.line();
 
         // build the lines
         let mut all_lines = Vec::<String>::new();
         for line in output_lines.iter() {
+            let line = style::strip(line);
             let lines = if line.len() > limit {
-                wrapit(line, limit)
+                wrapit(&line, limit)
             } else {
-                vec![line.to_string()]
+                vec![line]
             };
             all_lines.extend(lines);
         }
@@ -447,23 +848,37 @@ impl<'a> ShellScreen<'a> {
         all_lines
     }
     pub fn draw(&mut self) {
+        if self.pager.is_some() {
+            self.draw_pager();
+            return;
+        }
+
         // Draw output lines
-        let char_width = unsafe {
-            let cstr = CString::new("W").unwrap();
-            MeasureTextEx(self.font, cstr.as_ptr(), self.font_size, 1.2).x
-        };
-        let limit = ((self.window_width as f32 * (self.term_split_ratio - 0.12)) / char_width)
-            .floor() as usize;
-        let max_lines_on_screen = self.window_height / self.font_size as i32;
+        let char_width = self.layout.char_width();
+        let font_size = self.layout.font_size();
+        let limit = self.layout.columns();
+        let max_lines_on_screen = self.layout.rows();
 
         let mut visible_lines = Vec::<String>::new();
+        // First wrapped line of each output line, to place search matches
+        let mut line_starts = Vec::<usize>::with_capacity(self.output_lines.len());
         for line in self.output_lines.iter() {
-            let lines = if line.len() > limit {
-                wrapit(line, limit)
-            } else {
-                vec![line.to_string()]
-            };
-            visible_lines.extend(lines);
+            line_starts.push(visible_lines.len());
+            visible_lines.extend(style::wrap(line, limit));
+        }
+
+        // Bring the selected search match to the middle of the screen
+        let selected_match = self
+            .output_search
+            .as_ref()
+            .and_then(|search| search.current.and_then(|i| search.matches.get(i)))
+            .copied();
+        if take(&mut self.scroll_to_match)
+            && let Some(m) = selected_match.filter(|m| m.line < line_starts.len())
+        {
+            let row = (line_starts[m.line] + m.start / limit.max(1)) as i32;
+            let bottom_index = visible_lines.len() as i32 + 1 - max_lines_on_screen + 3;
+            self.scroll_offset = row - (max_lines_on_screen - 3) / 2 - bottom_index;
         }
 
         // Scroll offset is negative or zero. Clamp it to valid range.
@@ -471,14 +886,15 @@ impl<'a> ShellScreen<'a> {
         self.scroll_offset = max(self.scroll_offset, min_scroll_offset);
         self.scroll_offset = min(self.scroll_offset, 0); // Never go below bottom
 
+        let theme = current_theme();
         let mut d = self.rl.begin_drawing(self.thread);
-        d.clear_background(Color::BLACK);
+        d.clear_background(theme.background.color());
 
         // Input
         let input_line = if let Some(ref prompt) = self.active_prompt {
-            format!("{} {}", prompt, self.input_buffer)
+            format!("{} {}", prompt, self.input.line())
         } else {
-            format!("> {}", self.input_buffer)
+            format!("> {}", self.input.line())
         };
 
         let input_lines: Vec<String> = {
@@ -511,28 +927,24 @@ impl<'a> ShellScreen<'a> {
                 (end, start)
             };
 
-            let char_width = self.char_width;
-            let font_size = self.font_size;
-
             // Get all lines (including wrapped ones)
-            let limit = ((self.window_width as f32 * (self.term_split_ratio - 0.12)) / char_width)
-                .floor() as usize;
 
             let mut all_lines = Vec::<String>::new();
             for line in self.output_lines.iter() {
+                let line = style::strip(line);
                 let lines = if line.len() > limit {
-                    wrapit(line, limit)
+                    wrapit(&line, limit)
                 } else {
-                    vec![line.to_string()]
+                    vec![line]
                 };
                 all_lines.extend(lines);
             }
 
             // Add input lines
             let input_line = if let Some(ref prompt) = self.active_prompt {
-                format!("{} {}", prompt, self.input_buffer)
+                format!("{} {}", prompt, self.input.line())
             } else {
-                format!("> {}", self.input_buffer)
+                format!("> {}", self.input.line())
             };
 
             let input_lines: Vec<String> = wrapit(&input_line, limit)
@@ -544,9 +956,7 @@ impl<'a> ShellScreen<'a> {
 
             // Calculate visible range based on scroll
             let visible_start = (-self.scroll_offset).max(0) as usize;
-            let visible_end = (visible_start
-                + (self.window_height / self.font_size as i32) as usize)
-                .min(all_lines.len());
+            let visible_end = (visible_start + max_lines_on_screen as usize).min(all_lines.len());
 
             const MOUSE_OFFSET: usize = 12;
 
@@ -572,8 +982,7 @@ impl<'a> ShellScreen<'a> {
                         + (end_char as f32 * char_width)
                             .min(10.0 + ((line.len()) as f32 * char_width))
                         + MOUSE_OFFSET as f32;
-                    let y =
-                        10.0 + ((line_idx as i32 - (-self.scroll_offset)) as f32 * self.font_size);
+                    let y = 10.0 + ((line_idx as i32 - (-self.scroll_offset)) as f32 * font_size);
 
                     // Draw selection rectangle
                     unsafe {
@@ -581,8 +990,8 @@ impl<'a> ShellScreen<'a> {
                             start_x as c_int,
                             y as c_int,
                             (end_x - start_x) as c_int,
-                            self.font_size as c_int,
-                            ColorFromHSV(210.0, 0.5, 0.5), // Blue selection color
+                            font_size as c_int,
+                            theme.selection.ffi(),
                         );
                     }
                 }
@@ -590,31 +999,46 @@ impl<'a> ShellScreen<'a> {
         }
         // MOUSE TEXT SELECTION END
 
-        // When drawing text, we need to ensure it appears above the selection
-        for (i, line) in display_lines.iter().enumerate() {
-            unsafe {
-                let pos: Vector2 = Vector2 {
-                    x: 10.0,
-                    y: 10.0 + (i as f32 * self.font_size),
+        // Highlight search matches under the text
+        if let Some(search) = &self.output_search {
+            let limit = limit.max(1);
+            for m in search.matches.iter().filter(|m| m.line < line_starts.len()) {
+                let color = if Some(*m) == selected_match {
+                    theme.accent.alpha(140)
+                } else {
+                    theme.selection.color()
                 };
-                let content = CString::new(line.to_string()).unwrap();
-                DrawTextEx(
-                    self.font,
-                    content.as_ptr() as *const c_char,
-                    pos,
-                    self.font_size,
-                    1.2,
-                    ColorFromHSV(0.0, 0.0, 1.0),
-                );
+                // A match may continue on the next wrapped line
+                let mut start = m.start;
+                while start < m.start + m.len {
+                    let row = line_starts[m.line] + start / limit;
+                    let end = min(m.start + m.len, (start / limit + 1) * limit);
+                    if row >= index && row < visible_lines.len() {
+                        d.draw_rectangle(
+                            (10.0 + (start % limit) as f32 * char_width) as i32,
+                            (10.0 + (row - index) as f32 * font_size) as i32,
+                            ((end - start) as f32 * char_width) as i32,
+                            font_size as i32,
+                            color,
+                        );
+                    }
+                    start = end;
+                }
             }
         }
+
+        // When drawing text, we need to ensure it appears above the selection
+        for (i, line) in display_lines.iter().enumerate() {
+            let y = 10.0 + (i as f32 * font_size);
+            draw_styled_line(self.font, line, 10.0, y, font_size, &theme);
+        }
         //promt
 
         // '>' at the beginning of every line
         unsafe {
             let pos_cursr: Vector2 = Vector2 {
                 x: 10.0,
-                y: 10.0 + ((display_lines.len() - length_input) as f32 * self.font_size),
+                y: 10.0 + ((display_lines.len() - length_input) as f32 * font_size),
             };
             let content = CString::new(">").unwrap();
 
@@ -622,9 +1046,9 @@ impl<'a> ShellScreen<'a> {
                 self.font,
                 content.as_ptr() as *const c_char,
                 pos_cursr,
-                self.font_size,
+                font_size,
                 1.2,
-                ColorFromHSV(0.0, 0.0, 1.0),
+                theme.prompt.ffi(),
             );
         }
 
@@ -634,45 +1058,119 @@ impl<'a> ShellScreen<'a> {
         } else {
             "> ".to_string()
         };
-        let cursor_text = if self.cursor_pos <= self.input_buffer.len() {
-            format!("{}{}", cursor_prefix, &self.input_buffer[..self.cursor_pos])
-        } else {
-            format!("{}{}", cursor_prefix, &self.input_buffer)
-        };
+        let cursor_text = format!("{}{}", cursor_prefix, self.input.before_cursor());
         let cursor_line =
             display_lines.len() - length_input + wrapit(&cursor_text, limit).len() - 1;
         let cursor_x_offset = unsafe {
             let c_string = CString::new(cursor_text).unwrap();
-            (MeasureTextEx(self.font, c_string.as_ptr(), self.font_size, 1.2).x)
+            (MeasureTextEx(self.font, c_string.as_ptr(), font_size, 1.2).x)
                 % ((limit as f32 + 6.0) * char_width)
         };
         // Draw cursor
         unsafe {
             DrawRectangle(
                 (10.6 + cursor_x_offset) as c_int,
-                (10.0 + (cursor_line as f32 * self.font_size)) as c_int,
-                (char_width as f32 * 1.2) as c_int,
-                self.font_size as c_int,
-                ColorFromHSV(0.0, 0.0, 1.0),
+                (10.0 + (cursor_line as f32 * font_size)) as c_int,
+                (char_width * 1.2) as c_int,
+                font_size as c_int,
+                theme.foreground.ffi(),
             );
         }
 
         // DIVIDER
-        let divider_pos = self.term_split_ratio;
+        let divider_x = self.layout.split_x();
         unsafe {
             DrawLineEx(
                 Vector2 {
-                    x: self.window_width as f32 * divider_pos,
+                    x: divider_x,
                     y: 0.0,
                 },
                 Vector2 {
-                    x: self.window_width as f32 * divider_pos,
-                    y: self.window_height as f32,
+                    x: divider_x,
+                    y: self.layout.height as f32,
                 },
                 2.0,
                 ColorFromHSV(0.0, 0.0, 0.3),
             );
         }
+
+        // SIDE PANEL
+        let panel_x = divider_x + 10.0;
+        let panel_columns = ((self.layout.width as f32 - panel_x - 10.0) / char_width).max(1.0);
+        let panel_lines = self
+            .room_panel
+            .lines(&self.progress)
+            .into_iter()
+            .flat_map(|line| style::wrap(&line, panel_columns as usize))
+            .take(max_lines_on_screen.max(0) as usize);
+        for (i, line) in panel_lines.enumerate() {
+            let y = 10.0 + (i as f32 * font_size);
+            draw_styled_line(self.font, &line, panel_x, y, font_size, &theme);
+        }
+
+        // NOTIFICATIONS, at the bottom of the panel
+        let now = d.get_time();
+        self.notifications.retain(|(_, until)| *until > now);
+        let notification_lines: Vec<String> = self
+            .notifications
+            .iter()
+            .flat_map(|(notification, _)| style::wrap(notification, panel_columns as usize))
+            .collect();
+        if !notification_lines.is_empty() {
+            let height = notification_lines.len() as f32 * font_size + 10.0;
+            let top = self.layout.height as f32 - height - 10.0;
+            unsafe {
+                DrawRectangle(
+                    (panel_x - 5.0) as c_int,
+                    top as c_int,
+                    (self.layout.width as f32 - panel_x) as c_int,
+                    height as c_int,
+                    theme.selection.ffi(),
+                );
+            }
+            for (i, line) in notification_lines.iter().enumerate() {
+                let y = top + 5.0 + (i as f32 * font_size);
+                draw_styled_line(self.font, line, panel_x, y, font_size, &theme);
+            }
+        }
+    }
+
+    /// Draws the pager over the terminal, with a status line at the bottom.
+    fn draw_pager(&mut self) {
+        let height = self.pager_height();
+        let width = self.layout.split_x() as i32;
+        let font_size = self.layout.font_size();
+        let theme = current_theme();
+        let Some(pager) = &self.pager else {
+            return;
+        };
+        let mut d = self.rl.begin_drawing(self.thread);
+        d.clear_background(theme.background.color());
+
+        for (i, line) in pager.visible(height).iter().enumerate() {
+            let y = 10.0 + (i as f32 * font_size);
+            draw_styled_line(self.font, line, 10.0, y, font_size, &theme);
+        }
+
+        let y = 10.0 + (height as f32 * font_size);
+        d.draw_rectangle(
+            0,
+            y as i32,
+            width,
+            font_size as i32,
+            theme.foreground.color(),
+        );
+        let status = CString::new(pager.status(height)).unwrap();
+        unsafe {
+            DrawTextEx(
+                self.font,
+                status.as_ptr(),
+                Vector2 { x: 10.0, y },
+                font_size,
+                1.2,
+                theme.background.ffi(),
+            );
+        }
     }
 
     pub fn process_input(&mut self, mut input: &str, prefix: Option<&str>) -> Vec<String> {
@@ -697,23 +1195,48 @@ impl<'a> ShellScreen<'a> {
         // Parse and execute command
         let mut current_dir = self.current_dir.clone();
         let root_dir = self.root_dir.clone();
-        let parts: Vec<&str> = input.split_whitespace().collect();
-        match cmd_manager(&parts, &current_dir, &root_dir, self) {
-            CommandResult::ChangeDirectory(new_dir, message) => {
-                self.current_dir = new_dir;
+        let result = cmds::run_line(input, &current_dir, &root_dir, self);
+        if let CommandResult::ChangeDirectory(new_dir, _) = &result {
+            self.current_dir = new_dir.clone();
+        }
+        if self.panel_stale.swap(false, Ordering::Relaxed) {
+            let visibility = Visibility::current();
+            self.room_panel = RoomPanel::new(&self.current_dir, &self.root_dir, &visibility);
+            self.progress = visibility.progress;
+        }
+        if let CommandResult::Output(output) | CommandResult::ChangeDirectory(_, output) = &result {
+            if output.clear {
+                self.clear_output();
+            }
+            let until = self.rl.get_time() + NOTIFICATION_SECS;
+            self.notifications.extend(
+                output
+                    .notifications
+                    .iter()
+                    .map(|notification| (notification.clone(), until)),
+            );
+        }
+        match result {
+            CommandResult::ChangeDirectory(_, output) => {
                 self.output_lines
-                    .extend(message.split("\n").map(|s| s.to_string()));
+                    .extend(output.text().split("\n").map(|s| s.to_string()));
             }
             CommandResult::Output(output) => {
+                let text = output.text();
+                if let Some(Payload::Document { path }) = &output.payload {
+                    self.open_pager(path, &text);
+                }
                 self.output_lines
-                    .extend(output.split("\n").map(|s| s.to_string()));
-            }
-            CommandResult::Clear => {
-                self.output_lines.clear();
-                self.output_lines.push(INITIAL_MSG.to_string());
+                    .extend(text.split("\n").map(|s| s.to_string()));
             }
+            CommandResult::Clear => self.clear_output(),
             CommandResult::Exit => {
-                run_gui_loop(self.rl, self.thread, self.root_dir.clone(), self.font_size);
+                run_gui_loop(
+                    self.rl,
+                    self.thread,
+                    self.root_dir.clone(),
+                    self.layout.base_font_size,
+                );
             }
             CommandResult::NotFound => {
                 self.output_lines
@@ -722,9 +1245,15 @@ impl<'a> ShellScreen<'a> {
         }
     }
 
+    fn clear_output(&mut self) {
+        self.output_lines.clear();
+        self.output_lines.push(INITIAL_MSG.to_string());
+        self.output_search = None;
+    }
+
     pub fn prompt_yes_no(&mut self, message: &str) -> bool {
         self.active_prompt = Some(format!("{message} [y/N]"));
-        self.input_buffer.clear();
+        self.input.clear();
         self.draw();
 
         loop {
@@ -749,10 +1278,8 @@ impl<'a> ShellScreen<'a> {
     }
     pub fn prompt_input_text(&mut self, message: &str) -> String {
         self.active_prompt = Some(message.to_string());
-        self.input_buffer.clear();
+        self.input.clear();
         self.draw();
-        let excess = self.cursor_pos;
-        self.cursor_pos = 0;
 
         loop {
             self.update();
@@ -760,35 +1287,20 @@ impl<'a> ShellScreen<'a> {
 
             match self.rl.get_key_pressed() {
                 Some(KeyboardKey::KEY_ENTER) => {
-                    let input = take(&mut self.input_buffer);
+                    let input = self.input.take();
                     self.active_prompt = None;
                     self.output_lines.push(format!("{message}: {input}"));
-                    self.cursor_pos = 0;
                     return input;
                 }
-                Some(KeyboardKey::KEY_BACKSPACE) => {
-                    if !self.input_buffer.is_empty() && self.cursor_pos > 0 {
-                        self.input_buffer.remove(self.cursor_pos - 1);
-                        self.cursor_pos -= 1;
-                    }
-                }
-                Some(KeyboardKey::KEY_LEFT) => {
-                    if self.cursor_pos > 0 {
-                        self.cursor_pos -= 1;
-                    }
-                }
-                Some(KeyboardKey::KEY_RIGHT) => {
-                    if self.cursor_pos < self.input_buffer.len() {
-                        self.cursor_pos += 1;
-                    }
-                }
+                Some(KeyboardKey::KEY_BACKSPACE) => self.input.backspace(),
+                Some(KeyboardKey::KEY_LEFT) => self.input.move_left(),
+                Some(KeyboardKey::KEY_RIGHT) => self.input.move_right(),
                 Some(key) => {
                     let shift = self.rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT)
                         || self.rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
 
                     if let Some(c) = key_to_char(key, shift) {
-                        self.input_buffer.insert(self.cursor_pos, c);
-                        self.cursor_pos += 1;
+                        self.input.insert(c);
                     }
                 }
                 None => {}
@@ -801,7 +1313,8 @@ impl<'a> ShellScreen<'a> {
     fn get_char_index_at_pos(&self, pos: Vector2) -> Option<(usize, usize)> {
         let all_lines = self.get_window_lines();
         // Calculate which line we're on (accounting for scroll offset)
-        let line_index = ((pos.y - 10.0) / self.font_size).floor() as i32 + (-self.scroll_offset);
+        let line_index =
+            ((pos.y - 10.0) / self.layout.font_size()).floor() as i32 + (-self.scroll_offset);
         if line_index < 0 || line_index >= all_lines.len() as i32 {
             return None;
         }
@@ -809,7 +1322,7 @@ impl<'a> ShellScreen<'a> {
 
         // Calculate which character in the line
         let line = &all_lines[line_index];
-        let char_index = ((pos.x - 10.0) / self.char_width).floor() as usize;
+        let char_index = ((pos.x - 10.0) / self.layout.char_width()).floor() as usize;
         let char_index = char_index.min(line.len());
 
         Some((line_index, char_index))
@@ -852,6 +1365,55 @@ impl<'a> ShellScreen<'a> {
     }
 }
 
+/// Draws a line of styled output at the left edge of the terminal.
+fn draw_styled_line(font: ffi::Font, line: &str, x: f32, y: f32, font_size: f32, theme: &Theme) {
+    let mut x = x;
+    for (span_style, text) in style::spans(line) {
+        unsafe {
+            let pos: Vector2 = Vector2 { x, y };
+            let content = CString::new(text).unwrap();
+            let color = span_color(span_style, theme);
+            DrawTextEx(
+                font,
+                content.as_ptr() as *const c_char,
+                pos,
+                font_size,
+                1.2,
+                color,
+            );
+            // The font has no bold face, so bold text is drawn twice
+            if span_style.bold {
+                let bold_pos = Vector2 { x: x + 1.0, ..pos };
+                DrawTextEx(
+                    font,
+                    content.as_ptr() as *const c_char,
+                    bold_pos,
+                    font_size,
+                    1.2,
+                    color,
+                );
+            }
+            x += MeasureTextEx(font, content.as_ptr(), font_size, 1.2).x + 1.2;
+        }
+    }
+}
+
+/// Color used to draw a span of styled output.
+fn span_color(span_style: Style, theme: &Theme) -> raylib::ffi::Color {
+    let Rgb(r, g, b) = match span_style.color {
+        style::Color::Default => theme.foreground,
+        style::Color::Red => theme.error,
+        style::Color::Green => Rgb(95, 215, 95),
+        style::Color::Yellow => Rgb(255, 215, 95),
+        style::Color::Blue => Rgb(95, 175, 255),
+        style::Color::Magenta => Rgb(215, 135, 255),
+        style::Color::Cyan => Rgb(95, 215, 215),
+        style::Color::Gray => Rgb(150, 150, 150),
+    };
+    let a = if span_style.dim { 150 } else { 255 };
+    raylib::ffi::Color { r, g, b, a }
+}
+
 /// Runs the main GUI loop for the Sekai shell
 pub fn run_gui_loop(
     rl: &mut RaylibHandle,
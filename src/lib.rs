@@ -7,6 +7,7 @@ pub mod menu;
 pub mod metainfo;
 pub mod rns;
 pub mod server;
+pub mod tty_shell;
 pub mod utils;
 
 use std::sync::OnceLock;
@@ -1,5 +1,6 @@
 use crate::keys::key_to_char;
-use crate::utils::globals::{UserInfo, set_user_info};
+use crate::utils::auth::{login_with_password, register_user};
+use crate::utils::theme::current_theme;
 use raylib::ffi::{DrawTextEx, LoadFontEx, MeasureTextEx, SetExitKey, Vector2};
 use raylib::prelude::*;
 use std::ffi::CString;
@@ -236,7 +237,7 @@ impl FieldPair {
                 config.base_x as i32,
                 (pass_y + 90.0) as i32,
                 20,
-                Color::RED,
+                current_theme().error.color(),
             );
         }
     }
@@ -475,39 +476,23 @@ impl AuthHandler {
             && !fields.password.value.is_empty() {
                 let username = fields.username.value.trim();
                 let password = fields.password.value.trim();
-                if let Some(user) = users.iter().find(|u| u.username == username) {
-                    if crate::utils::auth::verify_password(
-                        &password.to_string(),
-                        &user.salt,
-                        &user.password_hash,
-                    ) {
-                        // Create and authenticate UserInfo
-                        let mut user_info = UserInfo::new(
-                            username.to_string(),
-                            user.salt.clone(),
-                            user.password_hash.clone(),
-                        );
-                        user_info.authenticate(); // Mark as authenticated with timestamp
-
-                        // Set global user info
-                        set_user_info(user_info).ok();
-                        return Some(true);
-                    } else {
-                        fields.password.warning = true;
-                        fields.password.warning_text = "Invalid password!".to_string();
-                    }
-                } else {
-                    fields.username.warning = true;
-                    fields.username.warning_text = "Username not found!".to_string();
+                // Same limits and audit as the web server
+                let response = login_with_password(username, password, None);
+                if response.status {
+                    return Some(true);
                 }
+                let field = if users.iter().any(|u| u.username == username) {
+                    &mut fields.password
+                } else {
+                    &mut fields.username
+                };
+                field.warning = true;
+                field.warning_text = response.message;
             }
         None
     }
 
-    fn handle_register(
-        fields: &mut FieldPair,
-        users: &mut Vec<crate::utils::auth::User>,
-    ) -> Option<bool> {
+    fn handle_register(fields: &mut FieldPair) -> Option<bool> {
         if fields.username.entering {
             if !fields.username.value.is_empty() {
                 fields.username.entering = false;
@@ -518,39 +503,13 @@ impl AuthHandler {
             && !fields.password.value.is_empty() {
                 let username = fields.username.value.trim();
                 let password = fields.password.value.trim();
-                if users.iter().any(|u| u.username == username) {
-                    fields.username.warning = true;
-                    fields.username.warning_text = "Username already exists!".to_string();
-                } else {
-                    match crate::utils::auth::hash_password(password) {
-                        Ok((salt, hash)) => {
-                            users.push(crate::utils::auth::User {
-                                username: username.to_string(),
-                                salt: salt.clone(),
-                                password_hash: hash.clone(),
-                            });
-                            if let Err(_) =
-                                std::panic::catch_unwind(|| crate::utils::auth::save_users(users))
-                            {
-                                fields.username.warning = true;
-                                fields.username.warning_text = "Failed to save user!".to_string();
-                            } else {
-                                // Create and authenticate UserInfo
-                                let mut user_info =
-                                    UserInfo::new(username.to_string(), salt.clone(), hash.clone());
-                                user_info.authenticate(); // Mark as authenticated with timestamp
-
-                                // Set global user info
-                                set_user_info(user_info).ok();
-                                return Some(true);
-                            }
-                        }
-                        Err(_) => {
-                            fields.username.warning = true;
-                            fields.username.warning_text = "Failed to hash password!".to_string();
-                        }
-                    }
+                // Same limits and audit as the web server
+                let response = register_user(username, password, None);
+                if response.status {
+                    return Some(true);
                 }
+                fields.username.warning = true;
+                fields.username.warning_text = response.message;
             }
         None
     }
@@ -572,7 +531,7 @@ pub fn show_login(rl: &mut RaylibHandle, thread: &RaylibThread, _font_size: f32)
 
     // Load users and initialize components
     let users_result = std::panic::catch_unwind(crate::utils::auth::load_users);
-    let mut users = match users_result {
+    let users = match users_result {
         Ok(u) => u,
         Err(_) => {
             eprintln!("Failed to load users. User database may be corrupted.");
@@ -617,10 +576,9 @@ pub fn show_login(rl: &mut RaylibHandle, thread: &RaylibThread, _font_size: f32)
                             TabType::Login => {
                                 AuthHandler::handle_login(&mut tab_manager.login_fields, &users)
                             }
-                            TabType::Register => AuthHandler::handle_register(
-                                &mut tab_manager.register_fields,
-                                &mut users,
-                            ),
+                            TabType::Register => {
+                                AuthHandler::handle_register(&mut tab_manager.register_fields)
+                            }
                         };
                         if let Some(success) = result {
                             return success;
@@ -640,9 +598,10 @@ pub fn show_login(rl: &mut RaylibHandle, thread: &RaylibThread, _font_size: f32)
         }
 
         // Begin drawing UI
+        let theme = current_theme();
         let mut d = rl.begin_drawing(thread);
-        d.clear_background(Color::BLACK);
-        let highlight_color = Color::GOLD;
+        d.clear_background(theme.background.color());
+        let highlight_color = theme.accent.color();
 
         // Draw welcome text
         if animation.stream_index > 0 {
@@ -666,7 +625,7 @@ pub fn show_login(rl: &mut RaylibHandle, thread: &RaylibThread, _font_size: f32)
                 },
                 60.0,
                 2.0,
-                Color::WHITE,
+                theme.foreground.color(),
             );
         }
 
@@ -711,9 +670,9 @@ pub fn show_login(rl: &mut RaylibHandle, thread: &RaylibThread, _font_size: f32)
                 24.0,
                 1.0,
                 if tab_manager.active_tab == TabType::Login {
-                    Color::BLACK
+                    theme.background.color()
                 } else {
-                    Color::WHITE
+                    theme.foreground.color()
                 },
             );
             d.draw_text_ex(
@@ -726,9 +685,9 @@ pub fn show_login(rl: &mut RaylibHandle, thread: &RaylibThread, _font_size: f32)
                 24.0,
                 1.0,
                 if tab_manager.active_tab == TabType::Register {
-                    Color::BLACK
+                    theme.background.color()
                 } else {
-                    Color::WHITE
+                    theme.foreground.color()
                 },
             );
 
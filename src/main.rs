@@ -1,39 +1,213 @@
 #![allow(unused_variables, unused_mut, dead_code)]
 // Import everything from the library crate instead of declaring separate modules
+use clap::{CommandFactory, Parser, error::ErrorKind};
 use deemak::DEBUG_MODE;
-use deemak::gui_shell::run_gui_loop;
+use deemak::commands::run::{ScriptStatus, run_script};
+use deemak::gui_shell::{ShellScreen, run_gui_loop};
 use deemak::metainfo::valid_sekai::validate_or_create_sekai;
 use deemak::rns::restore_comp;
+use deemak::server::{ServerConfig, TlsPaths};
+use deemak::utils::alias::load_aliases;
+use deemak::utils::auth::load_users;
+use deemak::utils::caller::{Caller, as_caller};
+use deemak::utils::config::load_config;
 use deemak::utils::globals::set_world_dir;
-use deemak::utils::{debug_mode, find_root, log};
+use deemak::utils::layout::font_size_for_monitor;
+use deemak::utils::session::{self, Pace, Replay};
+use deemak::utils::{debug_mode, events, find_root, log, shell_history, style};
 use raylib::ffi::{SetConfigFlags, SetTargetFPS};
-use raylib::prelude::get_monitor_width;
+use raylib::prelude::{get_current_monitor, get_monitor_width};
+use std::io::IsTerminal;
+use std::net::IpAddr;
+use std::path::PathBuf;
 
-pub const HELP_TXT: &str = r#"
-Usage: deemak <sekai_directory> [--debug] [--web]
+/// DEEMAK Shell: explore a sekai in a window, in the terminal or in the browser.
+#[derive(Parser)]
+#[command(name = "deemak", version)]
+struct Cli {
+    /// Path to the Sekai directory to parse. With --web, defaults to the `sekai` of the
+    /// server config.
+    sekai: Option<PathBuf>,
+    /// Enable debug mode for more verbose logging.
+    #[arg(long)]
+    debug: bool,
+    /// Run the application in web mode, serving the frontend and its backend.
+    #[arg(long, conflicts_with = "tty")]
+    web: bool,
+    /// Play in the terminal instead of opening a window.
+    #[arg(long)]
+    tty: bool,
+    /// Run the commands in FILE without opening a window and exit.
+    #[arg(long, value_name = "FILE")]
+    script: Option<PathBuf>,
+    /// Record the commands you type, and what they print, to FILE.
+    #[arg(long, value_name = "FILE")]
+    record: Option<PathBuf>,
+    /// Replay a recorded session, at its original pace. With --tty, replays it in the
+    /// terminal and fails if any output changed.
+    #[arg(long, value_name = "FILE")]
+    replay: Option<PathBuf>,
+    /// Replay one command at a time, on Enter.
+    #[arg(long, requires = "replay", conflicts_with = "instant")]
+    step: bool,
+    /// Replay without pauses.
+    #[arg(long, requires = "replay")]
+    instant: bool,
+    /// Registered user to run --script, or replay with --tty, as. Guest by default.
+    #[arg(long, value_name = "NAME")]
+    user: Option<String>,
 
-Options:
-  <sekai_directory> [Required]  :   Path to the Sekai directory to parse.
-  --debug [Optional]            :   Enable debug mode for more verbose logging.
-  --web [Optional]              :   Run the application in web mode (requires a web server).
-"#;
+    /// Server config to use instead of ~/.config/deemak/server.json.
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires = "web",
+        help_heading = "Web server"
+    )]
+    server_config: Option<PathBuf>,
+    /// Address to listen on, like 0.0.0.0 for every interface.
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        requires = "web",
+        help_heading = "Web server"
+    )]
+    bind: Option<IpAddr>,
+    /// Port to listen on.
+    #[arg(long, requires = "web", help_heading = "Web server")]
+    port: Option<u16>,
+    /// TLS certificate chain, in PEM. Serves HTTPS with --tls-key.
+    #[arg(long, value_name = "FILE", requires_all = ["web", "tls_key"], help_heading = "Web server")]
+    tls_cert: Option<PathBuf>,
+    /// TLS private key, in PEM.
+    #[arg(long, value_name = "FILE", requires_all = ["web", "tls_cert"], help_heading = "Web server")]
+    tls_key: Option<PathBuf>,
+    /// Directory of the frontend files.
+    #[arg(
+        long,
+        value_name = "DIR",
+        requires = "web",
+        help_heading = "Web server"
+    )]
+    static_dir: Option<PathBuf>,
+    /// Origin allowed to call the backend from a browser. Can be repeated.
+    #[arg(
+        long = "cors-origin",
+        value_name = "ORIGIN",
+        requires = "web",
+        help_heading = "Web server"
+    )]
+    cors_origins: Vec<String>,
+    /// URL the browser reaches the backend at, like https://deemak.example.org.
+    #[arg(
+        long,
+        value_name = "URL",
+        requires = "web",
+        help_heading = "Web server"
+    )]
+    public_url: Option<String>,
+    /// Header the proxy in front of the server puts the client's IP in, like X-Real-IP.
+    #[arg(
+        long,
+        value_name = "HEADER",
+        requires = "web",
+        help_heading = "Web server"
+    )]
+    trusted_ip_header: Option<String>,
+}
+
+impl Cli {
+    /// Caller of the commands run without a login: the user of `--user`, or guest.
+    fn headless_caller(&self) -> Caller {
+        let user = match &self.user {
+            Some(user) if !load_users().iter().any(|u| &u.username == user) => {
+                eprintln!("Error: No registered user '{user}'.");
+                std::process::exit(1);
+            }
+            Some(user) => user.clone(),
+            None => shell_history::GUEST_USER.to_string(),
+        };
+        Caller { user, ip: None }
+    }
+
+    /// Server config from its file, with the flags given on top.
+    fn server_config(&self) -> Result<ServerConfig, String> {
+        let mut config = ServerConfig::load(self.server_config.as_deref())?;
+        if let Some(address) = self.bind {
+            config.address = address;
+        }
+        if let Some(port) = self.port {
+            config.port = port;
+        }
+        if let (Some(cert), Some(key)) = (&self.tls_cert, &self.tls_key) {
+            config.tls = Some(TlsPaths {
+                cert: cert.clone(),
+                key: key.clone(),
+            });
+        }
+        if let Some(static_dir) = &self.static_dir {
+            config.static_dir = static_dir.clone();
+        }
+        if !self.cors_origins.is_empty() {
+            config.cors_origins = self.cors_origins.clone();
+        }
+        if let Some(public_url) = &self.public_url {
+            config.public_url = Some(public_url.clone());
+        }
+        if let Some(header) = &self.trusted_ip_header {
+            config.trusted_ip_header = Some(header.clone());
+        }
+        Ok(config)
+    }
+}
 
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
-    // first argument is sekai name to parse
-    DEBUG_MODE
-        .set(args.iter().any(|arg| arg == "--debug"))
-        .expect("DEBUG_MODE already set");
+    let cli = Cli::parse();
+    DEBUG_MODE.set(cli.debug).expect("DEBUG_MODE already set");
     unsafe {
         if DEBUG_MODE.get().unwrap_or(&false) == &true {
             std::env::set_var("RUST_BACKTRACE", "1");
         }
     }
+    let mut log_config = load_config().log;
+    if debug_mode() {
+        log_config.level = log::Level::Debug;
+    }
+    log::init_file_logging(log_config, log::get_log_path());
     log::log_info("Application", "Starting DEEMAK Shell");
+    if debug_mode() {
+        events::subscribe(|record| log::log_debug("events", &record.to_json_line()));
+    }
 
-    let sekai_dir = if args.len() > 1 {
+    if cli.user.is_some() && cli.script.is_none() && !(cli.replay.is_some() && cli.tty) {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--user only applies to --script, and to --replay with --tty",
+            )
+            .exit();
+    }
+
+    let server_config = if cli.web {
+        match cli.server_config() {
+            Ok(config) => Some(config),
+            Err(e) => {
+                eprintln!("Error: Could not read the server config: {e}");
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+    let sekai_arg = cli.sekai.clone().or_else(|| {
+        server_config
+            .as_ref()
+            .and_then(|config| config.sekai.clone())
+    });
+
+    let sekai_dir = if let Some(sekai_arg) = sekai_arg {
         // get absolute path to the sekai directory
-        let sekai_path = std::env::current_dir().unwrap().join(&args[1]);
+        let sekai_path = std::env::current_dir().unwrap().join(sekai_arg);
         log::log_info(
             "SEKAI",
             &format!("Sekai directory provided: {sekai_path:?}"),
@@ -57,6 +231,7 @@ fn main() {
                 );
                 // Set the global Sekai directory
                 root_dir = Some(sekai_dir.clone());
+                load_aliases(&sekai_dir);
                 set_world_dir(sekai_dir);
             }
             Ok(None) => {
@@ -140,11 +315,14 @@ Continuing..."
         }
         Some(sekai_path)
     } else {
-        // args.len() == 1
+        // No sekai directory given
         log::log_error("Application", "Invalid arguments provided.");
-        eprintln!("Error: At least one argument is required.");
-        println!("{HELP_TXT}");
-        return;
+        Cli::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "the sekai directory is required",
+            )
+            .exit();
     };
 
     // If `save_me` already exists, then the sekai will be restored from it.
@@ -166,16 +344,103 @@ Continuing..."
 
     // NOTE: All Directory operations and variables settings should be done before this point.
     //
-    // We have 2 modes, the web and the raylib gui. The web argument runs it on the web, else
-    // raylib gui is set by default.
+    // We have 3 modes, the web, the terminal and the raylib gui. The web argument runs it on the
+    // web, the tty argument in the terminal, else raylib gui is set by default. A script can also
+    // be run headless, without any of them.
+    //
+    // NOTE: #############    SCRIPT USAGE    #############
+    //
+    if let Some(script_path) = &cli.script {
+        let script_file = script_path.display();
+        log::log_info("Application", &format!("Running script {script_file}"));
+        let contents = match std::fs::read_to_string(script_path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                eprintln!("Error: Could not read script {script_file}: {e}");
+                std::process::exit(1);
+            }
+        };
+        let root_dir = find_root::get_home(sekai_dir.as_ref().unwrap())
+            .expect("Could not find sekai home directory");
+        let run = as_caller(cli.headless_caller(), || {
+            run_script(&contents, &root_dir, &root_dir)
+        });
+        let output = run.output.join("\n");
+        if std::io::stdout().is_terminal() {
+            println!("{output}");
+        } else {
+            println!("{}", style::strip(&output));
+        }
+        match run.status {
+            ScriptStatus::Completed | ScriptStatus::Exited => return,
+            ScriptStatus::Failed(e) => {
+                eprintln!("Error: {script_file}: {e}");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // NOTE: #############    SESSION RECORDING AND REPLAY    #############
+    //
+    if let Some(record_file) = &cli.record
+        && let Err(e) = session::start_recording(record_file)
+    {
+        eprintln!("Error: Could not record the session: {e}");
+        std::process::exit(1);
+    }
+    let pace = if cli.step {
+        Pace::Step
+    } else if cli.instant {
+        Pace::Instant
+    } else {
+        Pace::Original
+    };
+    let replay = match cli.replay.as_ref().map(|file| Replay::load(file, pace)) {
+        Some(Ok(replay)) => Some(replay),
+        Some(Err(e)) => {
+            eprintln!("Error: Could not load the session: {e}");
+            std::process::exit(1);
+        }
+        None => None,
+    };
+    if let Some(mut replay) = replay.clone()
+        && cli.tty
+    {
+        let root_dir = find_root::get_home(sekai_dir.as_ref().unwrap())
+            .expect("Could not find sekai home directory");
+        let terminal = std::io::stdout().is_terminal();
+        let mismatches = as_caller(cli.headless_caller(), || {
+            session::replay_headless(&mut replay, &root_dir, |text| {
+                if terminal {
+                    println!("{text}");
+                } else {
+                    println!("{}", style::strip(text));
+                }
+            })
+        });
+        if mismatches > 0 {
+            eprintln!("Error: {mismatches} command(s) printed something else than recorded.");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     //
     // NOTE: #############    SERVER USAGE    #############
     //
     // Initialize the server if --web argument is provided
-    if args.iter().any(|arg| arg == "--web") {
+    if let Some(server_config) = server_config {
         log::log_info("Application", "Running in web mode");
-        // server::launch_web(sekai_dir.clone().unwrap());
-        let _ = deemak::server::server();
+        let _ = deemak::server::server(server_config);
+        return;
+    }
+
+    // NOTE: #############    TERMINAL USAGE    #############
+    //
+    // Runs on stdin/stdout, so no window (or display) is needed.
+    if cli.tty {
+        log::log_info("Application", "Running in terminal mode");
+        deemak::tty_shell::run_tty(sekai_dir.unwrap());
         return;
     }
 
@@ -197,7 +462,7 @@ Continuing..."
         .size(800, 600)
         .title("DEEMAK Shell")
         .build();
-    let font_size = get_monitor_width(0) as f32 / 73.5;
+    let font_size = font_size_for_monitor(get_monitor_width(get_current_monitor()));
     rl.set_trace_log(loglevel);
     // Disable escape key exit to prevent accidental application closure
     unsafe {
@@ -211,6 +476,14 @@ Continuing..."
         return; // Exit if window closed during login
     }
 
+    // A replay opens the shell right away, the menu comes after it
+    if let Some(replay) = replay {
+        let mut shell =
+            ShellScreen::new_sekai(&mut rl, &thread, sekai_dir.clone().unwrap(), font_size);
+        shell.start_replay(replay);
+        shell.run();
+    }
+
     // Run the GUI loop
     run_gui_loop(&mut rl, &thread, sekai_dir.unwrap(), font_size);
 }
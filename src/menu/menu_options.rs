@@ -1,3 +1,4 @@
+use crate::utils::theme::current_theme;
 use raylib::prelude::*;
 use std::time::{Duration, Instant};
 
@@ -143,8 +144,9 @@ pub fn show_menu(rl: &mut RaylibHandle, thread: &RaylibThread) -> Option<MenuOpt
         animation.update(&config);
 
         // Draw everything
+        let theme = current_theme();
         let mut d = rl.begin_drawing(thread);
-        d.clear_background(Color::BLACK);
+        d.clear_background(theme.background.color());
 
         // Draw title
         d.draw_text_ex(
@@ -153,15 +155,15 @@ pub fn show_menu(rl: &mut RaylibHandle, thread: &RaylibThread) -> Option<MenuOpt
             Vector2::new(config.title_x, animation.y_offset),
             config.title_font_size,
             2.0,
-            Color::new(255, 255, 255, 255),
+            theme.foreground.color(),
         );
 
         // Draw menu options
         for (i, option) in MenuOption::opts().iter().enumerate() {
             let color = if i == input.selected {
-                Color::GOLD
+                theme.accent.color()
             } else {
-                Color::new(200, 200, 200, (animation.alpha * 200.0) as u8)
+                theme.foreground.alpha((animation.alpha * 200.0) as u8)
             };
 
             d.draw_text_ex(
@@ -183,12 +185,9 @@ pub fn show_menu(rl: &mut RaylibHandle, thread: &RaylibThread) -> Option<MenuOpt
             config.cursor_x as i32,
             (config.menu_start_y + input.selected as f32 * config.menu_item_height) as i32,
             config.cursor_font_size,
-            Color::new(
-                255,
-                255,
-                255,
-                ((animation.alpha * 0.5).sin().abs() * 255.0) as u8,
-            ),
+            theme
+                .prompt
+                .alpha(((animation.alpha * 0.5).sin().abs() * 255.0) as u8),
         );
 
         // Draw footer
@@ -1,4 +1,6 @@
 use crate::utils::config::{self, FONT_OPTIONS};
+use crate::utils::keymap::{Action, KeyChord, Keymap};
+use crate::utils::theme;
 use raylib::prelude::*;
 use std::time::{Duration, Instant};
 
@@ -11,18 +13,20 @@ trait Screen {
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SettingsOption {
     Font,
+    Themes,
     Keybindings,
     Back,
 }
 
 impl SettingsOption {
     pub fn opts() -> &'static [Self] {
-        &[Self::Font, Self::Keybindings, Self::Back]
+        &[Self::Font, Self::Themes, Self::Keybindings, Self::Back]
     }
 
     pub fn as_str(&self) -> &'static str {
         match self {
             Self::Font => "Font",
+            Self::Themes => "Themes",
             Self::Keybindings => "Keybindings",
             Self::Back => "Back",
         }
@@ -95,20 +99,21 @@ pub fn show_font_selection(
             }
         }
 
+        let colors = theme::current_theme();
         let mut d = rl.begin_drawing(thread);
-        d.clear_background(Color::BLACK);
+        d.clear_background(colors.background.color());
         d.draw_text_ex(
             &default_font,
             "Select Font",
             Vector2::new(200.0, 100.0),
             40.0,
             2.0,
-            Color::WHITE,
+            colors.foreground.color(),
         );
 
         for (i, (name, _)) in FONT_OPTIONS.iter().enumerate() {
             let color = if i == current_selection {
-                Color::GOLD
+                colors.accent.color()
             } else {
                 Color::GRAY
             };
@@ -139,7 +144,7 @@ pub fn show_font_selection(
                     Vector2::new(170.0, 180.0 + (i as f32 * 30.0)),
                     25.0,
                     1.0,
-                    Color::GOLD,
+                    colors.accent.color(),
                 );
             }
 
@@ -158,7 +163,7 @@ pub fn show_font_selection(
         // Determine if the "Back" option is currently selected.
         let is_back_selected = current_selection == FONT_OPTIONS.len();
         let back_color = if is_back_selected {
-            Color::GOLD
+            colors.accent.color()
         } else {
             Color::GRAY
         };
@@ -180,7 +185,7 @@ pub fn show_font_selection(
                 Vector2::new(170.0, cursor_y),
                 25.0,
                 1.0,
-                Color::GOLD,
+                colors.accent.color(),
             );
         }
 
@@ -196,10 +201,152 @@ pub fn show_font_selection(
     // Fonts are automatically unloaded when `loaded_fonts` and `custom_font` go out of scope.
 }
 
-/// A screen to display the application's keybindings.
+/// Lets the user pick a theme. The screen previews the hovered theme, Enter saves it.
+/// Leaving through "Back" restores the saved theme.
+pub fn show_theme_selection(rl: &mut RaylibHandle, thread: &RaylibThread) {
+    let themes = theme::available_themes();
+    let font = rl.get_font_default();
+    let saved = theme::current_theme();
+    let mut saved_index = themes.iter().position(|t| t.name == saved.name);
+    let mut current_selection = saved_index.unwrap_or(0);
+    let options_count = themes.len() + 1;
+    let mut last_change = Instant::now();
+
+    while !rl.window_should_close() {
+        if last_change.elapsed() > Duration::from_millis(100) {
+            if rl.is_key_pressed(KeyboardKey::KEY_UP) {
+                current_selection = (current_selection + options_count - 1) % options_count;
+                last_change = Instant::now();
+            } else if rl.is_key_pressed(KeyboardKey::KEY_DOWN) {
+                current_selection = (current_selection + 1) % options_count;
+                last_change = Instant::now();
+            } else if rl.is_key_pressed(KeyboardKey::KEY_ENTER) {
+                if current_selection < themes.len() {
+                    theme::save_theme(themes[current_selection].clone());
+                    saved_index = Some(current_selection);
+                } else {
+                    break;
+                }
+            }
+        }
+
+        // Live preview of the hovered theme
+        let preview = themes
+            .get(current_selection)
+            .or(saved_index.map(|i| &themes[i]))
+            .cloned()
+            .unwrap_or_default();
+        theme::set_theme(preview.clone());
+
+        let mut d = rl.begin_drawing(thread);
+        d.clear_background(preview.background.color());
+        d.draw_text_ex(
+            &font,
+            "Select Theme",
+            Vector2::new(200.0, 100.0),
+            40.0,
+            2.0,
+            preview.foreground.color(),
+        );
+
+        let names = themes.iter().map(|t| t.name.as_str()).chain(["Back"]);
+        for (i, name) in names.enumerate() {
+            let y = 180.0 + (i as f32 * 30.0);
+            let color = if i == current_selection {
+                preview.accent.color()
+            } else {
+                preview.foreground.alpha(150)
+            };
+            if Some(i) == saved_index {
+                d.draw_text_ex(&font, "*", Vector2::new(170.0, y), 25.0, 1.0, color);
+            } else if i == current_selection {
+                d.draw_text_ex(&font, ">", Vector2::new(170.0, y), 25.0, 1.0, color);
+            }
+            d.draw_text_ex(&font, name, Vector2::new(200.0, y), 25.0, 1.0, color);
+        }
+
+        // Sample shell output in the previewed colors
+        let x = 550.0;
+        let y = 180.0;
+        d.draw_rectangle_lines(
+            x as i32 - 15,
+            y as i32 - 15,
+            420,
+            170,
+            preview.foreground.alpha(80),
+        );
+        d.draw_text_ex(
+            &font,
+            ">",
+            Vector2::new(x, y),
+            20.0,
+            1.0,
+            preview.prompt.color(),
+        );
+        d.draw_text_ex(
+            &font,
+            "go room_1",
+            Vector2::new(x + 20.0, y),
+            20.0,
+            1.0,
+            preview.foreground.color(),
+        );
+        d.draw_text_ex(
+            &font,
+            "Objects:",
+            Vector2::new(x, y + 30.0),
+            20.0,
+            1.0,
+            preview.accent.color(),
+        );
+        d.draw_rectangle(x as i32, y as i32 + 58, 110, 24, preview.selection.color());
+        d.draw_text_ex(
+            &font,
+            "note.txt",
+            Vector2::new(x, y + 60.0),
+            20.0,
+            1.0,
+            preview.foreground.color(),
+        );
+        d.draw_text_ex(
+            &font,
+            "go: room_9: No such directory",
+            Vector2::new(x, y + 100.0),
+            20.0,
+            1.0,
+            preview.error.color(),
+        );
+
+        let footnote = "* Represents currently selected theme";
+        let footnote_width = d.measure_text(footnote, 18);
+        let footnote_pos = Vector2::new(
+            (d.get_screen_width() as f32 - footnote_width as f32) / 2.0,
+            d.get_screen_height() as f32 - 50.0,
+        );
+        d.draw_text_ex(
+            &font,
+            footnote,
+            footnote_pos,
+            18.0,
+            1.0,
+            preview.foreground.alpha(150),
+        );
+    }
+
+    // Drop the preview, keep what was saved
+    let saved = saved_index.map(|i| themes[i].clone()).unwrap_or(saved);
+    theme::set_theme(saved);
+}
+
+/// A screen to display and change the keybindings of the shell.
 struct KeybindingsScreen {
     font: Font,
-    keybindings: Vec<(String, String)>,
+    keymap: Keymap,
+    /// Selected row, one per action followed by "Back".
+    selected: usize,
+    /// Set while waiting for the new chord of the selected action.
+    capturing: bool,
+    message: Option<String>,
     last_change: Instant,
     alpha: f32,
     y_offset: f32,
@@ -218,22 +365,12 @@ impl KeybindingsScreen {
             )
             .expect("Failed to load keybindings font");
 
-        let keybindings = [
-            ("Keyboard characters", "Keyboard chars"),
-            ("Ctrl+Shift+C ", "Copy (Linux/MacOS)"),
-            ("Ctrl+Shift+V ", "Paste (Linux/MacOS)"),
-            ("Ctrl+K", "Clear Line"),
-            ("Ctrl+C", "Next prompt"),
-            ("Tab", "File completion only till Current Working Directory"),
-            ("Arrow keys", "Navigate through history"),
-        ]
-        .iter()
-        .map(|(key, desc)| (key.to_string(), desc.to_string()))
-        .collect();
-
         Self {
             font,
-            keybindings,
+            keymap: Keymap::load(),
+            selected: 0,
+            capturing: false,
+            message: None,
             last_change: Instant::now(),
             alpha: 0.0,
             y_offset: 200.0,
@@ -243,11 +380,68 @@ impl KeybindingsScreen {
 
     /// Handles user input for the keybindings screen. Returns true if the screen should be closed.
     fn handle_input(&mut self, rl: &mut RaylibHandle) -> bool {
-        if self.last_change.elapsed() > Duration::from_millis(150)
-            && rl.is_key_pressed(KeyboardKey::KEY_ENTER)
+        let actions = Action::all();
+        if self.capturing {
+            // Wait for a full chord, not just the modifiers being pressed
+            let Some(chord) = KeyChord::pressed(rl) else {
+                return false;
+            };
+            if chord.key == KeyboardKey::KEY_ESCAPE {
+                self.capturing = false;
+                self.message = None;
+            } else if chord.is_bindable() {
+                let action = actions[self.selected];
+                self.message = Some(match self.keymap.rebind(action, chord) {
+                    Ok(previous) => {
+                        self.keymap.save();
+                        match previous {
+                            Some(previous) => format!(
+                                "{chord} now does '{}' instead of '{}'",
+                                action.description(),
+                                previous.description()
+                            ),
+                            None => format!("{chord} now does '{}'", action.description()),
+                        }
+                    }
+                    Err(e) => e,
+                });
+                self.capturing = false;
+            }
+            self.last_change = Instant::now();
+            return false;
+        }
+
+        if self.last_change.elapsed() <= Duration::from_millis(150) {
+            return false;
+        }
+        let rows = actions.len() + 1;
+        if rl.is_key_pressed(KeyboardKey::KEY_UP) {
+            self.selected = (self.selected + rows - 1) % rows;
+        } else if rl.is_key_pressed(KeyboardKey::KEY_DOWN) {
+            self.selected = (self.selected + 1) % rows;
+        } else if rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+            return true;
+        } else if rl.is_key_pressed(KeyboardKey::KEY_ENTER) {
+            if self.selected == actions.len() {
+                return true; // Exit screen
+            }
+            self.capturing = true;
+            self.message = Some(format!(
+                "Press the new keys for '{}', or Esc to cancel",
+                actions[self.selected].description()
+            ));
+        } else if rl.is_key_pressed(KeyboardKey::KEY_DELETE)
+            || rl.is_key_pressed(KeyboardKey::KEY_BACKSPACE)
         {
-            return true; // Exit screen
+            if let Some(action) = actions.get(self.selected) {
+                self.keymap.reset(*action);
+                self.keymap.save();
+                self.message = Some(format!("Restored the keys of '{}'", action.description()));
+            }
+        } else {
+            return false;
         }
+        self.last_change = Instant::now();
         false
     }
 
@@ -260,7 +454,8 @@ impl KeybindingsScreen {
     /// Draws the keybindings screen.
     fn draw(&self, d: &mut RaylibDrawHandle) {
         let font_heading = d.get_font_default();
-        d.clear_background(Color::BLACK);
+        let colors = theme::current_theme();
+        d.clear_background(colors.background.color());
 
         // Draw heading with animation
         d.draw_text_ex(
@@ -269,7 +464,7 @@ impl KeybindingsScreen {
             Vector2::new(200.0, self.y_offset),
             50.0,
             2.0,
-            Color::new(255, 255, 255, (self.alpha * 255.0) as u8),
+            colors.foreground.alpha((self.alpha * 255.0) as u8),
         );
 
         // Draw column headers
@@ -305,17 +500,38 @@ impl KeybindingsScreen {
         let mut y = separator_y + 20.0;
         let max_desc_width = d.get_screen_width() as f32 - 400.0 - 20.0; // 20px padding
 
-        for (key, desc) in &self.keybindings {
-            d.draw_text_ex(
-                &self.font,
-                key,
-                Vector2::new(100.0, y),
-                20.0,
-                1.0,
-                Color::WHITE,
-            );
+        // Scroll so the selected row stays visible above the message and help lines
+        let actions = Action::all();
+        let visible_rows = ((d.get_screen_height() as f32 - y - 130.0) / 30.0).max(1.0) as usize;
+        let first_row = (self.selected + 1).saturating_sub(visible_rows);
+        let last_row = first_row + visible_rows;
+
+        for (i, action) in actions.iter().enumerate().take(last_row).skip(first_row) {
+            let color = if i == self.selected {
+                colors.accent.color()
+            } else {
+                colors.foreground.color()
+            };
+            if i == self.selected {
+                d.draw_text_ex(&self.font, ">", Vector2::new(75.0, y), 20.0, 1.0, color);
+            }
+
+            let chords = self.keymap.chords(*action);
+            let keys = if self.capturing && i == self.selected {
+                "...".to_string()
+            } else if chords.is_empty() {
+                "(unbound)".to_string()
+            } else {
+                chords
+                    .iter()
+                    .map(|chord| chord.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            d.draw_text_ex(&self.font, &keys, Vector2::new(100.0, y), 20.0, 1.0, color);
 
-            let wrapped_lines = wrap_text(&self.font, desc, 20.0, 1.0, max_desc_width);
+            let wrapped_lines =
+                wrap_text(&self.font, action.description(), 20.0, 1.0, max_desc_width);
             let mut line_y = y;
             for line in &wrapped_lines {
                 d.draw_text_ex(
@@ -324,7 +540,7 @@ impl KeybindingsScreen {
                     Vector2::new(400.0, line_y),
                     20.0,
                     1.0,
-                    Color::WHITE,
+                    color,
                 );
                 line_y += 25.0; // Line height
             }
@@ -332,14 +548,54 @@ impl KeybindingsScreen {
             y += (wrapped_lines.len() as f32 * 25.0).max(30.0); // Move to the next entry
         }
 
-        // Draw "Press Enter to go back" message
-        let back_msg = "Press Enter to go back";
-        let back_msg_width = d.measure_text(back_msg, 20);
+        // Draw the "Back" row
+        let back_selected = self.selected == actions.len();
+        let back_color = if back_selected {
+            colors.accent.color()
+        } else {
+            Color::GRAY
+        };
+        if actions.len() < last_row {
+            y += 10.0;
+            if back_selected {
+                d.draw_text_ex(
+                    &self.font,
+                    ">",
+                    Vector2::new(75.0, y),
+                    20.0,
+                    1.0,
+                    back_color,
+                );
+            }
+            d.draw_text_ex(
+                &self.font,
+                "Back",
+                Vector2::new(100.0, y),
+                20.0,
+                1.0,
+                back_color,
+            );
+        }
+
+        if let Some(message) = &self.message {
+            d.draw_text_ex(
+                &self.font,
+                message,
+                Vector2::new(100.0, d.get_screen_height() as f32 - 90.0),
+                20.0,
+                1.0,
+                colors.accent.color(),
+            );
+        }
+
+        // Draw the help line
+        let help_msg = "Enter: change keys   Delete: restore default   Esc: back";
+        let help_msg_width = d.measure_text(help_msg, 20);
         d.draw_text_ex(
             &font_heading,
-            back_msg,
+            help_msg,
             Vector2::new(
-                (d.get_screen_width() as f32 - back_msg_width as f32) / 2.0,
+                (d.get_screen_width() as f32 - help_msg_width as f32) / 2.0,
                 d.get_screen_height() as f32 - 50.0,
             ),
             20.0,
@@ -426,6 +682,7 @@ pub fn show_settings(rl: &mut RaylibHandle, thread: &RaylibThread) {
             } else if rl.is_key_pressed(KeyboardKey::KEY_ENTER) {
                 match SettingsOption::opts()[selected] {
                     SettingsOption::Font => show_font_selection(rl, thread, &mut selected_font),
+                    SettingsOption::Themes => show_theme_selection(rl, thread),
                     SettingsOption::Keybindings => show_keybindings(rl, thread),
                     SettingsOption::Back => return,
                 }
@@ -436,8 +693,9 @@ pub fn show_settings(rl: &mut RaylibHandle, thread: &RaylibThread) {
         alpha = (alpha + 0.02).min(1.0);
         y_offset += (target_y - y_offset) * 0.1;
 
+        let colors = theme::current_theme();
         let mut d = rl.begin_drawing(thread);
-        d.clear_background(Color::BLACK);
+        d.clear_background(colors.background.color());
 
         d.draw_text_ex(
             &font,
@@ -445,14 +703,14 @@ pub fn show_settings(rl: &mut RaylibHandle, thread: &RaylibThread) {
             Vector2::new(200.0, y_offset),
             50.0,
             2.0,
-            Color::new(255, 255, 255, (alpha * 255.0) as u8),
+            colors.foreground.alpha((alpha * 255.0) as u8),
         );
 
         for (i, option) in SettingsOption::opts().iter().enumerate() {
             let color = if i == selected {
-                Color::GOLD
+                colors.accent.color()
             } else {
-                Color::new(200, 200, 200, (alpha * 200.0) as u8)
+                colors.foreground.alpha((alpha * 200.0) as u8)
             };
 
             d.draw_text_ex(
@@ -470,7 +728,9 @@ pub fn show_settings(rl: &mut RaylibHandle, thread: &RaylibThread) {
             175,
             300 + selected as i32 * 50,
             30,
-            Color::new(255, 255, 255, ((alpha * 0.5).sin().abs() * 255.0) as u8),
+            colors
+                .prompt
+                .alpha(((alpha * 0.5).sin().abs() * 255.0) as u8),
         );
     }
 }
@@ -0,0 +1,275 @@
+//! Conversations with NPCs. An object of `"type": "npc"` in its room's `info.json` has a
+//! dialogue tree in `.dir_info/dialogues/<name>.json`, or in the file named by its
+//! `dialogue` property, relative to `.dir_info`:
+//!
+//! ```json
+//! {
+//!     "start": "greet",
+//!     "nodes": {
+//!         "greet": {
+//!             "say": "Do you know how to get inside OPB?",
+//!             "choices": [
+//!                 { "text": "No, how?", "goto": "passkey", "set": "asked_about_opb" },
+//!                 { "text": "Bye.", "goto": "bye" }
+//!             ]
+//!         },
+//!         "passkey": { "say": "Show a passkey to the guard.", "hint": "Try `ls -a`.", "goto": "bye" },
+//!         "bye": { "say": "Enjoy your food!", "give": "spoon" }
+//!     }
+//! }
+//! ```
+//!
+//! Entering a node says its text, gives its item, shows its hint and sets its flag. A node
+//! without choices moves on to its `goto`, or ends the conversation. A choice with an `if`
+//! is offered only once that flag is set.
+
+use super::info_reader::read_get_obj_info;
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Dialogue {
+    pub start: String,
+    pub nodes: HashMap<String, Node>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Node {
+    pub say: String,
+    pub choices: Vec<Choice>,
+    /// Next node when there are no choices.
+    pub goto: Option<String>,
+    /// Item handed to the player.
+    pub give: Option<String>,
+    pub hint: Option<String>,
+    /// Progress flag set when the node is reached.
+    pub set: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Choice {
+    pub text: String,
+    /// Next node. The conversation ends without one.
+    #[serde(default)]
+    pub goto: Option<String>,
+    /// Progress flag set when the choice is made.
+    #[serde(default)]
+    pub set: Option<String>,
+    /// Flag required for the choice to be offered.
+    #[serde(default, rename = "if")]
+    pub condition: Option<String>,
+}
+
+/// What happened in one step of a conversation.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Turn {
+    pub said: Vec<String>,
+    pub hints: Vec<String>,
+    pub give: Vec<String>,
+    pub set: Vec<String>,
+    /// Choices offered now.
+    pub choices: Vec<String>,
+    /// Node the conversation waits at. `None` once it ended.
+    pub node: Option<String>,
+}
+
+/// Whether the object at `obj_path` is an NPC.
+pub fn is_npc(obj_path: &Path) -> bool {
+    dialogue_path(obj_path).is_some()
+}
+
+/// Path of the dialogue file of the NPC at `obj_path`, if it is one.
+pub fn dialogue_path(obj_path: &Path) -> Option<PathBuf> {
+    let dir_info = obj_path.parent()?.join(".dir_info");
+    let name = obj_path.file_name()?.to_str()?;
+    let info = read_get_obj_info(&dir_info.join("info.json"), name).ok()?;
+    if info.properties.get("type")?.as_str()? != "npc" {
+        return None;
+    }
+    Some(
+        match info.properties.get("dialogue").and_then(|v| v.as_str()) {
+            Some(file) => dir_info.join(file),
+            None => dir_info.join("dialogues").join(format!("{name}.json")),
+        },
+    )
+}
+
+/// Reads and checks the dialogue of the NPC at `obj_path`.
+pub fn load_dialogue(obj_path: &Path) -> Result<Dialogue, String> {
+    let path = dialogue_path(obj_path).ok_or("not someone you can talk to")?;
+    let contents = fs::read_to_string(&path).map_err(|e| format!("cannot read dialogue: {e}"))?;
+    let dialogue: Dialogue =
+        serde_json::from_str(&contents).map_err(|e| format!("invalid dialogue: {e}"))?;
+    dialogue.validate()?;
+    Ok(dialogue)
+}
+
+impl Dialogue {
+    /// Checks that every `goto` leads to a node.
+    pub fn validate(&self) -> Result<(), String> {
+        let targets = self.nodes.values().flat_map(|node| {
+            node.goto
+                .iter()
+                .chain(node.choices.iter().filter_map(|c| c.goto.as_ref()))
+        });
+        for target in std::iter::once(&self.start).chain(targets) {
+            if !self.nodes.contains_key(target) {
+                return Err(format!("invalid dialogue: no node named '{target}'"));
+            }
+        }
+        Ok(())
+    }
+
+    fn choices_at<'a>(&'a self, node: &str, flags: &BTreeSet<String>) -> Vec<&'a Choice> {
+        self.nodes
+            .get(node)
+            .map(|node| {
+                node.choices
+                    .iter()
+                    .filter(|c| c.condition.as_ref().is_none_or(|flag| flags.contains(flag)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Shows the node the conversation waits at again, without its effects.
+    pub fn view(&self, node: &str, flags: &BTreeSet<String>) -> Turn {
+        Turn {
+            said: self
+                .nodes
+                .get(node)
+                .map(|n| n.say.clone())
+                .into_iter()
+                .collect(),
+            choices: self
+                .choices_at(node, flags)
+                .iter()
+                .map(|c| c.text.clone())
+                .collect(),
+            node: Some(node.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// Enters `node` and follows its `goto`s until the player has to choose.
+    pub fn enter(&self, node: &str, flags: &BTreeSet<String>) -> Turn {
+        let mut turn = Turn::default();
+        self.enter_into(node, flags, &mut turn);
+        turn
+    }
+
+    fn enter_into(&self, node: &str, flags: &BTreeSet<String>, turn: &mut Turn) {
+        let mut flags = flags.clone();
+        let mut visited = HashSet::new();
+        let mut current = Some(node.to_string());
+        // A node visited twice in a row of `goto`s would loop forever
+        while let Some(id) = current.take().filter(|id| visited.insert(id.clone())) {
+            let Some(node) = self.nodes.get(&id) else {
+                break;
+            };
+            if !node.say.is_empty() {
+                turn.said.push(node.say.clone());
+            }
+            turn.hints.extend(node.hint.clone());
+            turn.give.extend(node.give.clone());
+            if let Some(flag) = &node.set {
+                flags.insert(flag.clone());
+                turn.set.push(flag.clone());
+            }
+            let choices = self.choices_at(&id, &flags);
+            if !choices.is_empty() {
+                turn.choices = choices.iter().map(|c| c.text.clone()).collect();
+                turn.node = Some(id);
+                return;
+            }
+            current = node.goto.clone();
+        }
+        turn.node = None;
+    }
+
+    /// Makes the `number`-th choice (from 1) offered at `node`.
+    /// Returns the choice made and what follows it.
+    pub fn choose(
+        &self,
+        node: &str,
+        number: usize,
+        flags: &BTreeSet<String>,
+    ) -> Result<(String, Turn), String> {
+        let choices = self.choices_at(node, flags);
+        let choice = number
+            .checked_sub(1)
+            .and_then(|i| choices.get(i))
+            .ok_or_else(|| format!("choose a number from 1 to {}", choices.len()))?;
+        let mut turn = Turn::default();
+        let mut flags = flags.clone();
+        if let Some(flag) = &choice.set {
+            flags.insert(flag.clone());
+            turn.set.push(flag.clone());
+        }
+        match &choice.goto {
+            Some(next) => self.enter_into(next, &flags, &mut turn),
+            None => turn.node = None,
+        }
+        Ok((choice.text.clone(), turn))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dialogue() {
+        let dialogue: Dialogue = serde_json::from_str(
+            r#"{
+                "start": "greet",
+                "nodes": {
+                    "greet": {
+                        "say": "Hi!",
+                        "choices": [
+                            { "text": "How do I get in?", "goto": "passkey", "set": "asked" },
+                            { "text": "Secret?", "goto": "bye", "if": "knows_secret" },
+                            { "text": "Bye." }
+                        ]
+                    },
+                    "passkey": { "say": "Find the passkey.", "hint": "ls -a", "goto": "bye" },
+                    "bye": { "say": "Enjoy!", "give": "spoon" }
+                }
+            }"#,
+        )
+        .unwrap();
+        dialogue.validate().unwrap();
+        let flags = BTreeSet::new();
+
+        let turn = dialogue.enter(&dialogue.start, &flags);
+        assert_eq!(turn.said, ["Hi!"]);
+        assert_eq!(turn.choices, ["How do I get in?", "Bye."]);
+        assert_eq!(turn.node.as_deref(), Some("greet"));
+        assert_eq!(dialogue.view("greet", &flags).choices.len(), 2);
+
+        assert!(dialogue.choose("greet", 3, &flags).is_err());
+        let (choice, turn) = dialogue.choose("greet", 1, &flags).unwrap();
+        assert_eq!(choice, "How do I get in?");
+        assert_eq!(turn.said, ["Find the passkey.", "Enjoy!"]);
+        assert_eq!(
+            (turn.hints, turn.give),
+            (vec!["ls -a".to_string()], vec!["spoon".to_string()])
+        );
+        assert_eq!(turn.set, ["asked"]);
+        assert_eq!(turn.node, None);
+
+        let flags = BTreeSet::from(["knows_secret".to_string()]);
+        assert_eq!(dialogue.view("greet", &flags).choices.len(), 3);
+        let (_, turn) = dialogue.choose("greet", 3, &flags).unwrap();
+        assert!(turn.said.is_empty() && turn.node.is_none());
+
+        let broken: Dialogue =
+            serde_json::from_str(r#"{ "start": "a", "nodes": { "a": { "goto": "b" } } }"#).unwrap();
+        assert!(broken.validate().is_err());
+    }
+}
@@ -0,0 +1,149 @@
+//! Hidden objects. An object with a `hidden` property in its room's `info.json` does not show
+//! up, and cannot be read or entered, until it is revealed to the player:
+//!
+//! ```json
+//! "secret_passage": { "locked": "00", "hidden": true },
+//! "map.txt": { "locked": "00", "hidden": { "solved": "room_2/level_1" } }
+//! ```
+//!
+//! `true` hides an object until a trigger reveals it. A condition reveals it once the player
+//! has solved a level (`solved`, path from HOME), carries an item (`item`), has fired a
+//! trigger (`fired`, like `room_3/note.txt#on_read`) or has a flag set (`flag`).
+//! Everything inside a hidden directory is hidden too. Objects are revealed per user: the
+//! shared info.json is never changed.
+
+use super::info_reader::read_validate_info;
+use crate::commands::cmds::normalize_path;
+use crate::utils::find_root::home_relative;
+use crate::utils::inventory::Inventory;
+use crate::utils::progress::Progress;
+use crate::utils::shell_history::current_user;
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::Path;
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Condition {
+    Solved(String),
+    Item(String),
+    Fired(String),
+    Flag(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum Hidden {
+    Always(bool),
+    Until(Condition),
+}
+
+/// What the player has done, to decide what is revealed to them.
+#[derive(Clone, Debug, Default)]
+pub struct Visibility {
+    pub progress: Progress,
+    pub inventory: Inventory,
+}
+
+impl Visibility {
+    pub fn new(progress: Progress, inventory: Inventory) -> Self {
+        Self {
+            progress,
+            inventory,
+        }
+    }
+
+    /// Visibility for the current user.
+    pub fn current() -> Self {
+        let user = current_user();
+        Self::new(Progress::load(&user), Inventory::load(&user))
+    }
+
+    /// Whether the `hidden` property of the object at `obj_path` still hides it.
+    fn hides(&self, hidden: &Value, obj_path: &Path, root_dir: &Path) -> bool {
+        if self
+            .progress
+            .revealed
+            .contains(&home_relative(obj_path, root_dir))
+        {
+            return false;
+        }
+        match serde_json::from_value(hidden.clone()) {
+            Ok(Hidden::Always(hidden)) => hidden,
+            Ok(Hidden::Until(condition)) => !match condition {
+                Condition::Solved(level) => self.progress.solved.contains_key(&level),
+                Condition::Item(item) => self.inventory.carries(&item),
+                Condition::Fired(trigger) => self.progress.fired.contains(&trigger),
+                Condition::Flag(flag) => self.progress.flags.contains(&flag),
+            },
+            // A malformed condition keeps the object hidden rather than spoiling it
+            Err(_) => true,
+        }
+    }
+
+    /// Names of the objects in `dir` hidden from the player.
+    pub fn hidden_in(&self, dir: &Path, root_dir: &Path) -> Vec<String> {
+        let Ok(info) = read_validate_info(&dir.join(".dir_info/info.json")) else {
+            return Vec::new();
+        };
+        info.objects
+            .iter()
+            .filter(|(name, obj)| {
+                obj.properties
+                    .get("hidden")
+                    .is_some_and(|hidden| self.hides(hidden, &dir.join(name), root_dir))
+            })
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Whether the object at `path`, or a directory containing it, is hidden from the player.
+    /// `path` may hold `.` and `..`.
+    pub fn is_hidden(&self, path: &Path, root_dir: &Path) -> bool {
+        let path = normalize_path(path);
+        let mut current = path.as_path();
+        while current != root_dir && current.starts_with(root_dir) {
+            let (Some(dir), Some(name)) = (current.parent(), current.file_name()) else {
+                break;
+            };
+            if self
+                .hidden_in(dir, root_dir)
+                .iter()
+                .any(|hidden| hidden.as_str() == name)
+            {
+                return true;
+            }
+            current = dir;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metainfo::info_reader::update_obj_status;
+    use crate::utils::test_utils::setup_test_dir;
+    use serde_json::json;
+
+    #[test]
+    fn test_hidden_objects() {
+        let (_temp, root) = setup_test_dir(true);
+        update_obj_status(&root.join("subdir1"), "subdir1", "hidden", json!(true)).unwrap();
+        let condition = json!({ "flag": "lit_lamp" });
+        update_obj_status(&root.join("file1.txt"), "file1.txt", "hidden", condition).unwrap();
+
+        let mut visibility = Visibility::default();
+        let mut hidden = visibility.hidden_in(&root, &root);
+        hidden.sort();
+        assert_eq!(hidden, ["file1.txt", "subdir1"]);
+        assert!(visibility.is_hidden(&root.join("subdir1/nested1/file4.txt"), &root));
+        assert!(!visibility.is_hidden(&root.join("subdir2"), &root));
+        assert!(visibility.is_hidden(&root.join("subdir2/../subdir1/./nested1"), &root));
+
+        visibility.progress.flags.insert("lit_lamp".to_string());
+        visibility.progress.revealed.insert("subdir1".to_string());
+        assert!(visibility.hidden_in(&root, &root).is_empty());
+        assert!(!visibility.is_hidden(&root.join("subdir1/nested1/file4.txt"), &root));
+    }
+}
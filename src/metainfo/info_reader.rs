@@ -1,3 +1,4 @@
+use super::objectives::Objective;
 use crate::commands::cmds::normalize_path;
 use crate::utils::relative_deemak_path;
 use serde::{Deserialize, Serialize};
@@ -53,6 +54,12 @@ pub struct Info {
     pub about: String,
     #[serde(rename = "objects")]
     pub objects: HashMap<String, ObjectInfo>,
+    /// Default shell aliases shipped by the sekai author. Only read from HOME.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub aliases: HashMap<String, String>,
+    /// Objectives of the sekai, in order. Only read from HOME.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub objectives: Vec<Objective>,
 }
 
 #[derive(Debug, Error)]
@@ -76,6 +83,8 @@ impl Info {
             location: Self::default_location(&norm_path, home_dir),
             about: Self::default_about(&norm_path, home_dir),
             objects: Self::default_objects(&norm_path),
+            aliases: HashMap::new(),
+            objectives: Vec::new(),
         }
     }
 
@@ -164,6 +173,10 @@ pub fn read_validate_info(info_path: &Path) -> Result<Info, InfoError> {
                 if !is_locked {
                     continue; // Not locked, skip further checks
                 }
+                // Locks opened with an item carry no flag data
+                if obj_info.properties.contains_key("requires") {
+                    continue;
+                }
                 // ensure it has a 'decrypt_me' property
                 if is_level && !obj_info.properties.contains_key("decrypt_me") {
                     return Err(InfoError::ValidationError(format!(
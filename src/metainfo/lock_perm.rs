@@ -1,5 +1,7 @@
 use super::info_reader::read_validate_info;
+use crate::utils::find_root::home_relative;
 use crate::utils::log;
+use crate::utils::progress::Progress;
 use crate::utils::relative_deemak_path;
 use std::path::Path;
 
@@ -50,18 +52,35 @@ pub fn read_lock_perm(obj_path: &Path) -> Result<(bool, bool), String> {
     Ok((bits[0], bits[1]))
 }
 
-/// Checks if the operation can be performed if object is unlocked.
+/// Reads the lock permissions of an object as the player sees them: the locks they opened
+/// read as unlocked. The shared info.json keeps them locked for everyone else.
+pub fn read_player_lock_perm(
+    obj_path: &Path,
+    root_dir: &Path,
+    progress: &Progress,
+) -> Result<(bool, bool), String> {
+    let (is_level, is_locked) = read_lock_perm(obj_path)?;
+    let opened = obj_path.starts_with(root_dir)
+        && progress
+            .unlocked
+            .contains(&home_relative(obj_path, root_dir));
+    Ok((is_level, is_locked && !opened))
+}
+
+/// Checks if the operation can be performed if object is unlocked for the player.
 /// Returns: Ok if operation can proceed, Err with message if locked. OR Err if lock status cannot
 /// be determined.
 pub fn operation_locked_perm(
     obj_path: &Path,
+    root_dir: &Path,
+    progress: &Progress,
     operation: &str,
     message: &str,
 ) -> Result<(), String> {
     // Check all parents up to root
     let mut current = obj_path;
     while let Some(parent) = current.parent() {
-        if let Ok((_, locked)) = read_lock_perm(current) {
+        if let Ok((_, locked)) = read_player_lock_perm(current, root_dir, progress) {
             if locked {
                 let rel_path = relative_deemak_path(current);
                 log::log_warning(
@@ -80,7 +99,7 @@ pub fn operation_locked_perm(
     }
 
     // Check the object itself
-    match read_lock_perm(obj_path) {
+    match read_player_lock_perm(obj_path, root_dir, progress) {
         Ok((_, true)) => {
             let rel_path = relative_deemak_path(obj_path);
             log::log_warning(
@@ -108,3 +127,42 @@ pub fn operation_locked_perm(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metainfo::info_reader::update_obj_status;
+    use crate::utils::test_utils::setup_test_dir;
+    use serde_json::json;
+
+    #[test]
+    fn test_player_lock_perm() {
+        let (_temp, root) = setup_test_dir(true);
+        let locked = root.join("subdir2");
+        for (key, value) in [
+            ("obj_salt", "salt"),
+            ("compare_me", "hash"),
+            ("decrypt_me", "flag"),
+            ("locked", "11"),
+        ] {
+            update_obj_status(&locked, "subdir2", key, json!(value)).unwrap();
+        }
+        let inside = locked.join("nested2");
+
+        let mut progress = Progress::default();
+        assert_eq!(
+            read_player_lock_perm(&locked, &root, &progress),
+            Ok((true, true))
+        );
+        assert!(operation_locked_perm(&inside, &root, &progress, "go", "").is_err());
+
+        // Opened for this player only: info.json still has it locked
+        progress.unlocked.insert("subdir2".to_string());
+        assert_eq!(
+            read_player_lock_perm(&locked, &root, &progress),
+            Ok((true, false))
+        );
+        assert!(operation_locked_perm(&inside, &root, &progress, "go", "").is_ok());
+        assert_eq!(read_lock_perm(&locked), Ok((true, true)));
+    }
+}
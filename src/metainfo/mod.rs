@@ -5,3 +5,11 @@ pub mod valid_sekai;
 
 pub mod lock_perm;
 pub use lock_perm::read_lock_perm;
+
+pub mod triggers;
+
+pub mod hidden;
+
+pub mod dialogue;
+
+pub mod objectives;
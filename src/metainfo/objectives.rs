@@ -0,0 +1,186 @@
+//! Objectives of a sekai, listed in order in its HOME `info.json`:
+//!
+//! ```json
+//! "objectives": [
+//!     { "id": "friend", "task": "Go to the mess and talk to your friend", "visit": "mess/friend" },
+//!     { "id": "look", "task": "Look for hidden things with `ls -a`", "run": "ls -a" },
+//!     { "id": "phy_lab", "task": "Read about the Physics Lab", "read": "opb/labs/phy_lab" }
+//! ]
+//! ```
+//!
+//! Each objective is checked against the commands the player runs. `visit` is met by entering
+//! a room, or by a command on an object (paths from HOME). `read` is met by reading the object,
+//! and `run` by a command starting with the given words. Only successful commands count.
+//! Objectives can be met in any order: the first one not met yet is the current one.
+//! They are checked by `on_event`, which follows the commands published on the event bus.
+
+use super::info_reader::read_validate_info;
+use crate::commands::cmds::normalize_path;
+use crate::utils::events::{self, CommandEvent, EventRecord, Notice, ShellEvent};
+use crate::utils::progress::Progress;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Goal {
+    Visit(String),
+    Read(String),
+    Run(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Objective {
+    /// Key of the objective in the player's progress.
+    pub id: String,
+    /// What the player is told to do.
+    pub task: String,
+    #[serde(flatten)]
+    pub goal: Goal,
+}
+
+impl Goal {
+    /// Whether the command of `event` meets the goal.
+    pub fn is_met_by(&self, event: &CommandEvent) -> bool {
+        if event.status != 0 {
+            return false;
+        }
+        let Some((command, args)) = event.parts.split_first() else {
+            return false;
+        };
+        let targets = || {
+            args.iter()
+                .filter(|arg| !arg.starts_with('-'))
+                .map(|arg| normalize_path(&Path::new(&event.dir).join(arg)))
+        };
+        match self {
+            Goal::Visit(path) => {
+                &event.new_dir == path || targets().any(|target| target == Path::new(path))
+            }
+            Goal::Read(path) => {
+                command == "read" && targets().any(|target| target == Path::new(path))
+            }
+            Goal::Run(line) => {
+                let words: Vec<&str> = line.split_whitespace().collect();
+                !words.is_empty()
+                    && event.parts.len() >= words.len()
+                    && words
+                        .iter()
+                        .zip(&event.parts)
+                        .all(|(word, part)| word == part)
+            }
+        }
+    }
+}
+
+/// Objectives of the sekai at `root_dir`. Empty if it has none.
+pub fn read_objectives(root_dir: &Path) -> Vec<Objective> {
+    read_validate_info(&root_dir.join(".dir_info/info.json"))
+        .map(|info| info.objectives)
+        .unwrap_or_default()
+}
+
+/// First objective not met yet.
+pub fn current<'a>(objectives: &'a [Objective], progress: &Progress) -> Option<&'a Objective> {
+    objectives
+        .iter()
+        .find(|objective| !progress.objectives.contains(&objective.id))
+}
+
+/// Checks `event` against the objectives not met yet, and records the ones it meets.
+/// Returns those, in order.
+pub fn check(
+    objectives: &[Objective],
+    event: &CommandEvent,
+    progress: &mut Progress,
+) -> Vec<Objective> {
+    let met: Vec<Objective> = objectives
+        .iter()
+        .filter(|objective| {
+            !progress.objectives.contains(&objective.id) && objective.goal.is_met_by(event)
+        })
+        .cloned()
+        .collect();
+    progress
+        .objectives
+        .extend(met.iter().map(|objective| objective.id.clone()));
+    met
+}
+
+/// Subscriber recording the objectives met by the commands of each player, and telling
+/// the player about them.
+pub fn on_event(record: &EventRecord) {
+    let ShellEvent::CommandExecuted(event) = &record.event else {
+        return;
+    };
+    for line in record_met(event, &record.user, &record.root_dir) {
+        events::tell(Notice::News(line));
+    }
+}
+
+/// Records the objectives met by a command of `user`, and returns the lines announcing them.
+fn record_met(event: &CommandEvent, user: &str, root_dir: &Path) -> Vec<String> {
+    let objectives = read_objectives(root_dir);
+    if objectives.is_empty() {
+        return Vec::new();
+    }
+    Progress::update(user, |progress| {
+        let met = check(&objectives, event, progress);
+        if met.is_empty() {
+            return Vec::new();
+        }
+        let mut lines: Vec<String> = met
+            .iter()
+            .map(|objective| format!("Objective complete: {}", objective.task))
+            .collect();
+        match current(&objectives, progress) {
+            Some(next) => lines.push(format!("Next: {}", next.task)),
+            None => lines.push("All objectives complete!".to_string()),
+        }
+        lines
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_objectives() {
+        let objectives: Vec<Objective> = serde_json::from_str(
+            r#"[
+                { "id": "friend", "task": "Talk to your friend", "visit": "mess/friend" },
+                { "id": "look", "task": "Look around", "run": "ls -a" },
+                { "id": "lab", "task": "Read about the lab", "read": "opb/phy_lab" }
+            ]"#,
+        )
+        .unwrap();
+        let event = |dir: &str, line: &str, status: i32| CommandEvent {
+            parts: line.split_whitespace().map(str::to_string).collect(),
+            dir: dir.to_string(),
+            new_dir: dir.to_string(),
+            status,
+        };
+        let mut progress = Progress::default();
+        let check = |event: CommandEvent, progress: &mut Progress| {
+            check(&objectives, &event, progress)
+                .into_iter()
+                .map(|objective| objective.id)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            check(event("mess", "talk friend", 0), &mut progress),
+            ["friend"]
+        );
+        assert!(check(event("", "ls", 0), &mut progress).is_empty());
+        assert!(check(event("", "ls -a", 1), &mut progress).is_empty());
+        assert!(check(event("opb", "go phy_lab", 0), &mut progress).is_empty());
+        let met = check(event("opb", "read ../opb/phy_lab", 0), &mut progress);
+        assert_eq!(met, ["lab"]);
+        assert_eq!(current(&objectives, &progress).unwrap().id, "look");
+
+        check(event("mess", "ls -a ..", 0), &mut progress);
+        assert!(current(&objectives, &progress).is_none());
+    }
+}
@@ -0,0 +1,294 @@
+//! Triggers let sekai authors make things happen when the player interacts with an object.
+//!
+//! A trigger is a property of an object in its room's `info.json`, named after the event:
+//!
+//! ```json
+//! "note.txt": {
+//!     "locked": "00",
+//!     "on_read": [
+//!         { "print": "A draft blows through the room." },
+//!         { "reveal": "secret_passage" },
+//!         { "set": "read_note" },
+//!         { "unlock": "../room_8" }
+//!     ]
+//! }
+//! ```
+//!
+//! `on_enter` belongs to the room entered, so it sits in the info.json of the room above it.
+//! A trigger can also be written as `{ "once": true, "if": "<flag>", "do": [...] }` to fire
+//! only once, or only after a flag was set. Paths are relative to the room holding the
+//! info.json. Actions run in order and depend only on the sekai and the player's progress,
+//! so a trigger always does the same thing in the same state.
+//!
+//! Triggers fire on the events the commands publish, through `on_event`.
+
+use super::info_reader::read_get_obj_info;
+use crate::commands::cmds::normalize_path;
+use crate::utils::events::{self, EventRecord, Notice, ShellEvent};
+use crate::utils::find_root::home_relative;
+use crate::utils::log;
+use crate::utils::progress::Progress;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// What the player did to an object.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    Enter,
+    Read,
+    Unlock,
+    Solve,
+}
+
+impl Event {
+    /// Name of the trigger property in info.json.
+    pub fn property(self) -> &'static str {
+        match self {
+            Event::Enter => "on_enter",
+            Event::Read => "on_read",
+            Event::Unlock => "on_unlock",
+            Event::Solve => "on_solve",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Action {
+    /// Shows a message to the player.
+    Print(String),
+    /// Reveals a hidden object to this player.
+    Reveal(String),
+    /// Sets a progress flag.
+    Set(String),
+    /// Unlocks a level or chest for good, for this player.
+    Unlock(String),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Trigger {
+    /// Fires only the first time.
+    #[serde(default)]
+    pub once: bool,
+    /// Flag that must be set for the trigger to fire.
+    #[serde(default, rename = "if")]
+    pub condition: Option<String>,
+    #[serde(rename = "do")]
+    pub actions: Vec<Action>,
+}
+
+/// A bare list of actions is a trigger without conditions.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TriggerDef {
+    Actions(Vec<Action>),
+    Full(Trigger),
+}
+
+/// What firing a trigger does, with its paths resolved.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Effects {
+    pub messages: Vec<String>,
+    pub reveal: Vec<PathBuf>,
+    pub set: Vec<String>,
+    pub unlock: Vec<PathBuf>,
+}
+
+/// Reads the `event` trigger of the object at `obj_path`, if it has one.
+pub fn read_trigger(obj_path: &Path, event: Event) -> Result<Option<Trigger>, String> {
+    let (Some(dir), Some(name)) = (obj_path.parent(), obj_path.file_name()) else {
+        return Ok(None);
+    };
+    let info = read_get_obj_info(&dir.join(".dir_info/info.json"), &name.to_string_lossy())
+        .map_err(|e| e.to_string())?;
+    let Some(value) = info.properties.get(event.property()) else {
+        return Ok(None);
+    };
+    match serde_json::from_value(value.clone()) {
+        Ok(TriggerDef::Actions(actions)) => Ok(Some(Trigger {
+            actions,
+            ..Default::default()
+        })),
+        Ok(TriggerDef::Full(trigger)) => Ok(Some(trigger)),
+        Err(e) => Err(format!("Invalid {} trigger: {e}", event.property())),
+    }
+}
+
+/// Key under which a fired trigger is remembered.
+fn fired_key(obj_path: &Path, event: Event, root_dir: &Path) -> String {
+    format!("{}#{}", home_relative(obj_path, root_dir), event.property())
+}
+
+/// Works out what the trigger does in the current state, without doing it.
+/// Returns `None` if it does not fire.
+pub fn evaluate(
+    trigger: &Trigger,
+    obj_path: &Path,
+    event: Event,
+    root_dir: &Path,
+    progress: &Progress,
+) -> Option<Effects> {
+    if trigger.once
+        && progress
+            .fired
+            .contains(&fired_key(obj_path, event, root_dir))
+    {
+        return None;
+    }
+    if let Some(flag) = &trigger.condition
+        && !progress.flags.contains(flag)
+    {
+        return None;
+    }
+
+    let dir = obj_path.parent().unwrap_or(root_dir);
+    let resolve = |path: &str| normalize_path(&dir.join(path));
+    let mut effects = Effects::default();
+    for action in &trigger.actions {
+        match action {
+            Action::Print(message) => effects.messages.push(message.clone()),
+            Action::Reveal(path) => effects.reveal.push(resolve(path)),
+            Action::Set(flag) => effects.set.push(flag.clone()),
+            Action::Unlock(path) => effects.unlock.push(resolve(path)),
+        }
+    }
+    Some(effects)
+}
+
+/// Carries out the effects of a trigger and records it as fired.
+/// Returns the messages to show. Paths outside the sekai are ignored.
+pub fn apply(
+    effects: Effects,
+    obj_path: &Path,
+    event: Event,
+    root_dir: &Path,
+    progress: &mut Progress,
+) -> Vec<String> {
+    progress.fired.insert(fired_key(obj_path, event, root_dir));
+    progress.flags.extend(effects.set);
+    for path in effects.reveal.iter().filter(|p| p.starts_with(root_dir)) {
+        progress.revealed.insert(home_relative(path, root_dir));
+    }
+    for path in effects.unlock.iter().filter(|p| p.starts_with(root_dir)) {
+        progress.unlocked.insert(home_relative(path, root_dir));
+    }
+    effects.messages
+}
+
+/// Fires the `event` trigger of the object at `obj_path`, if any, updating `progress`.
+/// Returns the messages to show.
+pub fn fire(
+    event: Event,
+    obj_path: &Path,
+    root_dir: &Path,
+    progress: &mut Progress,
+) -> Vec<String> {
+    let trigger = match read_trigger(obj_path, event) {
+        Ok(Some(trigger)) => trigger,
+        Ok(None) => return Vec::new(),
+        Err(e) => {
+            log::log_warning(
+                "triggers",
+                &format!("{}: {e}", home_relative(obj_path, root_dir)),
+            );
+            return Vec::new();
+        }
+    };
+    match evaluate(&trigger, obj_path, event, root_dir, progress) {
+        Some(effects) => {
+            log::log_info(
+                "triggers",
+                &format!("Fired {}", fired_key(obj_path, event, root_dir)),
+            );
+            apply(effects, obj_path, event, root_dir, progress)
+        }
+        None => Vec::new(),
+    }
+}
+
+/// Subscriber firing the trigger of the object an event happened to, for the player it
+/// happened to. What the trigger prints is told to the player.
+pub fn on_event(record: &EventRecord) {
+    let (event, path) = match &record.event {
+        ShellEvent::DirectoryChanged { to, .. } => (Event::Enter, to),
+        ShellEvent::FileRead { path } => (Event::Read, path),
+        ShellEvent::ObjectUnlocked { path } => (Event::Unlock, path),
+        ShellEvent::LevelSolved { level } => (Event::Solve, level),
+        _ => return,
+    };
+    let obj_path = normalize_path(&record.root_dir.join(path));
+    let messages = Progress::update(&record.user, |progress| {
+        fire(event, &obj_path, &record.root_dir, progress)
+    });
+    for message in messages {
+        events::tell(Notice::Line(message));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metainfo::info_reader::update_obj_status;
+    use crate::metainfo::read_lock_perm;
+    use crate::utils::test_utils::setup_test_dir;
+    use serde_json::json;
+
+    #[test]
+    fn test_triggers() {
+        let (_temp, root) = setup_test_dir(true);
+        let note = root.join("file1.txt");
+        let locked = root.join("subdir2");
+        for (key, value) in [
+            ("obj_salt", "salt"),
+            ("compare_me", "hash"),
+            ("locked", "01"),
+        ] {
+            update_obj_status(&locked, "subdir2", key, json!(value)).unwrap();
+        }
+        update_obj_status(
+            &note,
+            "file1.txt",
+            "on_read",
+            json!({
+                "once": true,
+                "do": [
+                    { "print": "A draft blows." },
+                    { "reveal": "subdir1/nested1/file4.txt" },
+                    { "set": "read_note" },
+                    { "unlock": "subdir2" }
+                ]
+            }),
+        )
+        .unwrap();
+        update_obj_status(&note, "file1.txt", "on_enter", json!([{ "print": "Hi" }])).unwrap();
+
+        let mut progress = Progress::default();
+        assert!(fire(Event::Solve, &note, &root, &mut progress).is_empty());
+        assert_eq!(
+            fire(Event::Read, &note, &root, &mut progress),
+            ["A draft blows."]
+        );
+        assert!(progress.flags.contains("read_note"));
+        assert!(progress.revealed.contains("subdir1/nested1/file4.txt"));
+        assert!(progress.fired.contains("file1.txt#on_read"));
+        assert!(progress.unlocked.contains("subdir2"));
+        assert_eq!(read_lock_perm(&locked), Ok((false, true)));
+        // Fires only once, while a bare list fires every time
+        assert!(fire(Event::Read, &note, &root, &mut progress).is_empty());
+        assert_eq!(fire(Event::Enter, &note, &root, &mut progress), ["Hi"]);
+        assert_eq!(fire(Event::Enter, &note, &root, &mut progress), ["Hi"]);
+
+        let trigger = Trigger {
+            condition: Some("opened_gate".to_string()),
+            actions: vec![Action::Print("Through".to_string())],
+            ..Default::default()
+        };
+        assert_eq!(
+            evaluate(&trigger, &note, Event::Enter, &root, &progress),
+            None
+        );
+        progress.flags.insert("opened_gate".to_string());
+        assert!(evaluate(&trigger, &note, Event::Enter, &root, &progress).is_some());
+    }
+}
@@ -1,36 +1,232 @@
 use std::env;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::Write;
-use std::path::PathBuf;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::{Path, PathBuf};
 
 // === External Crates ===
 use dotenvy::dotenv;
+use rocket::config::TlsConfig;
 use rocket::fairing::{Fairing, Info, Kind};
-use rocket::fs::{FileServer, relative};
-use rocket::http::Header;
-use rocket::serde::{Serialize, json::Json};
-use rocket::{Config, Request, Response, get, options, routes};
+use rocket::form::Form;
+use rocket::fs::FileServer;
+use rocket::http::uncased::Uncased;
+use rocket::http::{Header, Status};
+use rocket::serde::{Deserialize, Serialize, json::Json};
+use rocket::{Config, FromForm, Request, Response, get, options, post, routes};
 
 // === Local Modules ===
-use crate::commands::cmds;
+use crate::commands;
+use crate::commands::cmds::{self, CommandOutput};
+use crate::utils::audit::{self, AuditEntry};
 use crate::utils::auth;
+use crate::utils::caller::{Caller, as_caller};
+use crate::utils::config::get_config_path;
 use crate::utils::globals::get_world_dir;
-use crate::utils::{find_root, prompt::DummyPrompter};
+use crate::utils::{find_root, prompt::WebPrompter, shell_history, style, tab_completion};
+
+// === Server Configuration ===
+/// Settings of the web server, read from `~/.config/deemak/server.json` or the file given
+/// with `--server-config`. Every field is optional:
+///
+/// ```json
+/// {
+///     "address": "0.0.0.0",
+///     "port": 8443,
+///     "tls": { "cert": "certs/cert.pem", "key": "certs/key.pem" },
+///     "static_dir": "static",
+///     "sekai": "sekai",
+///     "cors_origins": ["https://deemak.example.org"],
+///     "public_url": "https://deemak.example.org",
+///     "trusted_ip_header": "X-Real-IP"
+/// }
+/// ```
+///
+/// Relative paths are relative to the working directory. Command line flags override the
+/// file, and the file overrides `BACKEND_PORT`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub address: IpAddr,
+    pub port: u16,
+    /// Serves HTTPS when set.
+    pub tls: Option<TlsPaths>,
+    /// Frontend files, where `config.js` is written too.
+    pub static_dir: PathBuf,
+    /// Sekai to serve, when none is given on the command line.
+    pub sekai: Option<PathBuf>,
+    /// Origins allowed to call the backend from a browser. Any origin when empty.
+    pub cors_origins: Vec<String>,
+    /// URL the browser reaches the backend at, behind a proxy or another host name. The
+    /// frontend calls the origin it was loaded from when unset.
+    pub public_url: Option<String>,
+    /// Header the proxy in front of the server puts the client's IP in. Only set it when the
+    /// server cannot be reached but through that proxy, as anyone else could forge it. The
+    /// IP of the connection is used when unset.
+    pub trusted_ip_header: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TlsPaths {
+    /// Certificate chain, in PEM.
+    pub cert: PathBuf,
+    /// Private key, in PEM.
+    pub key: PathBuf,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        let port = env::var("BACKEND_PORT")
+            .ok()
+            .and_then(|port| port.parse().ok())
+            .unwrap_or(8001);
+        Self {
+            address: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port,
+            tls: None,
+            static_dir: PathBuf::from("static"),
+            sekai: None,
+            cors_origins: Vec::new(),
+            public_url: None,
+            trusted_ip_header: None,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Path of the default server config: `~/.config/deemak/server.json`
+    pub fn default_path() -> PathBuf {
+        let mut path = get_config_path();
+        path.set_file_name("server.json");
+        path
+    }
+
+    /// Reads the config at `path`, or the default one if it exists. `.env` is loaded first,
+    /// for `BACKEND_PORT`.
+    pub fn load(path: Option<&Path>) -> Result<Self, String> {
+        dotenv().ok();
+        let default_path = Self::default_path();
+        let path = match path {
+            Some(path) => path,
+            None if default_path.exists() => &default_path,
+            None => return Ok(Self::default()),
+        };
+        let contents = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        serde_json::from_str(&contents).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    /// URL the frontend calls the backend at: `public_url`, or empty for the origin the
+    /// frontend was served from, as this server serves both.
+    pub fn backend_url(&self) -> String {
+        self.public_url
+            .as_deref()
+            .map(|url| url.trim_end_matches('/').to_string())
+            .unwrap_or_default()
+    }
+}
 
 // === Data Structures ===
+/// Response of `/run`. `output` is everything the command printed as plain text, and `html`
+/// the same with its styling. `status`, `stdout`, `stderr` and `payload` come from the
+/// command's `CommandOutput`. When the command asks for something, only `prompt` is set,
+/// and the browser sends the command again with the answer.
 #[derive(Serialize)]
 struct CommandResponse {
     output: String,
+    html: String,
     new_current_dir: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prompt: Option<String>,
+    #[serde(flatten)]
+    result: CommandOutput,
+}
+
+impl CommandResponse {
+    fn new(result: CommandOutput, new_current_dir: Option<String>) -> Json<Self> {
+        let text = result.text();
+        Json(CommandResponse {
+            output: style::strip(&text),
+            html: style::to_html(&text),
+            new_current_dir,
+            prompt: None,
+            result,
+        })
+    }
+
+    fn prompt(message: String) -> Json<Self> {
+        Json(CommandResponse {
+            output: String::new(),
+            html: String::new(),
+            new_current_dir: None,
+            prompt: Some(message),
+            result: CommandOutput::default(),
+        })
+    }
+}
+
+/// Form of `/run`. It is posted, so that answers stay out of URLs and request logs.
+#[derive(FromForm)]
+struct RunInput {
+    command: String,
+    current_dir: String,
+    token: Option<String>,
+    /// Answers to the prompts of the command, in order.
+    answer: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct CompletionResponse {
+    input: String,
+    completions: Vec<String>,
+}
+
+/// Resolves the history owner of a request from its token.
+fn history_user(token: Option<&str>) -> String {
+    token
+        .and_then(auth::username_from_token)
+        .unwrap_or_else(|| shell_history::GUEST_USER.to_string())
 }
 
 // === Command Execution Endpoint ===
-#[get("/run?<command>&<current_dir>")]
-fn response(command: &str, current_dir: &str) -> Json<CommandResponse> {
+#[post("/run", data = "<input>")]
+fn response(input: Form<RunInput>, ip: Option<IpAddr>) -> Json<CommandResponse> {
+    let input = input.into_inner();
+    // The commands keep their state (aliases, variables, progress...) per request user
+    let user = history_user(input.token.as_deref());
+    let caller = Caller {
+        user: user.clone(),
+        ip,
+    };
+    as_caller(caller, || {
+        run_request(&input.command, &input.current_dir, input.answer, &user)
+    })
+}
+
+fn run_request(
+    command: &str,
+    current_dir: &str,
+    answers: Vec<String>,
+    user: &str,
+) -> Json<CommandResponse> {
     use cmds::{CommandResult, cmd_manager};
 
+    // Expand `!!`/`!n` against this user's history, then record the command, unless it is
+    // sent again with answers
+    let command =
+        match shell_history::expand_history(command, &shell_history::get_user_history(user)) {
+            Ok(expanded) => expanded,
+            Err(e) => return CommandResponse::new(CommandOutput::err(e), None),
+        };
+    if answers.is_empty() {
+        shell_history::add_to_user_history(user, &command);
+    }
+
     let world_dir = &get_world_dir();
     let parts: Vec<&str> = command.split_whitespace().collect();
+    if parts.first() == Some(&"history") {
+        let output = commands::history(&parts[1..], user).into();
+        return CommandResponse::new(output, None);
+    }
     let root_dir = find_root::get_home(world_dir).expect("Could not find sekai home directory");
     let mut current_dir = if current_dir.is_empty() {
         root_dir.clone()
@@ -38,27 +234,109 @@ fn response(command: &str, current_dir: &str) -> Json<CommandResponse> {
         PathBuf::from(current_dir)
     };
 
-    let mut prompter = DummyPrompter;
-    match cmd_manager(&parts, &current_dir, &root_dir, &mut prompter) {
-        CommandResult::Output(output) => Json(CommandResponse {
-            output,
-            new_current_dir: None,
-        }),
-        CommandResult::ChangeDirectory(new_dir, message) => Json(CommandResponse {
-            output: message,
-            new_current_dir: Some(new_dir.display().to_string()),
-        }),
-        CommandResult::Clear => Json(CommandResponse {
-            output: "__CLEAR__".to_string(),
-            new_current_dir: None,
+    let mut prompter = WebPrompter::new(answers);
+    let result = cmd_manager(&parts, &current_dir, &root_dir, &mut prompter);
+    if let Some(message) = prompter.unanswered {
+        return CommandResponse::prompt(message);
+    }
+    let (result, new_dir) = match result {
+        CommandResult::Output(result) => (result, None),
+        CommandResult::ChangeDirectory(new_dir, result) => {
+            (result, Some(new_dir.display().to_string()))
+        }
+        CommandResult::Clear => (CommandOutput::ok("__CLEAR__"), None),
+        CommandResult::Exit => (CommandOutput::ok("__EXIT__"), None),
+        CommandResult::NotFound => (
+            CommandOutput {
+                status: cmds::STATUS_NOT_FOUND,
+                stderr: "Command not found. Try `help`.".to_string(),
+                ..Default::default()
+            },
+            None,
+        ),
+    };
+    CommandResponse::new(result, new_dir)
+}
+
+// === Shell History Endpoint ===
+#[get("/history?<token>")]
+fn history(token: Option<&str>) -> Json<Vec<String>> {
+    Json(shell_history::get_user_history(&history_user(token)))
+}
+
+// === Audit Log Endpoint ===
+/// Audit log entries, newest first, for the admins listed in `ADMIN_USERS`. Filters by
+/// `user`, `action` and `failed`, and returns at most `limit` entries (100 by default).
+#[get("/admin/audit?<token>&<user>&<action>&<failed>&<limit>")]
+fn audit_log(
+    token: Option<&str>,
+    user: Option<&str>,
+    action: Option<&str>,
+    failed: Option<bool>,
+    limit: Option<usize>,
+    ip: Option<IpAddr>,
+) -> Result<Json<Vec<AuditEntry>>, Status> {
+    let requester = token.and_then(auth::username_from_token);
+    let allowed = requester.as_deref().is_some_and(auth::is_admin);
+    if !allowed {
+        let requester = requester.unwrap_or_else(|| shell_history::GUEST_USER.to_string());
+        let message = "Forbidden: not an admin";
+        audit::record(AuditEntry::new(
+            &requester, ip, "admin", "audit", false, message,
+        ));
+        return Err(Status::Forbidden);
+    }
+
+    let entries = audit::read_from(&audit::get_audit_path())
+        .into_iter()
+        .rev()
+        .filter(|entry| user.is_none_or(|user| entry.user == user))
+        .filter(|entry| action.is_none_or(|action| entry.action == action))
+        .filter(|entry| failed.is_none_or(|failed| entry.success != failed))
+        .take(limit.unwrap_or(100))
+        .collect();
+    Ok(Json(entries))
+}
+
+// === Tab Completion Endpoint ===
+#[get("/complete?<input>&<current_dir>&<token>")]
+fn complete(input: &str, current_dir: &str, token: Option<&str>) -> Json<CompletionResponse> {
+    let caller = Caller {
+        user: history_user(token),
+        ip: None,
+    };
+    as_caller(caller, || complete_input(input, current_dir))
+}
+
+fn complete_input(input: &str, current_dir: &str) -> Json<CompletionResponse> {
+    use tab_completion::{TabCompletionResult, completions, process_tab_completion};
+
+    let world_dir = &get_world_dir();
+    let root_dir = find_root::get_home(world_dir).expect("Could not find sekai home directory");
+    let current_dir = if current_dir.is_empty() {
+        root_dir.clone()
+    } else {
+        PathBuf::from(current_dir)
+    };
+
+    let parts = tab_completion::split_for_completion(input);
+    let matches = completions(&parts, &current_dir, &root_dir);
+    // The browser wraps long lists itself, so never ask before showing them
+    match process_tab_completion(parts, matches, 80, usize::MAX, input, Some("$")) {
+        TabCompletionResult::SingleMatch(completed)
+        | TabCompletionResult::CommonPrefix(completed) => Json(CompletionResponse {
+            input: completed,
+            completions: Vec::new(),
         }),
-        CommandResult::Exit => Json(CommandResponse {
-            output: "__EXIT__".to_string(),
-            new_current_dir: None,
+        TabCompletionResult::DisplayCompletions {
+            completion_lines, ..
+        } => Json(CompletionResponse {
+            input: input.to_string(),
+            completions: completion_lines,
         }),
-        CommandResult::NotFound => Json(CommandResponse {
-            output: "Command not found. Try `help`.".to_string(),
-            new_current_dir: None,
+        TabCompletionResult::NoAction => Json(CompletionResponse {
+            input: input.to_string(),
+            completions: Vec::new(),
         }),
     }
 }
@@ -70,7 +348,10 @@ fn cors_preflight() -> &'static str {
 }
 
 // === Add CORS headers ===
-pub struct Cors;
+/// Allows browsers from `origins` to call the backend, or from anywhere if it is empty.
+pub struct Cors {
+    pub origins: Vec<String>,
+}
 
 #[rocket::async_trait]
 impl Fairing for Cors {
@@ -81,8 +362,21 @@ impl Fairing for Cors {
         }
     }
 
-    async fn on_response<'r>(&self, _req: &'r Request<'_>, res: &mut Response<'r>) {
-        res.set_header(Header::new("Access-Control-Allow-Origin", "*"));
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        if self.origins.is_empty() {
+            res.set_header(Header::new("Access-Control-Allow-Origin", "*"));
+        } else {
+            let origin = req.headers().get_one("Origin");
+            let Some(origin) = origin.filter(|origin| self.origins.iter().any(|o| o == origin))
+            else {
+                return;
+            };
+            res.set_header(Header::new(
+                "Access-Control-Allow-Origin",
+                origin.to_string(),
+            ));
+            res.set_header(Header::new("Vary", "Origin"));
+        }
         res.set_header(Header::new(
             "Access-Control-Allow-Methods",
             "POST, GET, OPTIONS",
@@ -93,39 +387,62 @@ impl Fairing for Cors {
 }
 
 // === Write Frontend Config ===
-fn generate_config_js(port: u16) {
-    let js_content = format!(r#"export const BACKEND_URL = "http://localhost:{port}";"#);
+fn generate_config_js(config: &ServerConfig) {
+    let url = config.backend_url();
+    let js_content = format!(r#"export const BACKEND_URL = "{url}";"#);
 
-    let path = "static/config.js";
-    let mut file = File::create(path).expect("Failed to create config.js");
+    let path = config.static_dir.join("config.js");
+    let mut file = File::create(&path).expect("Failed to create config.js");
     file.write_all(js_content.as_bytes())
         .expect("Failed to write config.js");
-    println!("Generated static/config.js with port {port}");
+    let backend = if url.is_empty() {
+        "the same origin"
+    } else {
+        &url
+    };
+    println!("Generated {} for {backend}", path.display());
 }
 
 // === Rocket Entry Point ===
-#[rocket::main]
-pub async fn server() -> Option<Result<(), rocket::Error>> {
-    dotenv().ok();
+pub fn server(server_config: ServerConfig) -> Option<Result<(), rocket::Error>> {
+    rocket::execute(launch(server_config))
+}
 
-    let port: u16 = env::var("BACKEND_PORT")
-        .unwrap_or_else(|_| "8001".to_string())
-        .parse()
-        .expect("Invalid port number");
+async fn launch(server_config: ServerConfig) -> Option<Result<(), rocket::Error>> {
+    dotenv().ok();
 
-    generate_config_js(port);
+    generate_config_js(&server_config);
 
     let config = Config {
-        port,
+        address: server_config.address,
+        port: server_config.port,
+        tls: server_config
+            .tls
+            .as_ref()
+            .map(|tls| TlsConfig::from_paths(&tls.cert, &tls.key)),
+        ip_header: server_config
+            .trusted_ip_header
+            .clone()
+            .map(Uncased::from_owned),
         ..Config::default()
     };
 
     let _rocket = rocket::custom(config)
-        .attach(Cors)
-        .mount("/", FileServer::from(relative!("static")))
+        .attach(Cors {
+            origins: server_config.cors_origins.clone(),
+        })
+        .mount("/", FileServer::from(&server_config.static_dir))
         .mount(
             "/backend",
-            routes![response, cors_preflight, auth::register, auth::login],
+            routes![
+                response,
+                history,
+                audit_log,
+                complete,
+                cors_preflight,
+                auth::register,
+                auth::login
+            ],
         )
         .launch()
         .await
@@ -133,3 +450,37 @@ pub async fn server() -> Option<Result<(), rocket::Error>> {
 
     Some(Ok(()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_config() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("server.json");
+        fs::write(
+            &path,
+            r#"{
+                "address": "0.0.0.0",
+                "port": 8443,
+                "tls": { "cert": "cert.pem", "key": "key.pem" },
+                "cors_origins": ["https://deemak.example.org"]
+            }"#,
+        )
+        .unwrap();
+        let config = ServerConfig::load(Some(&path)).unwrap();
+        assert_eq!(config.port, 8443);
+        assert_eq!(config.static_dir, PathBuf::from("static"));
+        assert_eq!(config.sekai, None);
+        assert_eq!(config.backend_url(), "");
+        assert_eq!(config.trusted_ip_header, None);
+
+        let config = ServerConfig {
+            public_url: Some("https://deemak.example.org/".to_string()),
+            ..config
+        };
+        assert_eq!(config.backend_url(), "https://deemak.example.org");
+        assert!(ServerConfig::load(Some(&temp.path().join("missing.json"))).is_err());
+    }
+}
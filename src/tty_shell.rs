@@ -0,0 +1,502 @@
+use crate::commands::cmds::{self, CommandOutput, CommandResult};
+use crate::gui_shell::{DEEMAK_BANNER, INITIAL_MSG};
+use crate::metainfo::info_reader::read_validate_info;
+use crate::utils::auth::{load_users, login_with_password, register_user};
+use crate::utils::line_editor::LineEditor;
+use crate::utils::tab_completion::{
+    TabCompletionResult, completions, process_tab_completion, split_for_completion,
+};
+use crate::utils::{find_root, log, prompt::UserPrompter, shell_history, style};
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::path::PathBuf;
+
+/// Keys understood by the terminal line editor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Delete,
+    Tab,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    WordLeft,
+    WordRight,
+    CtrlC,
+    CtrlD,
+    CtrlK,
+    CtrlL,
+    CtrlU,
+    CtrlW,
+    CtrlY,
+    Unknown,
+}
+
+/// Puts the terminal in non-canonical, no-echo mode while alive.
+#[cfg(unix)]
+struct RawMode {
+    original: libc::termios,
+}
+
+#[cfg(unix)]
+impl RawMode {
+    fn enable() -> Option<Self> {
+        unsafe {
+            let mut termios: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut termios) != 0 {
+                return None;
+            }
+            let original = termios;
+            // Read key by key without echo; Ctrl+C/Ctrl+S are handled by the shell itself
+            termios.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+            termios.c_iflag &= !(libc::IXON | libc::ICRNL);
+            termios.c_cc[libc::VMIN] = 1;
+            termios.c_cc[libc::VTIME] = 0;
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSAFLUSH, &termios) != 0 {
+                return None;
+            }
+            Some(Self { original })
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSAFLUSH, &self.original);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+struct RawMode;
+
+#[cfg(not(unix))]
+impl RawMode {
+    fn enable() -> Option<Self> {
+        None
+    }
+}
+
+/// Returns the terminal size as (columns, rows), defaulting to 80x24.
+fn terminal_size() -> (usize, usize) {
+    #[cfg(unix)]
+    unsafe {
+        let mut size: libc::winsize = std::mem::zeroed();
+        if libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) == 0 && size.ws_col > 0 {
+            return (size.ws_col as usize, size.ws_row as usize);
+        }
+    }
+    (80, 24)
+}
+
+/// Shell running on the terminal through stdin/stdout.
+pub struct TtyShell {
+    current_dir: PathBuf,
+    root_dir: PathBuf,
+    raw: Option<RawMode>,
+}
+
+impl UserPrompter for TtyShell {
+    fn confirm(&mut self, message: &str) -> bool {
+        let answer = self
+            .read_line(&format!("{message} [y/N] "), false)
+            .unwrap_or_default();
+        matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+    }
+    fn input(&mut self, message: &str) -> String {
+        self.read_line(&format!("{message}: "), false)
+            .unwrap_or_default()
+    }
+}
+
+impl TtyShell {
+    pub fn new_sekai(sekai_dir: PathBuf) -> Self {
+        let root_dir =
+            find_root::get_home(&sekai_dir).expect("Could not find sekai home directory");
+        // Line editing only makes sense when a person is typing
+        let raw = if io::stdin().is_terminal() {
+            RawMode::enable()
+        } else {
+            None
+        };
+        Self {
+            current_dir: root_dir.clone(),
+            root_dir,
+            raw,
+        }
+    }
+
+    pub fn run(&mut self) {
+        println!("{DEEMAK_BANNER}");
+        println!("{INITIAL_MSG}");
+
+        let info_path = self.root_dir.join(".dir_info").join("info.json");
+        let home_about = read_validate_info(&info_path)
+            .map(|info| info.about)
+            .unwrap_or_else(|_| "Welcome User to Deemak!".to_string());
+        println!("\nYou are in 'HOME'\n\nAbout:\n{home_about}\n");
+
+        while let Some(input) = self.read_line("> ", true) {
+            let input = input.trim();
+            if input.is_empty() {
+                continue;
+            }
+            let input = match shell_history::expand_history(input, &shell_history::get_history()) {
+                Ok(expanded) => expanded,
+                Err(e) => {
+                    println!("{e}");
+                    continue;
+                }
+            };
+            shell_history::add_to_history(&input);
+            if !self.process_shell_input(&input) {
+                break;
+            }
+        }
+    }
+
+    /// Runs a command line. Returns false when the shell should exit.
+    pub fn process_shell_input(&mut self, input: &str) -> bool {
+        let current_dir = self.current_dir.clone();
+        let root_dir = self.root_dir.clone();
+        match cmds::run_line(input, &current_dir, &root_dir, self) {
+            CommandResult::ChangeDirectory(new_dir, output) => {
+                self.current_dir = new_dir;
+                print_output(&output);
+            }
+            CommandResult::Output(output) => print_output(&output),
+            CommandResult::Clear => clear_screen(),
+            CommandResult::Exit => return false,
+            CommandResult::NotFound => println!("Command not found. Try `help`."),
+        }
+        true
+    }
+
+    /// Reads one line from the user. Returns None at end of input.
+    /// `editing` enables history navigation and tab completion.
+    fn read_line(&mut self, prompt: &str, editing: bool) -> Option<String> {
+        if self.raw.is_none() {
+            print!("{prompt}");
+            let _ = io::stdout().flush();
+            let mut line = String::new();
+            return match io::stdin().lock().read_line(&mut line) {
+                Ok(0) | Err(_) => None,
+                Ok(_) => Some(line.trim_end_matches(['\n', '\r']).to_string()),
+            };
+        }
+
+        let mut editor = LineEditor::new();
+        let history = if editing {
+            shell_history::get_history()
+        } else {
+            Vec::new()
+        };
+        let mut history_index: Option<usize> = None;
+        let mut working_buffer = String::new();
+
+        redraw(prompt, &editor);
+        loop {
+            match read_key()? {
+                Key::Enter => {
+                    println!();
+                    return Some(editor.take());
+                }
+                Key::Char(c) => editor.insert(c),
+                Key::Backspace => editor.backspace(),
+                Key::Delete => editor.delete(),
+                Key::Left => editor.move_left(),
+                Key::Right => editor.move_right(),
+                Key::WordLeft => editor.word_left(),
+                Key::WordRight => editor.word_right(),
+                Key::Home => editor.move_home(),
+                Key::End => editor.move_end(),
+                Key::CtrlK => editor.kill_to_end(),
+                Key::CtrlU => editor.kill_to_start(),
+                Key::CtrlW => editor.kill_word(),
+                Key::CtrlY => editor.yank(),
+                Key::Up if !history.is_empty() => {
+                    let new_index = match history_index {
+                        Some(index) => index.saturating_sub(1),
+                        None => {
+                            working_buffer = editor.line().to_string();
+                            history.len() - 1
+                        }
+                    };
+                    editor.set_line(history[new_index].clone());
+                    history_index = Some(new_index);
+                }
+                Key::Down => {
+                    if let Some(index) = history_index {
+                        if index + 1 < history.len() {
+                            editor.set_line(history[index + 1].clone());
+                            history_index = Some(index + 1);
+                        } else {
+                            editor.set_line(std::mem::take(&mut working_buffer));
+                            history_index = None;
+                        }
+                    }
+                }
+                Key::Tab if editing => {
+                    let completed = self.complete(prompt, editor.line());
+                    editor.set_line(completed);
+                }
+                Key::CtrlC => {
+                    // Abandon the line and start a new prompt
+                    println!("^C");
+                    editor.clear();
+                    history_index = None;
+                }
+                Key::CtrlD if editor.is_empty() => {
+                    println!();
+                    return None;
+                }
+                Key::CtrlL => {
+                    print!("\x1b[2J\x1b[H");
+                }
+                _ => {}
+            }
+            redraw(prompt, &editor);
+        }
+    }
+
+    /// Reads a line without echoing it, for passwords.
+    fn read_secret(&mut self, prompt: &str) -> Option<String> {
+        if self.raw.is_none() {
+            return self.read_line(prompt, false);
+        }
+        print!("{prompt}");
+        let _ = io::stdout().flush();
+        let mut secret = String::new();
+        loop {
+            match read_key()? {
+                Key::Enter => {
+                    println!();
+                    return Some(secret);
+                }
+                Key::Char(c) => secret.push(c),
+                Key::Backspace => {
+                    secret.pop();
+                }
+                Key::CtrlC | Key::CtrlD => {
+                    println!();
+                    return None;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Completes the last word of `input` against the current directory.
+    fn complete(&mut self, prompt: &str, input: &str) -> String {
+        let parts = split_for_completion(input);
+        let matches = completions(&parts, &self.current_dir, &self.root_dir);
+
+        let (term_width, term_height) = terminal_size();
+        match process_tab_completion(
+            parts,
+            matches,
+            term_width,
+            term_height,
+            input,
+            Some(prompt.trim_end()),
+        ) {
+            TabCompletionResult::SingleMatch(new_input)
+            | TabCompletionResult::CommonPrefix(new_input) => new_input,
+            TabCompletionResult::DisplayCompletions {
+                current_line,
+                completion_lines,
+                should_display_all,
+            } => {
+                println!();
+                if !should_display_all
+                    || self.confirm(&format!(
+                        "Display all {} possibilities? (y or n)",
+                        completion_lines.len()
+                    ))
+                {
+                    for line in completion_lines {
+                        println!("{line}");
+                    }
+                }
+                input.to_string()
+            }
+            TabCompletionResult::NoAction => input.to_string(),
+        }
+    }
+}
+
+/// Clears the terminal, leaving the welcome message.
+fn clear_screen() {
+    print!("\x1b[2J\x1b[H");
+    println!("{INITIAL_MSG}");
+}
+
+/// Prints the output of a command, errors on stderr.
+/// Styling is only kept when printing to a terminal.
+fn print_output(output: &CommandOutput) {
+    if output.clear {
+        clear_screen();
+    }
+    if !output.stdout.is_empty() || output.stderr.is_empty() {
+        if io::stdout().is_terminal() {
+            println!("{}", output.stdout);
+        } else {
+            println!("{}", style::strip(&output.stdout));
+        }
+    }
+    if !output.stderr.is_empty() {
+        if io::stderr().is_terminal() {
+            eprintln!("{}", style::error(&output.stderr));
+        } else {
+            eprintln!("{}", style::strip(&output.stderr));
+        }
+    }
+}
+
+/// Redraws the prompt line and places the cursor.
+fn redraw(prompt: &str, editor: &LineEditor) {
+    let mut stdout = io::stdout();
+    let line = editor.line();
+    let back = line[editor.cursor()..].chars().count();
+    let _ = write!(stdout, "\r\x1b[K{prompt}{line}");
+    if back > 0 {
+        let _ = write!(stdout, "\x1b[{back}D");
+    }
+    let _ = stdout.flush();
+}
+
+fn read_byte() -> Option<u8> {
+    let mut byte = [0u8; 1];
+    match io::stdin().lock().read(&mut byte) {
+        Ok(1) => Some(byte[0]),
+        _ => None,
+    }
+}
+
+/// Reads a single key press, decoding UTF-8 and escape sequences.
+fn read_key() -> Option<Key> {
+    let byte = read_byte()?;
+    Some(match byte {
+        b'\r' | b'\n' => Key::Enter,
+        b'\t' => Key::Tab,
+        0x7f | 0x08 => Key::Backspace,
+        0x01 => Key::Home,
+        0x05 => Key::End,
+        0x03 => Key::CtrlC,
+        0x04 => Key::CtrlD,
+        0x0b => Key::CtrlK,
+        0x0c => Key::CtrlL,
+        0x15 => Key::CtrlU,
+        0x17 => Key::CtrlW,
+        0x19 => Key::CtrlY,
+        0x1b => match read_byte()? {
+            b'b' => Key::WordLeft,
+            b'f' => Key::WordRight,
+            b'[' | b'O' => match read_byte()? {
+                b'A' => Key::Up,
+                b'B' => Key::Down,
+                b'C' => Key::Right,
+                b'D' => Key::Left,
+                b'H' => Key::Home,
+                b'F' => Key::End,
+                digit @ b'0'..=b'9' => {
+                    // Sequences like `ESC [ 3 ~`, or `ESC [ 1 ; 5 D` for Ctrl+Left
+                    let mut code = vec![digit];
+                    let end = loop {
+                        match read_byte()? {
+                            b @ (b'0'..=b'9' | b';') => code.push(b),
+                            b => break b,
+                        }
+                    };
+                    match (code.as_slice(), end) {
+                        (b"1" | b"7", b'~') => Key::Home,
+                        (b"4" | b"8", b'~') => Key::End,
+                        (b"3", b'~') => Key::Delete,
+                        (b"1;5" | b"1;3", b'D') => Key::WordLeft,
+                        (b"1;5" | b"1;3", b'C') => Key::WordRight,
+                        _ => Key::Unknown,
+                    }
+                }
+                _ => Key::Unknown,
+            },
+            _ => Key::Unknown,
+        },
+        b if b < 0x20 => Key::Unknown,
+        b => {
+            // Collect the rest of a multi-byte UTF-8 character
+            let len = match b {
+                0xf0..=0xf7 => 4,
+                0xe0..=0xef => 3,
+                0xc0..=0xdf => 2,
+                _ => 1,
+            };
+            let mut bytes = vec![b];
+            for _ in 1..len {
+                bytes.push(read_byte()?);
+            }
+            match std::str::from_utf8(&bytes)
+                .ok()
+                .and_then(|s| s.chars().next())
+            {
+                Some(c) => Key::Char(c),
+                None => Key::Unknown,
+            }
+        }
+    })
+}
+
+/// Asks for a username and password on the terminal and logs the user in.
+/// An empty username continues as a guest. Unknown users can register.
+pub fn tty_login(shell: &mut TtyShell) -> bool {
+    loop {
+        let username = shell.input("Username (leave empty to play as guest)");
+        let username = username.trim().to_string();
+        if username.is_empty() {
+            log::log_info("login", "Continuing as guest");
+            return true;
+        }
+
+        let exists = load_users().iter().any(|u| u.username == username);
+        if !exists && !shell.confirm(&format!("User '{username}' not found. Register?")) {
+            continue;
+        }
+
+        let Some(password) = shell.read_secret("Password: ") else {
+            return false;
+        };
+        let password = password.trim().to_string();
+        if password.is_empty() {
+            println!("Password cannot be empty.");
+            continue;
+        }
+
+        // Same limits and audit as the web server
+        let response = if exists {
+            login_with_password(&username, &password, None)
+        } else {
+            register_user(&username, &password, None)
+        };
+        if !response.status {
+            println!("{}", response.message);
+            continue;
+        }
+        println!("Welcome, {username}!");
+        return true;
+    }
+}
+
+/// Runs the terminal front-end for a sekai
+pub fn run_tty(sekai_dir: PathBuf) {
+    let mut shell = TtyShell::new_sekai(sekai_dir);
+    if !tty_login(&mut shell) {
+        log::log_info("Application", "Login aborted by user.");
+        return;
+    }
+    shell.run();
+}
@@ -0,0 +1,256 @@
+use crate::metainfo::info_reader::{InfoError, read_validate_info};
+use crate::utils::config::{load_config, save_config};
+use crate::utils::globals::{ALIASES, USER_ALIASES};
+use crate::utils::log;
+use crate::utils::shell_history::current_user;
+use crate::utils::variables::split_chain;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::Path;
+
+/// Aliases and shell functions of a user, saved under their name in the `aliases` of
+/// `DeemakConfig`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserAliases {
+    /// Aliases defined with `alias`. They override the sekai defaults.
+    pub defined: BTreeMap<String, String>,
+    /// Sekai defaults removed with `unalias`.
+    pub removed: BTreeSet<String>,
+    /// Functions defined with `function`, by name.
+    pub functions: BTreeMap<String, String>,
+}
+
+impl UserAliases {
+    fn load(user: &str) -> Self {
+        load_config().aliases.remove(user).unwrap_or_default()
+    }
+
+    fn save(&self, user: &str) {
+        let mut config = load_config();
+        if *self == Self::default() {
+            config.aliases.remove(user);
+        } else {
+            config.aliases.insert(user.to_string(), self.clone());
+        }
+        save_config(&config);
+    }
+
+    /// The aliases active for this user, given the sekai defaults.
+    pub fn apply(&self, defaults: &HashMap<String, String>) -> BTreeMap<String, String> {
+        let mut aliases: BTreeMap<String, String> = defaults
+            .iter()
+            .filter(|(name, _)| !self.removed.contains(*name))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        aliases.extend(self.defined.clone());
+        aliases
+    }
+}
+
+/// Lets `f` change the aliases of the current user, and saves them if they changed.
+fn update_user_aliases<T>(f: impl FnOnce(&mut UserAliases, &HashMap<String, String>) -> T) -> T {
+    let user = current_user();
+    let defaults = ALIASES.lock().unwrap_or_else(|e| e.into_inner());
+    let mut cache = USER_ALIASES.lock().unwrap_or_else(|e| e.into_inner());
+    let aliases = cache
+        .entry(user.clone())
+        .or_insert_with(|| UserAliases::load(&user));
+    let before = aliases.clone();
+    let result = f(aliases, &defaults);
+    if *aliases != before {
+        aliases.save(&user);
+    }
+    result
+}
+
+/// Loads the default aliases of a sekai, shipped in its HOME `info.json`. Each user's own
+/// aliases are read from the config on first use, and override them.
+pub fn load_aliases(root_dir: &Path) {
+    let mut aliases = ALIASES.lock().unwrap_or_else(|e| e.into_inner());
+    aliases.clear();
+    USER_ALIASES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clear();
+
+    match read_validate_info(&root_dir.join(".dir_info/info.json")) {
+        Ok(info) => {
+            for (name, value) in info.aliases {
+                if is_valid_name(&name) {
+                    aliases.insert(name, value);
+                } else {
+                    log::log_warning("alias", &format!("Ignoring invalid sekai alias '{name}'"));
+                }
+            }
+        }
+        Err(InfoError::NotFound(_)) => {}
+        Err(e) => log::log_warning("alias", &format!("Could not read sekai aliases: {e}")),
+    }
+
+    log::log_debug("alias", &format!("Loaded {} sekai aliases", aliases.len()));
+}
+
+/// Alias names may only contain letters, digits, `-`, `_` and `.`
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+/// Returns the aliases of the current user, sorted by name.
+pub fn get_aliases() -> BTreeMap<String, String> {
+    update_user_aliases(|aliases, defaults| aliases.apply(defaults))
+}
+
+/// Returns the value of a single alias of the current user.
+pub fn get_alias(name: &str) -> Option<String> {
+    get_aliases().remove(name)
+}
+
+/// Defines an alias for the current user and saves it.
+pub fn set_alias(name: &str, value: &str) {
+    update_user_aliases(|aliases, _| {
+        aliases.defined.insert(name.to_string(), value.to_string());
+    });
+}
+
+/// Removes an alias of the current user, for good if it is a sekai default. Returns false
+/// if no such alias exists.
+pub fn remove_alias(name: &str) -> bool {
+    update_user_aliases(|aliases, defaults| {
+        let active = aliases.apply(defaults).contains_key(name);
+        aliases.defined.remove(name);
+        if defaults.contains_key(name) {
+            aliases.removed.insert(name.to_string());
+        }
+        active
+    })
+}
+
+/// Removes all aliases of the current user, including the sekai defaults.
+pub fn clear_aliases() {
+    update_user_aliases(|aliases, defaults| {
+        aliases.defined.clear();
+        aliases.removed = defaults.keys().cloned().collect();
+    });
+}
+
+/// Returns the functions of the current user, sorted by name.
+pub fn get_functions() -> BTreeMap<String, String> {
+    update_user_aliases(|aliases, _| aliases.functions.clone())
+}
+
+/// Returns the body of a function of the current user.
+pub fn get_function(name: &str) -> Option<String> {
+    update_user_aliases(|aliases, _| aliases.functions.get(name).cloned())
+}
+
+/// Defines a function for the current user and saves it.
+pub fn set_function(name: &str, body: &str) {
+    update_user_aliases(|aliases, _| {
+        aliases.functions.insert(name.to_string(), body.to_string());
+    });
+}
+
+/// Removes a function of the current user. Returns false if no such function exists.
+pub fn remove_function(name: &str) -> bool {
+    update_user_aliases(|aliases, _| aliases.functions.remove(name).is_some())
+}
+
+/// Splits a command line into its chained commands, and expands aliases in the first word
+/// of each. An alias may hold a chain itself, like `alias up='go .. && ls'`. The expansion
+/// is repeated on the result, but an alias is never expanded twice within a command so
+/// that `alias ls='ls -a'` does not loop.
+pub fn expand_chain(line: &str) -> Vec<(String, Option<&'static str>)> {
+    let aliases = get_aliases();
+    expand_with(line, &|name| aliases.get(name).cloned())
+}
+
+fn expand_with(
+    line: &str,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Vec<(String, Option<&'static str>)> {
+    let mut commands = Vec::new();
+    for (command, operator) in split_chain(line) {
+        expand_command(command, operator, &HashSet::new(), lookup, &mut commands);
+    }
+    commands
+}
+
+fn expand_command(
+    command: String,
+    operator: Option<&'static str>,
+    seen: &HashSet<String>,
+    lookup: &dyn Fn(&str) -> Option<String>,
+    commands: &mut Vec<(String, Option<&'static str>)>,
+) {
+    let command = command.trim().to_string();
+    let (first, rest) =
+        command.split_at(command.find(char::is_whitespace).unwrap_or(command.len()));
+    let value = match lookup(first) {
+        Some(value) if !seen.contains(first) => value,
+        _ => return commands.push((command, operator)),
+    };
+    let mut seen = seen.clone();
+    seen.insert(first.to_string());
+    let mut chain = split_chain(&format!("{value}{rest}"));
+    if let Some(last) = chain.last_mut() {
+        last.1 = operator;
+    }
+    for (command, operator) in chain {
+        expand_command(command, operator, &seen, lookup, commands);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_expand_alias() {
+        let aliases: HashMap<&str, &str> = HashMap::from([
+            ("ll", "ls -l"),
+            ("l", "ll"),
+            ("ls", "ls -a"),
+            ("a", "b"),
+            ("b", "a"),
+            ("up", "go .. && l"),
+        ]);
+        let lookup = |name: &str| aliases.get(name).map(|v| v.to_string());
+        let expand = |line: &str| expand_with(line, &lookup);
+
+        assert_eq!(expand("ll room_1"), [("ls -a -l room_1".to_string(), None)]);
+        assert_eq!(expand("l"), [("ls -a -l".to_string(), None)]);
+        assert_eq!(expand("go ll"), [("go ll".to_string(), None)]);
+        assert_eq!(expand("a"), [("a".to_string(), None)]);
+        assert_eq!(
+            expand("up room_1 || 'up'"),
+            [
+                ("go ..".to_string(), Some("&&")),
+                ("ls -a -l room_1".to_string(), Some("||")),
+                ("'up'".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_user_aliases() {
+        let defaults = HashMap::from([
+            ("ll".to_string(), "ls -l".to_string()),
+            ("la".to_string(), "ls -a".to_string()),
+        ]);
+        let user = UserAliases {
+            defined: BTreeMap::from([("ll".to_string(), "ls -l -a".to_string())]),
+            removed: BTreeSet::from(["la".to_string()]),
+            ..Default::default()
+        };
+        assert_eq!(
+            user.apply(&defaults),
+            BTreeMap::from([("ll".to_string(), "ls -l -a".to_string())])
+        );
+        assert_eq!(UserAliases::default().apply(&defaults).len(), 2);
+    }
+}
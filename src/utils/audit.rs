@@ -0,0 +1,169 @@
+//! Audit log of logins and registrations in every shell, and of the commands of web requests
+//! and headless runs that change the sekai or guess flags, with who ran them, from where and
+//! how it went. Entries are appended as JSON lines to `~/.config/deemak/audit.log` and never
+//! rewritten.
+
+use crate::utils::config::get_config_path;
+use crate::utils::log;
+use chrono::{DateTime, SecondsFormat, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// RFC 3339, in UTC.
+    pub time: String,
+    pub user: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+    /// `login`, `register`, or the audited command, like `del` or `unlock`.
+    pub action: String,
+    /// What the action was about: the command and its paths, or the username of a login.
+    pub detail: String,
+    pub success: bool,
+    /// First line of what the server answered. Only errors are kept for commands, as their
+    /// output may hold flags.
+    pub message: String,
+}
+
+impl AuditEntry {
+    pub fn new(
+        user: &str,
+        ip: Option<IpAddr>,
+        action: &str,
+        detail: &str,
+        success: bool,
+        message: &str,
+    ) -> Self {
+        Self {
+            time: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            user: user.to_string(),
+            ip: ip.map(|ip| ip.to_string()),
+            action: action.to_string(),
+            detail: detail.to_string(),
+            success,
+            message: message.lines().next().unwrap_or_default().to_string(),
+        }
+    }
+
+    pub fn timestamp(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.time)
+            .ok()
+            .map(|time| time.with_timezone(&Utc))
+    }
+}
+
+/// Path of the audit log: `~/.config/deemak/audit.log`
+pub fn get_audit_path() -> PathBuf {
+    let mut path = get_config_path();
+    path.pop();
+    path.push("audit.log");
+    path
+}
+
+/// Keeps concurrent requests from interleaving their lines.
+static AUDIT_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// Appends an entry to the audit log at `path`.
+pub fn append_to(path: &Path, entry: &AuditEntry) -> Result<(), String> {
+    let _guard = AUDIT_LOCK.lock().unwrap();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let line = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| e.to_string())?;
+    writeln!(file, "{line}").map_err(|e| e.to_string())
+}
+
+/// Appends an entry to the audit log. Failures are logged, as the action already happened.
+pub fn record(entry: AuditEntry) {
+    if let Err(e) = append_to(&get_audit_path(), &entry) {
+        log::log_error("audit", &format!("Failed to write the audit log: {e}"));
+    }
+}
+
+/// Reads the audit log at `path`, oldest entry first. Lines that cannot be read are skipped.
+pub fn read_from(path: &Path) -> Vec<AuditEntry> {
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Name under which a command must be audited, if any: `del`, `copy -x`, `restore -f`, and
+/// the flag guesses of `unlock` and `solve`. `parts` is one command, once expanded.
+pub fn audited_command(parts: &[&str]) -> Option<&'static str> {
+    let has = |flags: &[&str]| parts.iter().any(|part| flags.contains(part));
+    match *parts.first()? {
+        "del" => Some("del"),
+        "copy" if has(&["-x", "--cut"]) => Some("copy"),
+        "restore" if has(&["-f", "--force"]) => Some("restore"),
+        "unlock" => Some("unlock"),
+        "solve" => Some("solve"),
+        _ => None,
+    }
+}
+
+/// Detail of an audited command: its name and the paths it was given. Flags are left out,
+/// and so is anything after the path of `unlock` and `solve`, which could be a flag.
+pub fn command_detail(parts: &[&str]) -> String {
+    let Some((name, args)) = parts.split_first() else {
+        return String::new();
+    };
+    let paths = args.iter().filter(|arg| !arg.starts_with('-'));
+    let paths: Vec<&str> = match *name {
+        "unlock" | "solve" => paths.take(1).copied().collect(),
+        _ => paths.copied().collect(),
+    };
+    std::iter::once(*name)
+        .chain(paths)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit_log() {
+        assert_eq!(audited_command(&["del", "note"]), Some("del"));
+        assert_eq!(audited_command(&["ls"]), None);
+        assert_eq!(audited_command(&["copy", "a", "b"]), None);
+        assert_eq!(audited_command(&["copy", "-x", "a", "b"]), Some("copy"));
+        assert_eq!(audited_command(&["restore"]), None);
+        assert_eq!(
+            command_detail(&["unlock", "-l", "room_1", "abc"]),
+            "unlock room_1"
+        );
+        assert_eq!(command_detail(&["copy", "-x", "a", "b"]), "copy a b");
+
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("audit.log");
+        let ip = "127.0.0.1".parse().ok();
+        let entry = AuditEntry::new("ana", ip, "unlock", "unlock room_1", false, "Wrong\nflag");
+        append_to(&path, &entry).unwrap();
+        append_to(
+            &path,
+            &AuditEntry::new("ana", None, "login", "ana", true, "Login successful"),
+        )
+        .unwrap();
+
+        let entries = read_from(&path);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0], entry);
+        assert_eq!(entries[0].message, "Wrong");
+        assert_eq!(entries[0].ip.as_deref(), Some("127.0.0.1"));
+        assert!(entries[1].timestamp().is_some());
+    }
+}
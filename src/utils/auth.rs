@@ -1,4 +1,7 @@
+use crate::utils::audit::{self, AuditEntry};
 use crate::utils::globals::{UserInfo, get_user_info, set_user_info};
+use crate::utils::rate_limit::{self, Action};
+use crate::utils::shell_history::GUEST_USER;
 use chrono::{Duration, Utc};
 use data_encoding::HEXUPPER;
 use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
@@ -11,6 +14,7 @@ use rocket::serde::{Deserialize, Serialize, json::Json};
 use rocket::{FromForm, post};
 use std::fs::File;
 use std::io::{Read, Write};
+use std::net::IpAddr;
 use std::num::NonZeroU32;
 use std::path::Path;
 
@@ -34,9 +38,9 @@ pub struct AuthInput {
 
 #[derive(Serialize)]
 pub struct AuthResponse {
-    status: bool,
-    message: String,
-    token: Option<String>,
+    pub status: bool,
+    pub message: String,
+    pub token: Option<String>,
 }
 #[derive(Debug, Serialize, Deserialize)]
 struct Claims {
@@ -102,35 +106,132 @@ pub fn verify_password(password: &String, salt_hex: &str, hash_hex: &str) -> boo
     .is_ok()
 }
 #[post("/register", data = "<input>")]
-pub fn register(input: Form<AuthInput>) -> Json<AuthResponse> {
+pub fn register(input: Form<AuthInput>, ip: Option<IpAddr>) -> Json<AuthResponse> {
+    Json(register_user(&input.username, &input.password, ip))
+}
+
+/// Registers a user, within the rate limits, and records it in the audit log. `ip` is
+/// `None` for the local shells. The web server, GUI and terminal all register through here.
+pub fn register_user(username: &str, password: &str, ip: Option<IpAddr>) -> AuthResponse {
+    let input = AuthInput {
+        username: username.to_string(),
+        password: password.to_string(),
+        token: None,
+    };
+    let keys = rate_limit::keys(username, ip);
+    let response = rate_limited(Action::Register, &keys, || try_register(&input));
+    audit_auth("register", username, ip, &response);
+    response
+}
+
+/// Logs a user in with their password, within the rate limits, and records it in the audit
+/// log. `ip` is `None` for the local shells. The web server, GUI and terminal all log in
+/// through here.
+pub fn login_with_password(username: &str, password: &str, ip: Option<IpAddr>) -> AuthResponse {
+    let input = AuthInput {
+        username: username.to_string(),
+        password: password.to_string(),
+        token: None,
+    };
+    let keys = rate_limit::keys(username, ip);
+    let response = rate_limited(Action::Login, &keys, || try_login(&input));
+    audit_auth("login", username, ip, &response);
+    response
+}
+
+fn audit_auth(action: &str, username: &str, ip: Option<IpAddr>, response: &AuthResponse) {
+    let entry = AuditEntry::new(
+        username,
+        ip,
+        action,
+        username,
+        response.status,
+        &response.message,
+    );
+    audit::record(entry);
+}
+
+/// Runs `attempt` unless one of `keys` is locked out of `action`, and records how it went.
+fn rate_limited(
+    action: Action,
+    keys: &[String],
+    attempt: impl FnOnce() -> AuthResponse,
+) -> AuthResponse {
+    if let Err(message) = rate_limit::check(action, keys) {
+        return AuthResponse {
+            status: false,
+            message,
+            token: None,
+        };
+    }
+    let mut response = attempt();
+    if let Some(lockout) = rate_limit::record(action, keys, response.status) {
+        response.message = format!("{}. {lockout}", response.message);
+    }
+    response
+}
+
+/// Longest username that can be registered.
+const MAX_USERNAME_LEN: usize = 32;
+
+/// Checks that a new username is allowed: letters, digits, `-`, `_` and `.`, and not the
+/// name anonymous players go by.
+pub fn validate_username(username: &str) -> Result<(), String> {
+    if username.is_empty() || username.len() > MAX_USERNAME_LEN {
+        return Err(format!(
+            "Username must be 1 to {MAX_USERNAME_LEN} characters long"
+        ));
+    }
+    if !username
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+    {
+        return Err("Username may only contain letters, digits, '-', '_' and '.'".to_string());
+    }
+    if username.eq_ignore_ascii_case(GUEST_USER) {
+        return Err(format!("Username '{username}' is reserved"));
+    }
+    Ok(())
+}
+
+fn try_register(input: &AuthInput) -> AuthResponse {
+    if let Err(message) = validate_username(&input.username) {
+        return AuthResponse {
+            status: false,
+            message,
+            token: None,
+        };
+    }
     let mut users = load_users();
 
     if users.iter().any(|u| u.username == input.username) {
-        return Json(AuthResponse {
+        return AuthResponse {
             status: false,
             message: "Username already exists".into(),
             token: None,
-        });
+        };
     }
 
     let (salt, hash) = match hash_password(&input.password) {
         Ok(res) => res,
         Err(_) => {
-            return Json(AuthResponse {
+            return AuthResponse {
                 status: false,
                 message: "Failed to hash password".into(),
                 token: None,
-            });
+            };
         }
     };
 
-    users.push(User {
+    let user = User {
         username: input.username.clone(),
         salt,
         password_hash: hash,
-    });
+    };
+    users.push(user.clone());
 
     save_users(&users);
+    sign_in(&user);
 
     let expiration = Utc::now()
         .checked_add_signed(Duration::hours(2))
@@ -149,15 +250,36 @@ pub fn register(input: Form<AuthInput>) -> Json<AuthResponse> {
     )
     .expect("Failed to create token");
 
-    Json(AuthResponse {
+    AuthResponse {
         status: true,
         message: "User registered successfully".into(),
         token: Some(token),
-    })
+    }
 }
 
 #[post("/login", data = "<input>")]
-pub fn login(input: Form<AuthInput>) -> Json<AuthResponse> {
+pub fn login(input: Form<AuthInput>, ip: Option<IpAddr>) -> Json<AuthResponse> {
+    // A valid token proves who the user is, so only password logins are limited
+    if input.token.is_none() {
+        return Json(login_with_password(&input.username, &input.password, ip));
+    }
+    let response = try_login(&input);
+    audit_auth("login", &input.username, ip, &response);
+    Json(response)
+}
+
+/// Sets the global user info to `user`, for the local shells.
+fn sign_in(user: &User) {
+    let mut user_info = UserInfo::new(
+        user.username.clone(),
+        user.salt.clone(),
+        user.password_hash.clone(),
+    );
+    user_info.authenticate();
+    set_user_info(user_info).ok(); // Set the global user info, ignoring error if already set
+}
+
+fn try_login(input: &AuthInput) -> AuthResponse {
     let users = load_users();
 
     if let Some(token) = &input.token {
@@ -167,18 +289,18 @@ pub fn login(input: Form<AuthInput>) -> Json<AuthResponse> {
             &Validation::default(),
         ) {
             Ok(token_data) => {
-                return Json(AuthResponse {
+                return AuthResponse {
                     status: true,
                     message: format!("Token valid. Welcome, {}!", token_data.claims.sub),
                     token: Some(token.clone()),
-                });
+                };
             }
             Err(err) => {
-                return Json(AuthResponse {
+                return AuthResponse {
                     status: false,
                     message: format!("Invalid token: {err}"),
                     token: None,
-                });
+                };
             }
         }
     }
@@ -203,42 +325,56 @@ pub fn login(input: Form<AuthInput>) -> Json<AuthResponse> {
                 )
                 .expect("Failed to create token");
 
-                // Create and set the global UserInfo
-                let mut user_info = UserInfo::new(
-                    user.username.clone(),
-                    user.salt.clone(),
-                    user.password_hash.clone(),
-                );
-                user_info.authenticate();
-                set_user_info(user_info).ok(); // Set the global user info, ignoring error if already set
+                sign_in(user);
 
-                return Json(AuthResponse {
+                return AuthResponse {
                     status: true,
                     message: "Login successful".into(),
                     token: Some(token),
-                });
+                };
             } else {
-                return Json(AuthResponse {
+                return AuthResponse {
                     status: false,
                     message: "Invalid password".into(),
                     token: None,
-                });
+                };
             }
         }
 
-        return Json(AuthResponse {
+        return AuthResponse {
             status: false,
             message: "User not found".into(),
             token: None,
-        });
+        };
     }
-    Json(AuthResponse {
+    AuthResponse {
         status: false,
         message: "Invalid request".into(),
         token: None,
+    }
+}
+
+/// Whether `username` may use the admin endpoints. Admins are listed in the `ADMIN_USERS`
+/// environment variable, separated by commas.
+pub fn is_admin(username: &str) -> bool {
+    std::env::var("ADMIN_USERS").is_ok_and(|admins| {
+        admins
+            .split(',')
+            .any(|admin| !admin.trim().is_empty() && admin.trim() == username)
     })
 }
 
+/// Returns the username a token was issued for, if the token is valid.
+pub fn username_from_token(token: &str) -> Option<String> {
+    decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(JWT_SECRET),
+        &Validation::default(),
+    )
+    .ok()
+    .map(|token_data| token_data.claims.sub)
+}
+
 // UserInfo integration functions
 /// Get current authenticated user info
 pub fn get_current_user() -> Option<&'static UserInfo> {
@@ -0,0 +1,79 @@
+//! Who the commands of a thread run for. The GUI and terminal shells run them for the
+//! logged-in user. The web server runs them for the user of each request, and `--script`
+//! and headless replays for the user of `--user`, with `as_caller`.
+//! Per-user state (history, aliases, variables, progress, inventory, events) follows it.
+
+use crate::utils::auth::get_current_username;
+use crate::utils::shell_history::GUEST_USER;
+use std::cell::RefCell;
+use std::net::IpAddr;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Caller {
+    pub user: String,
+    /// Address of the web client, if the command came from one.
+    pub ip: Option<IpAddr>,
+}
+
+thread_local! {
+    /// Caller of the request being handled on this thread.
+    static REQUEST_CALLER: RefCell<Option<Caller>> = const { RefCell::new(None) };
+}
+
+/// Restores the previous caller, even if the commands panic.
+struct Restore(Option<Caller>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        REQUEST_CALLER.with(|caller| *caller.borrow_mut() = previous);
+    }
+}
+
+/// Runs `f` on behalf of `caller`: `Caller::current` returns it meanwhile.
+pub fn as_caller<T>(caller: Caller, f: impl FnOnce() -> T) -> T {
+    let previous = REQUEST_CALLER.with(|current| current.borrow_mut().replace(caller));
+    let _restore = Restore(previous);
+    f()
+}
+
+impl Caller {
+    /// The caller set with `as_caller`, when this thread handles a web request or a headless
+    /// run.
+    pub fn request() -> Option<Self> {
+        REQUEST_CALLER.with(|caller| caller.borrow().clone())
+    }
+
+    /// The caller of this thread, or the logged-in user of the local shell.
+    pub fn current() -> Self {
+        if let Some(caller) = Self::request() {
+            return caller;
+        }
+        let user = match get_current_username() {
+            Some(name) if !name.is_empty() => name.to_string(),
+            _ => GUEST_USER.to_string(),
+        };
+        Caller { user, ip: None }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_as_caller() {
+        let local = Caller::current();
+        let web = Caller {
+            user: "ana".to_string(),
+            ip: "10.0.0.2".parse().ok(),
+        };
+        let seen = as_caller(web.clone(), || {
+            let inner = as_caller(local.clone(), Caller::current);
+            (Caller::current(), inner)
+        });
+        assert_eq!(seen, (web, local.clone()));
+        assert_eq!(Caller::current(), local);
+        assert_eq!(Caller::request(), None);
+    }
+}
@@ -1,11 +1,60 @@
+use crate::utils::alias::UserAliases;
+use crate::utils::log::{self, LogConfig};
+use crate::utils::rate_limit::RateLimitConfig;
+use crate::utils::scrollback::DEFAULT_SCROLLBACK_LINES;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs::{self, File};
 use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize)]
 pub struct DeemakConfig {
     pub font_index: usize,
+    /// Aliases and shell functions of each user, by user name.
+    #[serde(default)]
+    pub aliases: BTreeMap<String, UserAliases>,
+    /// Name of the GUI theme. Empty for the default one.
+    #[serde(default)]
+    pub theme: String,
+    /// Key chords of the shell actions the user changed, by action name.
+    #[serde(default)]
+    pub keybindings: BTreeMap<String, Vec<String>>,
+    /// Output lines the GUI terminal keeps before dropping the oldest.
+    #[serde(default = "default_scrollback_lines")]
+    pub scrollback_lines: usize,
+    /// Zoom of the GUI terminal, 1.0 for 100%.
+    #[serde(default = "default_zoom")]
+    pub zoom: f32,
+    /// Log file settings.
+    #[serde(default)]
+    pub log: LogConfig,
+    /// Limits of logins, registrations and flag guesses.
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+}
+
+fn default_scrollback_lines() -> usize {
+    DEFAULT_SCROLLBACK_LINES
+}
+
+fn default_zoom() -> f32 {
+    1.0
+}
+
+impl Default for DeemakConfig {
+    fn default() -> Self {
+        Self {
+            font_index: 0,
+            aliases: BTreeMap::new(),
+            theme: String::new(),
+            keybindings: BTreeMap::new(),
+            scrollback_lines: DEFAULT_SCROLLBACK_LINES,
+            zoom: default_zoom(),
+            log: LogConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+        }
+    }
 }
 
 pub fn get_config_path() -> PathBuf {
@@ -15,6 +64,18 @@ pub fn get_config_path() -> PathBuf {
     path
 }
 
+/// Directory of the data deemak writes as it runs, such as logs and player progress:
+/// `$XDG_DATA_HOME/deemak`, or `~/.local/share/deemak`.
+pub fn get_data_dir() -> PathBuf {
+    match std::env::var("XDG_DATA_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir).join("deemak"),
+        _ => {
+            let home = std::env::var("HOME").unwrap_or(".".to_string());
+            PathBuf::from(home).join(".local/share/deemak")
+        }
+    }
+}
+
 pub const FONT_OPTIONS: [(&str, &str); 11] = [
     (
         "JetBrains Mono Medium",
@@ -59,21 +120,46 @@ pub const FONT_OPTIONS: [(&str, &str); 11] = [
     ),
 ];
 
+/// Reads the config file, as it is on disk. `Ok(None)` if there is none.
+fn read_config(path: &Path) -> Result<Option<DeemakConfig>, String> {
+    let Ok(mut file) = File::open(path) else {
+        return Ok(None);
+    };
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)
+        .map_err(|e| e.to_string())?;
+    serde_json::from_str(&contents)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
 pub fn load_config() -> DeemakConfig {
     let path = get_config_path();
-    if let Ok(mut file) = File::open(&path) {
-        let mut contents = String::new();
-        if file.read_to_string(&mut contents).is_ok() {
-            if let Ok(cfg) = serde_json::from_str(&contents) {
-                return cfg;
-            }
+    match read_config(&path) {
+        Ok(cfg) => cfg.unwrap_or_default(),
+        Err(e) => {
+            log::log_error(
+                "config",
+                &format!("Could not read {}, using the defaults: {e}", path.display()),
+            );
+            DeemakConfig::default()
         }
     }
-    DeemakConfig::default()
 }
 
+/// Saves the config, unless the file on disk cannot be read: it is kept for the user to fix.
 pub fn save_config(cfg: &DeemakConfig) {
     let path = get_config_path();
+    if let Err(e) = read_config(&path) {
+        log::log_error(
+            "config",
+            &format!(
+                "Not saving over {}, which cannot be read: {e}",
+                path.display()
+            ),
+        );
+        return;
+    }
     if let Some(parent) = path.parent() {
         let _ = fs::create_dir_all(parent);
     }
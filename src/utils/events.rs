@@ -0,0 +1,232 @@
+//! Event bus of the shell. The commands publish what happened, for the user they ran for,
+//! and front-ends and subsystems subscribe to it. Every event is one JSON line:
+//!
+//! ```json
+//! {"time":1760000000000,"user":"guest","event":"file_read","path":"mess/friend"}
+//! ```
+//!
+//! Paths are relative to HOME. Subscribers are called in the order they subscribed, on the
+//! thread that published the event. What they have to tell the player, they `tell`, and the
+//! command the event happened in prints it after its own output.
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A command run by the player, after alias and variable expansion.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CommandEvent {
+    pub parts: Vec<String>,
+    /// Room the command ran in, relative to HOME.
+    pub dir: String,
+    /// Room the player is in after it, relative to HOME.
+    pub new_dir: String,
+    pub status: i32,
+}
+
+/// A line typed by the player, with the answers to its prompts and what it printed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LineEvent {
+    pub command: String,
+    /// Room the line was typed in, relative to HOME.
+    pub dir: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub answers: Vec<String>,
+    pub status: i32,
+    /// What the line printed, without styling.
+    pub output: String,
+    /// Room the player is in after it, relative to HOME.
+    pub new_dir: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ShellEvent {
+    CommandExecuted(CommandEvent),
+    LineEntered(LineEvent),
+    DirectoryChanged { from: String, to: String },
+    FileRead { path: String },
+    ObjectUnlocked { path: String },
+    LevelSolved { level: String },
+    Saved,
+    Restored,
+}
+
+/// An event, with when it happened and to whom.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EventRecord {
+    /// Milliseconds since the Unix epoch.
+    pub time: u64,
+    pub user: String,
+    /// HOME of the sekai it happened in, which the paths are relative to. Not part of the
+    /// JSON line.
+    #[serde(skip)]
+    pub root_dir: PathBuf,
+    #[serde(flatten)]
+    pub event: ShellEvent,
+}
+
+impl EventRecord {
+    pub fn new(user: &str, root_dir: &Path, event: ShellEvent) -> Self {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or_default();
+        Self {
+            time,
+            user: user.to_string(),
+            root_dir: root_dir.to_path_buf(),
+            event,
+        }
+    }
+
+    /// The record as one line of JSON, without the newline.
+    pub fn to_json_line(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    pub fn from_json_line(line: &str) -> Result<Self, String> {
+        serde_json::from_str(line).map_err(|e| e.to_string())
+    }
+}
+
+type Subscriber = Arc<dyn Fn(&EventRecord) + Send + Sync>;
+
+/// Subscribers, with the id they were given.
+static SUBSCRIBERS: Lazy<Mutex<Vec<(usize, Subscriber)>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// Id of the next subscriber. Ids are never given twice.
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// What a subscriber has to tell the player about an event.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Notice {
+    /// A line printed after the output of the command, like what a trigger prints.
+    Line(String),
+    /// News worth a notification too, like an objective met.
+    News(String),
+}
+
+thread_local! {
+    /// Notices of the events published on this thread, until the command takes them.
+    static NOTICES: RefCell<Vec<Notice>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Calls `f` with every event published from now on. Returns an id for `unsubscribe`.
+pub fn subscribe(f: impl Fn(&EventRecord) + Send + Sync + 'static) -> usize {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    SUBSCRIBERS.lock().unwrap().push((id, Arc::new(f)));
+    id
+}
+
+pub fn unsubscribe(id: usize) {
+    SUBSCRIBERS.lock().unwrap().retain(|(own, _)| *own != id);
+}
+
+/// Publishes an event of `user` in the sekai at `root_dir` to every subscriber.
+pub fn publish(user: &str, root_dir: &Path, event: ShellEvent) {
+    // Subscribers may publish or subscribe themselves, so they run without the lock
+    let subscribers: Vec<Subscriber> = SUBSCRIBERS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(_, subscriber)| subscriber.clone())
+        .collect();
+    if subscribers.is_empty() {
+        return;
+    }
+    let record = EventRecord::new(user, root_dir, event);
+    for subscriber in subscribers {
+        subscriber(&record);
+    }
+}
+
+/// Tells the player about the event being published, after the output of its command.
+pub fn tell(notice: Notice) {
+    NOTICES.with(|notices| notices.borrow_mut().push(notice));
+}
+
+/// Takes what subscribers told about the events published on this thread so far.
+pub fn take_notices() -> Vec<Notice> {
+    NOTICES.with(|notices| notices.take())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_bus() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        let id = subscribe(move |record| {
+            sink.lock().unwrap().push(record.clone());
+            if record.event
+                == (ShellEvent::FileRead {
+                    path: "event_bus_test".to_string(),
+                })
+            {
+                tell(Notice::Line("heard".to_string()));
+            }
+        });
+        publish(
+            "ana",
+            Path::new(""),
+            ShellEvent::FileRead {
+                path: "event_bus_test".to_string(),
+            },
+        );
+        assert_eq!(take_notices(), vec![Notice::Line("heard".to_string())]);
+        assert!(take_notices().is_empty());
+        unsubscribe(id);
+        // Ids are not given again once unsubscribed
+        let other = subscribe(|_| {});
+        unsubscribe(other);
+        assert!(other > id);
+        publish(
+            "ana",
+            Path::new(""),
+            ShellEvent::FileRead {
+                path: "event_bus_test".to_string(),
+            },
+        );
+
+        // Other tests may publish at the same time
+        let seen: Vec<EventRecord> = seen
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|record| {
+                record.event
+                    == ShellEvent::FileRead {
+                        path: "event_bus_test".to_string(),
+                    }
+            })
+            .cloned()
+            .collect();
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].user, "ana");
+
+        let line = seen[0].to_json_line();
+        assert!(line.contains(r#""event":"file_read","path":"event_bus_test""#));
+        assert_eq!(EventRecord::from_json_line(&line).unwrap(), seen[0]);
+
+        let command = EventRecord::new(
+            "ana",
+            Path::new(""),
+            ShellEvent::CommandExecuted(CommandEvent {
+                parts: vec!["ls".to_string(), "-a".to_string()],
+                dir: "mess".to_string(),
+                new_dir: "mess".to_string(),
+                status: 0,
+            }),
+        );
+        let line = command.to_json_line();
+        assert!(line.contains(r#""event":"command_executed","parts":["ls","-a"]"#));
+        assert_eq!(EventRecord::from_json_line(&line).unwrap(), command);
+    }
+}
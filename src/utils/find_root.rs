@@ -84,3 +84,16 @@ pub fn relative_deemak_path(path: &Path) -> PathBuf {
         })
         .unwrap_or_else(|_| path.to_path_buf())
 }
+
+/// Path relative to HOME with `/` separators, as used in per-user data files.
+/// Empty for HOME itself.
+pub fn home_relative(path: &Path, root_dir: &Path) -> String {
+    path.strip_prefix(root_dir)
+        .map(|rel| {
+            rel.components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/")
+        })
+        .unwrap_or_default()
+}
@@ -1,10 +1,28 @@
 use crate::SEKAI_DIR;
+use crate::utils::alias::UserAliases;
+use crate::utils::theme::Theme;
 use once_cell::sync::{Lazy, OnceCell};
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Mutex;
 
-/// Shell history to store the commands executed by the user.
-pub static SHELL_HISTORY: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(Vec::new()));
+/// Shell history cache, keyed by the history file of each user and sekai.
+pub static SHELL_HISTORY: Lazy<Mutex<HashMap<PathBuf, Vec<String>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Default aliases of the sekai, from its HOME `info.json`.
+pub static ALIASES: Lazy<Mutex<HashMap<String, String>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Aliases of each user, loaded on first use.
+pub static USER_ALIASES: Lazy<Mutex<HashMap<String, UserAliases>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Variables set with `set` during this session, by user.
+pub static SHELL_VARS: Lazy<Mutex<HashMap<String, HashMap<String, String>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Theme of the GUI, loaded from the config on first use.
+pub static THEME: Lazy<Mutex<Option<Theme>>> = Lazy::new(|| Mutex::new(None));
 
 /// Global user information instance
 pub static USER_INFO: OnceCell<UserInfo> = OnceCell::new();
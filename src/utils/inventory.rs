@@ -0,0 +1,250 @@
+//! Items carried by the player.
+//!
+//! Objects marked `"collectible": true` in their room's `info.json` can be taken and dropped.
+//! The sekai is shared by every user, so its files are never moved: each user's inventory
+//! records which items they took and where they dropped them, and `ls` shows the rooms
+//! accordingly.
+
+use crate::metainfo::info_reader::read_get_obj_info;
+use crate::utils::find_root::home_relative;
+use crate::utils::log;
+use crate::utils::shell_history::{get_user_data_path, user_data_lock};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Item {
+    pub name: String,
+    /// Where the item was found, relative to HOME, like `room_3/key`.
+    pub origin: String,
+    /// Room the item was dropped in, relative to HOME. `None` while it is carried.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub room: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Inventory {
+    #[serde(default)]
+    pub items: Vec<Item>,
+}
+
+/// Path of an object called `name` in `room`, relative to HOME.
+fn origin_of(room: &str, name: &str) -> String {
+    if room.is_empty() {
+        name.to_string()
+    } else {
+        format!("{room}/{name}")
+    }
+}
+
+/// Whether the object at `obj_path` is marked collectible in its room's `info.json`.
+pub fn is_collectible(obj_path: &Path) -> bool {
+    let (Some(dir), Some(name)) = (obj_path.parent(), obj_path.file_name()) else {
+        return false;
+    };
+    read_get_obj_info(&dir.join(".dir_info/info.json"), &name.to_string_lossy()).is_ok_and(|info| {
+        match info.properties.get("collectible") {
+            Some(Value::Bool(collectible)) => *collectible,
+            Some(Value::String(collectible)) => collectible == "true",
+            _ => false,
+        }
+    })
+}
+
+impl Inventory {
+    /// Reads the inventory of `user` for the current sekai. A missing file is an empty one.
+    pub fn load(user: &str) -> Self {
+        let path = get_user_data_path("inventory", user);
+        match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                log::log_warning(
+                    "inventory",
+                    &format!("Ignoring invalid {}: {e}", path.display()),
+                );
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self, user: &str) -> Result<(), String> {
+        let path = get_user_data_path("inventory", user);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(&path, json).map_err(|e| e.to_string())
+    }
+
+    /// Loads the inventory of `user`, lets `f` change it and saves it back if it succeeded.
+    /// Updates of a user run one at a time.
+    pub fn update<T>(
+        user: &str,
+        f: impl FnOnce(&mut Self) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let lock = user_data_lock("inventory", user);
+        let _guard = lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut inventory = Self::load(user);
+        let result = f(&mut inventory)?;
+        inventory.save(user).map_err(|e| {
+            log::log_error("inventory", &format!("Failed to save inventory: {e}"));
+            format!("Failed to save your inventory: {e}")
+        })?;
+        Ok(result)
+    }
+
+    /// Items the player is carrying.
+    pub fn carried(&self) -> impl Iterator<Item = &Item> {
+        self.items.iter().filter(|item| item.room.is_none())
+    }
+
+    pub fn carries(&self, name: &str) -> bool {
+        self.carried().any(|item| item.name == name)
+    }
+
+    /// Items dropped in `room`.
+    pub fn dropped_in(&self, room: &str) -> impl Iterator<Item = &Item> {
+        self.items
+            .iter()
+            .filter(move |item| item.room.as_deref() == Some(room))
+    }
+
+    /// Whether the object found at `origin` is no longer there.
+    /// An item dropped back where it was found leaves the inventory, so any entry means moved.
+    pub fn is_moved(&self, origin: &str) -> bool {
+        self.items.iter().any(|item| item.origin == origin)
+    }
+
+    /// Whether something called `name` lies in `room`. `exists` tells whether the sekai has
+    /// an object of that name there.
+    fn lies_in(&self, room: &str, name: &str, exists: bool) -> bool {
+        (exists && !self.is_moved(&origin_of(room, name)))
+            || self.dropped_in(room).any(|item| item.name == name)
+    }
+
+    /// Picks up `name` from `room`. `collectible` tells whether the sekai has a collectible
+    /// object of that name there. Names of carried items are unique, as `drop` uses them.
+    pub fn take(&mut self, room: &str, name: &str, collectible: bool) -> Result<(), String> {
+        if self.carries(name) {
+            return Err(format!("You already carry a {name}."));
+        }
+        if let Some(item) = self
+            .items
+            .iter_mut()
+            .find(|item| item.room.as_deref() == Some(room) && item.name == name)
+        {
+            item.room = None;
+            return Ok(());
+        }
+        let origin = origin_of(room, name);
+        if !collectible || self.is_moved(&origin) {
+            return Err(format!("There is no {name} here that you can take."));
+        }
+        self.items.push(Item {
+            name: name.to_string(),
+            origin,
+            room: None,
+        });
+        Ok(())
+    }
+
+    /// Hands the player an item, unless they already carry one of that name.
+    /// `from` tells where it came from, relative to HOME. Returns whether it was given.
+    pub fn receive(&mut self, name: &str, from: &str) -> bool {
+        if self.carries(name) {
+            return false;
+        }
+        self.items.push(Item {
+            name: name.to_string(),
+            // Not the path of an object, so nothing in the room disappears
+            origin: format!("{from}#{name}"),
+            room: None,
+        });
+        true
+    }
+
+    /// Drops the carried `name` in `room`. `exists` tells whether the sekai has an object of
+    /// that name there.
+    pub fn drop_item(&mut self, room: &str, name: &str, exists: bool) -> Result<(), String> {
+        let Some(index) = self
+            .items
+            .iter()
+            .position(|item| item.room.is_none() && item.name == name)
+        else {
+            return Err(format!("You do not carry a {name}."));
+        };
+        let origin = origin_of(room, name);
+        if self.items[index].origin == origin {
+            // Back where it was found
+            self.items.remove(index);
+            return Ok(());
+        }
+        if self.lies_in(room, name, exists) {
+            return Err(format!("There is already a {name} here."));
+        }
+        self.items[index].room = Some(room.to_string());
+        Ok(())
+    }
+
+    /// Where the file the player sees at `path` really is, as `ls` lists its room: where it
+    /// was found if they dropped it there, or `None` if they took it away.
+    pub fn locate(&self, path: &Path, root_dir: &Path) -> Option<PathBuf> {
+        let room = home_relative(path.parent()?, root_dir);
+        let name = path.file_name()?.to_string_lossy();
+        if let Some(item) = self.dropped_in(&room).find(|item| item.name == name) {
+            return Some(root_dir.join(&item.origin));
+        }
+        (!self.is_moved(&origin_of(&room, &name))).then(|| path.to_path_buf())
+    }
+
+    /// Adjusts the files listed in `room` to what this user has taken and dropped.
+    pub fn adjust_listing(&self, room: &str, files: &mut Vec<String>) {
+        files.retain(|name| !self.is_moved(&origin_of(room, name)));
+        files.extend(self.dropped_in(room).map(|item| item.name.clone()));
+        files.sort();
+        files.dedup();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_and_drop() {
+        let mut inventory = Inventory::default();
+        assert!(inventory.take("room_3", "note.txt", false).is_err());
+        inventory.take("room_3", "key", true).unwrap();
+        assert!(inventory.carries("key"));
+        assert!(inventory.take("room_3", "key", true).is_err());
+
+        let mut files = vec!["key".to_string(), "note.txt".to_string()];
+        inventory.adjust_listing("room_3", &mut files);
+        assert_eq!(files, ["note.txt"]);
+
+        assert!(inventory.drop_item("room_8", "key", true).is_err());
+        inventory.drop_item("room_8", "key", false).unwrap();
+        assert!(!inventory.carries("key"));
+        let mut files = Vec::new();
+        inventory.adjust_listing("room_8", &mut files);
+        assert_eq!(files, ["key"]);
+
+        let root = Path::new("/home");
+        assert_eq!(inventory.locate(&root.join("room_3/key"), root), None);
+        assert_eq!(
+            inventory.locate(&root.join("room_8/key"), root),
+            Some(root.join("room_3/key"))
+        );
+        assert_eq!(
+            inventory.locate(&root.join("room_3/note.txt"), root),
+            Some(root.join("room_3/note.txt"))
+        );
+
+        inventory.take("room_8", "key", false).unwrap();
+        inventory.drop_item("room_3", "key", true).unwrap();
+        assert!(inventory.items.is_empty());
+        assert!(inventory.drop_item("room_3", "key", true).is_err());
+    }
+}
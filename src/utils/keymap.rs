@@ -0,0 +1,534 @@
+use crate::keys::key_to_char;
+use crate::utils::config::{load_config, save_config};
+use crate::utils::log;
+use raylib::prelude::{KeyboardKey, RaylibHandle};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Something a key chord can do in the shell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Action {
+    Submit,
+    Backspace,
+    Delete,
+    Complete,
+    HistoryPrev,
+    HistoryNext,
+    HistorySearch,
+    CursorLeft,
+    CursorRight,
+    WordLeft,
+    WordRight,
+    LineStart,
+    LineEnd,
+    KillWord,
+    KillToStart,
+    KillToEnd,
+    Yank,
+    PageUp,
+    PageDown,
+    Find,
+    FindNext,
+    FindPrevious,
+    ZoomIn,
+    ZoomOut,
+    ZoomReset,
+    ClearScreen,
+    Interrupt,
+    Paste,
+}
+
+impl Action {
+    pub fn all() -> &'static [Self] {
+        &[
+            Self::Submit,
+            Self::Backspace,
+            Self::Delete,
+            Self::Complete,
+            Self::HistoryPrev,
+            Self::HistoryNext,
+            Self::HistorySearch,
+            Self::CursorLeft,
+            Self::CursorRight,
+            Self::WordLeft,
+            Self::WordRight,
+            Self::LineStart,
+            Self::LineEnd,
+            Self::KillWord,
+            Self::KillToStart,
+            Self::KillToEnd,
+            Self::Yank,
+            Self::PageUp,
+            Self::PageDown,
+            Self::Find,
+            Self::FindNext,
+            Self::FindPrevious,
+            Self::ZoomIn,
+            Self::ZoomOut,
+            Self::ZoomReset,
+            Self::ClearScreen,
+            Self::Interrupt,
+            Self::Paste,
+        ]
+    }
+
+    /// Name of the action in the config file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Submit => "submit",
+            Self::Backspace => "backspace",
+            Self::Delete => "delete",
+            Self::Complete => "complete",
+            Self::HistoryPrev => "history_prev",
+            Self::HistoryNext => "history_next",
+            Self::HistorySearch => "history_search",
+            Self::CursorLeft => "cursor_left",
+            Self::CursorRight => "cursor_right",
+            Self::WordLeft => "word_left",
+            Self::WordRight => "word_right",
+            Self::LineStart => "line_start",
+            Self::LineEnd => "line_end",
+            Self::KillWord => "kill_word",
+            Self::KillToStart => "kill_to_start",
+            Self::KillToEnd => "kill_to_end",
+            Self::Yank => "yank",
+            Self::PageUp => "page_up",
+            Self::PageDown => "page_down",
+            Self::Find => "find",
+            Self::FindNext => "find_next",
+            Self::FindPrevious => "find_previous",
+            Self::ZoomIn => "zoom_in",
+            Self::ZoomOut => "zoom_out",
+            Self::ZoomReset => "zoom_reset",
+            Self::ClearScreen => "clear_screen",
+            Self::Interrupt => "interrupt",
+            Self::Paste => "paste",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Submit => "Run the command",
+            Self::Backspace => "Delete the previous character",
+            Self::Delete => "Delete the next character",
+            Self::Complete => "Complete commands and paths",
+            Self::HistoryPrev => "Previous command in history",
+            Self::HistoryNext => "Next command in history",
+            Self::HistorySearch => "Search through history",
+            Self::CursorLeft => "Move the cursor left",
+            Self::CursorRight => "Move the cursor right",
+            Self::WordLeft => "Move to the start of the word",
+            Self::WordRight => "Move to the end of the word",
+            Self::LineStart => "Move to the start of the line",
+            Self::LineEnd => "Move to the end of the line",
+            Self::KillWord => "Cut the word before the cursor",
+            Self::KillToStart => "Cut to the start of the line",
+            Self::KillToEnd => "Cut to the end of the line",
+            Self::Yank => "Paste the last cut text",
+            Self::PageUp => "Scroll the output up a page",
+            Self::PageDown => "Scroll the output down a page",
+            Self::Find => "Search the output",
+            Self::FindNext => "Go to the match above",
+            Self::FindPrevious => "Go to the match below",
+            Self::ZoomIn => "Make the text bigger",
+            Self::ZoomOut => "Make the text smaller",
+            Self::ZoomReset => "Reset the text size",
+            Self::ClearScreen => "Clear the screen",
+            Self::Interrupt => "Copy selection, or start a new prompt",
+            Self::Paste => "Paste from the clipboard",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::all()
+            .iter()
+            .copied()
+            .find(|action| action.name() == name)
+    }
+}
+
+/// Keys that can be bound, with the names used in the config file.
+const KEY_NAMES: [(&str, KeyboardKey); 76] = [
+    ("A", KeyboardKey::KEY_A),
+    ("B", KeyboardKey::KEY_B),
+    ("C", KeyboardKey::KEY_C),
+    ("D", KeyboardKey::KEY_D),
+    ("E", KeyboardKey::KEY_E),
+    ("F", KeyboardKey::KEY_F),
+    ("G", KeyboardKey::KEY_G),
+    ("H", KeyboardKey::KEY_H),
+    ("I", KeyboardKey::KEY_I),
+    ("J", KeyboardKey::KEY_J),
+    ("K", KeyboardKey::KEY_K),
+    ("L", KeyboardKey::KEY_L),
+    ("M", KeyboardKey::KEY_M),
+    ("N", KeyboardKey::KEY_N),
+    ("O", KeyboardKey::KEY_O),
+    ("P", KeyboardKey::KEY_P),
+    ("Q", KeyboardKey::KEY_Q),
+    ("R", KeyboardKey::KEY_R),
+    ("S", KeyboardKey::KEY_S),
+    ("T", KeyboardKey::KEY_T),
+    ("U", KeyboardKey::KEY_U),
+    ("V", KeyboardKey::KEY_V),
+    ("W", KeyboardKey::KEY_W),
+    ("X", KeyboardKey::KEY_X),
+    ("Y", KeyboardKey::KEY_Y),
+    ("Z", KeyboardKey::KEY_Z),
+    ("0", KeyboardKey::KEY_ZERO),
+    ("1", KeyboardKey::KEY_ONE),
+    ("2", KeyboardKey::KEY_TWO),
+    ("3", KeyboardKey::KEY_THREE),
+    ("4", KeyboardKey::KEY_FOUR),
+    ("5", KeyboardKey::KEY_FIVE),
+    ("6", KeyboardKey::KEY_SIX),
+    ("7", KeyboardKey::KEY_SEVEN),
+    ("8", KeyboardKey::KEY_EIGHT),
+    ("9", KeyboardKey::KEY_NINE),
+    ("Enter", KeyboardKey::KEY_ENTER),
+    ("Tab", KeyboardKey::KEY_TAB),
+    ("Backspace", KeyboardKey::KEY_BACKSPACE),
+    ("Delete", KeyboardKey::KEY_DELETE),
+    ("Insert", KeyboardKey::KEY_INSERT),
+    ("Escape", KeyboardKey::KEY_ESCAPE),
+    ("Space", KeyboardKey::KEY_SPACE),
+    ("Up", KeyboardKey::KEY_UP),
+    ("Down", KeyboardKey::KEY_DOWN),
+    ("Left", KeyboardKey::KEY_LEFT),
+    ("Right", KeyboardKey::KEY_RIGHT),
+    ("Home", KeyboardKey::KEY_HOME),
+    ("End", KeyboardKey::KEY_END),
+    ("PageUp", KeyboardKey::KEY_PAGE_UP),
+    ("PageDown", KeyboardKey::KEY_PAGE_DOWN),
+    ("F1", KeyboardKey::KEY_F1),
+    ("F2", KeyboardKey::KEY_F2),
+    ("F3", KeyboardKey::KEY_F3),
+    ("F4", KeyboardKey::KEY_F4),
+    ("F5", KeyboardKey::KEY_F5),
+    ("F6", KeyboardKey::KEY_F6),
+    ("F7", KeyboardKey::KEY_F7),
+    ("F8", KeyboardKey::KEY_F8),
+    ("F9", KeyboardKey::KEY_F9),
+    ("F10", KeyboardKey::KEY_F10),
+    ("F11", KeyboardKey::KEY_F11),
+    ("F12", KeyboardKey::KEY_F12),
+    ("Minus", KeyboardKey::KEY_MINUS),
+    ("Equal", KeyboardKey::KEY_EQUAL),
+    ("Comma", KeyboardKey::KEY_COMMA),
+    ("Period", KeyboardKey::KEY_PERIOD),
+    ("Slash", KeyboardKey::KEY_SLASH),
+    ("Semicolon", KeyboardKey::KEY_SEMICOLON),
+    ("Apostrophe", KeyboardKey::KEY_APOSTROPHE),
+    ("LeftBracket", KeyboardKey::KEY_LEFT_BRACKET),
+    ("RightBracket", KeyboardKey::KEY_RIGHT_BRACKET),
+    ("Backslash", KeyboardKey::KEY_BACKSLASH),
+    ("Grave", KeyboardKey::KEY_GRAVE),
+    ("KpAdd", KeyboardKey::KEY_KP_ADD),
+    ("KpSubtract", KeyboardKey::KEY_KP_SUBTRACT),
+];
+
+fn key_name(key: KeyboardKey) -> Option<&'static str> {
+    KEY_NAMES
+        .iter()
+        .find(|(_, k)| *k == key)
+        .map(|(name, _)| *name)
+}
+
+/// A key pressed together with modifiers, written like `Ctrl+Shift+K`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyChord {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    pub key: KeyboardKey,
+}
+
+impl KeyChord {
+    pub fn new(key: KeyboardKey) -> Self {
+        Self {
+            ctrl: false,
+            shift: false,
+            alt: false,
+            key,
+        }
+    }
+
+    pub fn ctrl(key: KeyboardKey) -> Self {
+        Self {
+            ctrl: true,
+            ..Self::new(key)
+        }
+    }
+
+    pub fn alt(key: KeyboardKey) -> Self {
+        Self {
+            alt: true,
+            ..Self::new(key)
+        }
+    }
+
+    /// The chord pressed in this frame, if any.
+    pub fn pressed(rl: &mut RaylibHandle) -> Option<Self> {
+        let key = rl.get_key_pressed()?;
+        let down = |a, b| rl.is_key_down(a) || rl.is_key_down(b);
+        Some(Self {
+            ctrl: down(
+                KeyboardKey::KEY_LEFT_CONTROL,
+                KeyboardKey::KEY_RIGHT_CONTROL,
+            ),
+            shift: down(KeyboardKey::KEY_LEFT_SHIFT, KeyboardKey::KEY_RIGHT_SHIFT),
+            alt: down(KeyboardKey::KEY_LEFT_ALT, KeyboardKey::KEY_RIGHT_ALT),
+            key,
+        })
+    }
+
+    /// Whether the key can be bound. Modifiers alone and unnamed keys can't.
+    pub fn is_bindable(&self) -> bool {
+        key_name(self.key).is_some()
+    }
+
+    /// Whether the chord types a character, so binding it would break typing.
+    pub fn is_text(&self) -> bool {
+        !self.ctrl && !self.alt && key_to_char(self.key, self.shift).is_some()
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.alt {
+            write!(f, "Alt+")?;
+        }
+        if self.shift {
+            write!(f, "Shift+")?;
+        }
+        write!(f, "{}", key_name(self.key).unwrap_or("?"))
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let key = parts.pop().unwrap_or_default();
+        let key = KEY_NAMES
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, k)| *k)
+            .ok_or_else(|| format!("unknown key '{key}' in '{s}'"))?;
+
+        let mut chord = KeyChord::new(key);
+        for modifier in parts {
+            match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => chord.ctrl = true,
+                "shift" => chord.shift = true,
+                "alt" => chord.alt = true,
+                _ => return Err(format!("unknown modifier '{modifier}' in '{s}'")),
+            }
+        }
+        Ok(chord)
+    }
+}
+
+/// Key chords of the shell and the actions they run.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keymap {
+    bindings: Vec<(KeyChord, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        use KeyboardKey::*;
+        Self {
+            bindings: vec![
+                (KeyChord::new(KEY_ENTER), Action::Submit),
+                (KeyChord::new(KEY_BACKSPACE), Action::Backspace),
+                (KeyChord::new(KEY_DELETE), Action::Delete),
+                (KeyChord::new(KEY_TAB), Action::Complete),
+                (KeyChord::new(KEY_UP), Action::HistoryPrev),
+                (KeyChord::new(KEY_DOWN), Action::HistoryNext),
+                (KeyChord::ctrl(KEY_R), Action::HistorySearch),
+                (KeyChord::new(KEY_LEFT), Action::CursorLeft),
+                (KeyChord::new(KEY_RIGHT), Action::CursorRight),
+                (KeyChord::alt(KEY_B), Action::WordLeft),
+                (KeyChord::ctrl(KEY_LEFT), Action::WordLeft),
+                (KeyChord::alt(KEY_F), Action::WordRight),
+                (KeyChord::ctrl(KEY_RIGHT), Action::WordRight),
+                (KeyChord::new(KEY_HOME), Action::LineStart),
+                (KeyChord::ctrl(KEY_A), Action::LineStart),
+                (KeyChord::new(KEY_END), Action::LineEnd),
+                (KeyChord::ctrl(KEY_E), Action::LineEnd),
+                (KeyChord::ctrl(KEY_W), Action::KillWord),
+                (KeyChord::ctrl(KEY_U), Action::KillToStart),
+                (KeyChord::ctrl(KEY_K), Action::KillToEnd),
+                (KeyChord::ctrl(KEY_Y), Action::Yank),
+                (KeyChord::new(KEY_PAGE_UP), Action::PageUp),
+                (KeyChord::new(KEY_PAGE_DOWN), Action::PageDown),
+                (KeyChord::ctrl(KEY_F), Action::Find),
+                (KeyChord::new(KEY_F3), Action::FindNext),
+                (
+                    KeyChord {
+                        shift: true,
+                        ..KeyChord::new(KEY_F3)
+                    },
+                    Action::FindPrevious,
+                ),
+                (KeyChord::ctrl(KEY_EQUAL), Action::ZoomIn),
+                (KeyChord::ctrl(KEY_KP_ADD), Action::ZoomIn),
+                (KeyChord::ctrl(KEY_MINUS), Action::ZoomOut),
+                (KeyChord::ctrl(KEY_KP_SUBTRACT), Action::ZoomOut),
+                (KeyChord::ctrl(KEY_ZERO), Action::ZoomReset),
+                (KeyChord::ctrl(KEY_L), Action::ClearScreen),
+                (KeyChord::ctrl(KEY_C), Action::Interrupt),
+                (KeyChord::ctrl(KEY_V), Action::Paste),
+            ],
+        }
+    }
+}
+
+impl Keymap {
+    /// Default keymap with the user's bindings from the config on top.
+    /// An action listed in the config loses its default chords. Invalid entries are skipped.
+    pub fn from_config(overrides: &BTreeMap<String, Vec<String>>) -> Self {
+        let mut keymap = Self::default();
+        for (name, chords) in overrides {
+            let Some(action) = Action::from_name(name) else {
+                log::log_warning("keymap", &format!("Unknown action '{name}'"));
+                continue;
+            };
+            keymap.bindings.retain(|(_, a)| *a != action);
+            for chord in chords {
+                match chord.parse::<KeyChord>() {
+                    Ok(chord) if chord.is_text() => log::log_warning(
+                        "keymap",
+                        &format!("'{chord}' types text and can't be bound to {name}"),
+                    ),
+                    Ok(chord) => {
+                        keymap.bindings.retain(|(c, _)| *c != chord);
+                        keymap.bindings.push((chord, action));
+                    }
+                    Err(e) => log::log_warning("keymap", &e),
+                }
+            }
+        }
+        keymap
+    }
+
+    /// Keymap from the user's config.
+    pub fn load() -> Self {
+        Self::from_config(&load_config().keybindings)
+    }
+
+    /// Saves the bindings that differ from the defaults to the config.
+    pub fn save(&self) {
+        let defaults = Self::default();
+        let mut cfg = load_config();
+        cfg.keybindings = Action::all()
+            .iter()
+            .filter(|action| self.chords(**action) != defaults.chords(**action))
+            .map(|action| {
+                let chords = self.chords(*action).iter().map(|c| c.to_string()).collect();
+                (action.name().to_string(), chords)
+            })
+            .collect();
+        save_config(&cfg);
+    }
+
+    /// Action bound to a chord. Chords bound without Shift also match with it held.
+    pub fn action(&self, chord: &KeyChord) -> Option<Action> {
+        let find = |chord: &KeyChord| {
+            self.bindings
+                .iter()
+                .find(|(c, _)| c == chord)
+                .map(|(_, action)| *action)
+        };
+        find(chord).or_else(|| {
+            chord.shift.then(|| {
+                find(&KeyChord {
+                    shift: false,
+                    ..*chord
+                })
+            })?
+        })
+    }
+
+    pub fn chords(&self, action: Action) -> Vec<KeyChord> {
+        self.bindings
+            .iter()
+            .filter(|(_, a)| *a == action)
+            .map(|(chord, _)| *chord)
+            .collect()
+    }
+
+    /// Makes `chord` the only binding of `action`. Returns the action that had the chord before.
+    pub fn rebind(&mut self, action: Action, chord: KeyChord) -> Result<Option<Action>, String> {
+        if !chord.is_bindable() {
+            return Err("This key can't be bound".to_string());
+        }
+        if chord.is_text() {
+            return Err(format!("{chord} types text, add Ctrl or Alt"));
+        }
+        let previous = self.action(&chord).filter(|a| *a != action);
+        self.bindings.retain(|(c, a)| *a != action && *c != chord);
+        self.bindings.push((chord, action));
+        Ok(previous)
+    }
+
+    /// Restores the default chords of `action`, taking them from other actions if needed.
+    pub fn reset(&mut self, action: Action) {
+        let defaults = Self::default().chords(action);
+        self.bindings
+            .retain(|(c, a)| *a != action && !defaults.contains(c));
+        self.bindings
+            .extend(defaults.into_iter().map(|chord| (chord, action)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keymap() {
+        let chord: KeyChord = "ctrl+shift+k".parse().unwrap();
+        assert_eq!(chord.to_string(), "Ctrl+Shift+K");
+        assert!("Ctrl+Nope".parse::<KeyChord>().is_err());
+        assert!("Hyper+K".parse::<KeyChord>().is_err());
+
+        let keymap = Keymap::default();
+        assert_eq!(keymap.action(&chord), Some(Action::KillToEnd));
+        assert_eq!(keymap.action(&"Alt+K".parse().unwrap()), None);
+
+        let overrides = BTreeMap::from([
+            ("clear_screen".to_string(), vec!["F5".to_string()]),
+            ("paste".to_string(), vec!["X".to_string()]),
+            ("nope".to_string(), vec!["Ctrl+N".to_string()]),
+        ]);
+        let mut keymap = Keymap::from_config(&overrides);
+        assert_eq!(
+            keymap.chords(Action::ClearScreen),
+            vec!["F5".parse().unwrap()]
+        );
+        assert!(keymap.chords(Action::Paste).is_empty());
+
+        let ctrl_r = "Ctrl+R".parse().unwrap();
+        assert_eq!(
+            keymap.rebind(Action::ClearScreen, ctrl_r),
+            Ok(Some(Action::HistorySearch))
+        );
+        assert!(keymap.chords(Action::HistorySearch).is_empty());
+        assert!(
+            keymap
+                .rebind(Action::Paste, KeyChord::new(KeyboardKey::KEY_A))
+                .is_err()
+        );
+
+        keymap.reset(Action::HistorySearch);
+        assert_eq!(keymap.action(&ctrl_r), Some(Action::HistorySearch));
+        assert!(keymap.chords(Action::ClearScreen).is_empty());
+    }
+}
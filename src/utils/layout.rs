@@ -0,0 +1,124 @@
+//! Geometry of the GUI terminal: how many characters fit, where the sidebar starts and how
+//! big the text is. It is recomputed when the window is resized, moves to another monitor or
+//! the user zooms.
+
+use crate::utils::config::{load_config, save_config};
+
+pub const MIN_ZOOM: f32 = 0.5;
+pub const MAX_ZOOM: f32 = 3.0;
+const ZOOM_STEP: f32 = 0.1;
+
+/// Font size at 100% zoom for a monitor of the given width.
+pub fn font_size_for_monitor(monitor_width: i32) -> f32 {
+    (monitor_width as f32 / 73.5).max(8.0)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Layout {
+    pub width: i32,
+    pub height: i32,
+    /// Font size at 100% zoom.
+    pub base_font_size: f32,
+    pub zoom: f32,
+    /// Width of a character divided by the font size. The fonts are monospaced.
+    pub glyph_ratio: f32,
+    /// Share of the window used by the terminal. The sidebar gets the rest.
+    pub split_ratio: f32,
+}
+
+impl Layout {
+    pub fn new(width: i32, height: i32, base_font_size: f32, zoom: f32, glyph_ratio: f32) -> Self {
+        Self {
+            width,
+            height,
+            base_font_size,
+            zoom: clamp_zoom(zoom),
+            glyph_ratio,
+            split_ratio: 2.0 / 3.0,
+        }
+    }
+
+    pub fn font_size(&self) -> f32 {
+        self.base_font_size * self.zoom
+    }
+
+    pub fn char_width(&self) -> f32 {
+        self.font_size() * self.glyph_ratio
+    }
+
+    /// X coordinate of the divider between the terminal and the sidebar.
+    pub fn split_x(&self) -> f32 {
+        self.width as f32 * self.split_ratio
+    }
+
+    /// Characters on a terminal line, keeping a margin left of the divider.
+    pub fn columns(&self) -> usize {
+        ((self.width as f32 * (self.split_ratio - 0.12)) / self.char_width())
+            .floor()
+            .max(1.0) as usize
+    }
+
+    /// Lines of text that fit in the window.
+    pub fn rows(&self) -> i32 {
+        (self.height as f32 / self.font_size()) as i32
+    }
+
+    /// Updates the window size. Returns whether it changed.
+    pub fn resize(&mut self, width: i32, height: i32) -> bool {
+        let changed = (width, height) != (self.width, self.height);
+        self.width = width;
+        self.height = height;
+        changed
+    }
+
+    pub fn zoom_in(&mut self) {
+        self.zoom = clamp_zoom(self.zoom + ZOOM_STEP);
+    }
+
+    pub fn zoom_out(&mut self) {
+        self.zoom = clamp_zoom(self.zoom - ZOOM_STEP);
+    }
+
+    pub fn reset_zoom(&mut self) {
+        self.zoom = 1.0;
+    }
+}
+
+/// Keeps the zoom in range, rounded to whole steps so repeated zooming does not drift.
+fn clamp_zoom(zoom: f32) -> f32 {
+    let zoom = if zoom.is_finite() { zoom } else { 1.0 };
+    ((zoom / ZOOM_STEP).round() * ZOOM_STEP).clamp(MIN_ZOOM, MAX_ZOOM)
+}
+
+/// Saves the zoom of the terminal to the config.
+pub fn save_zoom(zoom: f32) {
+    let mut cfg = load_config();
+    cfg.zoom = zoom;
+    save_config(&cfg);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout() {
+        let mut layout = Layout::new(1200, 800, 20.0, 1.0, 0.6);
+        assert_eq!(layout.columns(), 54);
+        assert_eq!(layout.rows(), 40);
+
+        assert!(layout.resize(600, 800));
+        assert!(!layout.resize(600, 800));
+        assert_eq!(layout.columns(), 27);
+
+        layout.zoom_in();
+        layout.zoom_in();
+        assert!((layout.font_size() - 24.0).abs() < 1e-3);
+        assert_eq!(layout.rows(), 33);
+        for _ in 0..100 {
+            layout.zoom_out();
+        }
+        assert_eq!(layout.zoom, MIN_ZOOM);
+        assert_eq!(Layout::new(1, 1, 20.0, 99.0, 0.6).zoom, MAX_ZOOM);
+    }
+}
@@ -0,0 +1,212 @@
+//! Readline-style editing of the input line, shared by the GUI and TTY shells.
+//!
+//! The cursor is a byte offset into the line and always sits on a character boundary,
+//! so non-ASCII input is safe to edit.
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LineEditor {
+    line: String,
+    cursor: usize,
+    /// Text removed by the last kill, inserted back by `yank`.
+    kill_buffer: String,
+}
+
+/// Characters that make up a word for Alt+B and Alt+F.
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+impl LineEditor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+
+    /// Cursor position as a byte offset into the line.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// The part of the line left of the cursor.
+    pub fn before_cursor(&self) -> &str {
+        &self.line[..self.cursor]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.line.is_empty()
+    }
+
+    /// Replaces the line and moves the cursor to its end.
+    pub fn set_line(&mut self, line: impl Into<String>) {
+        self.line = line.into();
+        self.cursor = self.line.len();
+    }
+
+    /// Takes the line out of the editor, leaving it empty.
+    pub fn take(&mut self) -> String {
+        self.cursor = 0;
+        std::mem::take(&mut self.line)
+    }
+
+    pub fn clear(&mut self) {
+        self.line.clear();
+        self.cursor = 0;
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.line.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    pub fn insert_str(&mut self, text: &str) {
+        self.line.insert_str(self.cursor, text);
+        self.cursor += text.len();
+    }
+
+    fn prev_boundary(&self) -> Option<usize> {
+        self.line[..self.cursor]
+            .chars()
+            .next_back()
+            .map(|c| self.cursor - c.len_utf8())
+    }
+
+    fn next_boundary(&self) -> Option<usize> {
+        self.line[self.cursor..]
+            .chars()
+            .next()
+            .map(|c| self.cursor + c.len_utf8())
+    }
+
+    pub fn backspace(&mut self) {
+        if let Some(start) = self.prev_boundary() {
+            self.line.drain(start..self.cursor);
+            self.cursor = start;
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if let Some(end) = self.next_boundary() {
+            self.line.drain(self.cursor..end);
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        if let Some(start) = self.prev_boundary() {
+            self.cursor = start;
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        if let Some(end) = self.next_boundary() {
+            self.cursor = end;
+        }
+    }
+
+    pub fn move_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.cursor = self.line.len();
+    }
+
+    /// Start of the word left of the cursor.
+    fn word_start(&self) -> usize {
+        let before = &self.line[..self.cursor];
+        let end = before.trim_end_matches(|c| !is_word_char(c)).len();
+        before[..end].trim_end_matches(is_word_char).len()
+    }
+
+    /// End of the word right of the cursor.
+    fn word_end(&self) -> usize {
+        let after = &self.line[self.cursor..];
+        let gap = after.len() - after.trim_start_matches(|c| !is_word_char(c)).len();
+        let word = after[gap..].len() - after[gap..].trim_start_matches(is_word_char).len();
+        self.cursor + gap + word
+    }
+
+    pub fn word_left(&mut self) {
+        self.cursor = self.word_start();
+    }
+
+    pub fn word_right(&mut self) {
+        self.cursor = self.word_end();
+    }
+
+    /// Removes `start..end` and keeps it for `yank`.
+    fn kill(&mut self, start: usize, end: usize) {
+        if start < end {
+            self.kill_buffer = self.line.drain(start..end).collect();
+            self.cursor = start;
+        }
+    }
+
+    /// Kills from the cursor to the end of the line.
+    pub fn kill_to_end(&mut self) {
+        self.kill(self.cursor, self.line.len());
+    }
+
+    /// Kills from the start of the line to the cursor.
+    pub fn kill_to_start(&mut self) {
+        self.kill(0, self.cursor);
+    }
+
+    /// Kills the whitespace-separated word left of the cursor, like Ctrl+W in a shell.
+    pub fn kill_word(&mut self) {
+        let before = self.line[..self.cursor].trim_end();
+        let start = before.trim_end_matches(|c: char| !c.is_whitespace()).len();
+        self.kill(start, self.cursor);
+    }
+
+    /// Inserts the last killed text at the cursor.
+    pub fn yank(&mut self) {
+        let text = self.kill_buffer.clone();
+        self.insert_str(&text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_editing() {
+        let mut editor = LineEditor::new();
+        editor.set_line("go rüm_1/nöte");
+        editor.word_left();
+        assert_eq!(editor.before_cursor(), "go rüm_1/");
+        editor.word_left();
+        assert_eq!(editor.before_cursor(), "go ");
+        editor.word_right();
+        assert_eq!(editor.before_cursor(), "go rüm_1");
+        editor.move_left();
+        editor.move_left();
+        editor.move_left();
+        assert_eq!(editor.before_cursor(), "go rü");
+        editor.backspace();
+        editor.insert('o');
+        editor.delete();
+        assert_eq!(editor.line(), "go ro_1/nöte");
+
+        editor.move_end();
+        editor.kill_word();
+        assert_eq!(editor.line(), "go ");
+        editor.yank();
+        editor.yank();
+        assert_eq!(editor.line(), "go ro_1/nötero_1/nöte");
+
+        editor.set_line("read note.txt");
+        editor.move_home();
+        editor.word_right();
+        editor.kill_to_end();
+        assert_eq!(editor.line(), "read");
+        editor.kill_to_start();
+        assert!(editor.is_empty());
+        editor.yank();
+        assert_eq!(editor.take(), "read");
+        assert_eq!(editor.cursor(), 0);
+    }
+}
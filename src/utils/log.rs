@@ -1,9 +1,203 @@
+//! Logging. Messages are printed in color when `--debug` is set, and written to a rotating
+//! log file once `init_file_logging` was called, filtered by level for each feature. The
+//! file is set up in the `log` section of `config.json`:
+//!
+//! ```json
+//! "log": { "level": "info", "features": { "unlock": "debug" }, "json": false }
+//! ```
+
 use crate::DEBUG_MODE;
+use crate::utils::config::get_data_dir;
+use chrono::{SecondsFormat, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
 pub fn debug_mode() -> bool {
     *DEBUG_MODE.get().unwrap_or(&false)
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Debug,
+    Info,
+    Warning,
+    Error,
+}
+
+impl Level {
+    pub fn label(self) -> &'static str {
+        match self {
+            Level::Debug => "DEBUG",
+            Level::Info => "INFO",
+            Level::Warning => "WARNING",
+            Level::Error => "ERROR",
+        }
+    }
+}
+
+/// The `log` section of the config.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    /// Lowest level written to the log file.
+    pub level: Level,
+    /// Levels of some features, instead of `level`.
+    pub features: BTreeMap<String, Level>,
+    /// Writes JSON lines instead of text.
+    pub json: bool,
+    /// Size in bytes from which the log file is rotated.
+    pub max_size: u64,
+    /// Rotated files kept, as `deemak.log.1`, `deemak.log.2`, ...
+    pub max_files: usize,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: Level::Info,
+            features: BTreeMap::new(),
+            json: false,
+            max_size: 1024 * 1024,
+            max_files: 3,
+        }
+    }
+}
+
+/// Path of the log file: `~/.local/share/deemak/logs/deemak.log`
+pub fn get_log_path() -> PathBuf {
+    get_data_dir().join("logs").join("deemak.log")
+}
+
+#[derive(Serialize)]
+struct LogLine<'a> {
+    time: String,
+    level: Level,
+    feature: &'a str,
+    message: &'a str,
+}
+
+/// Writes log messages to a file, and rotates it when it grows too big.
+pub struct FileLogger {
+    config: LogConfig,
+    path: PathBuf,
+    file: Option<File>,
+    size: u64,
+}
+
+impl FileLogger {
+    pub fn new(config: LogConfig, path: PathBuf) -> Self {
+        Self {
+            config,
+            path,
+            file: None,
+            size: 0,
+        }
+    }
+
+    pub fn enabled(&self, level: Level, feature: &str) -> bool {
+        level
+            >= *self
+                .config
+                .features
+                .get(feature)
+                .unwrap_or(&self.config.level)
+    }
+
+    /// Path of the `n`-th rotated file.
+    fn rotated(path: &Path, n: usize) -> PathBuf {
+        let mut name = path.as_os_str().to_owned();
+        name.push(format!(".{n}"));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file = None;
+        if self.config.max_files == 0 {
+            return fs::remove_file(&self.path);
+        }
+        for n in (1..self.config.max_files).rev() {
+            let from = Self::rotated(&self.path, n);
+            if from.exists() {
+                fs::rename(&from, Self::rotated(&self.path, n + 1))?;
+            }
+        }
+        fs::rename(&self.path, Self::rotated(&self.path, 1))
+    }
+
+    fn open(&mut self) -> std::io::Result<&mut File> {
+        if self.file.is_none() {
+            if let Some(parent) = self.path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+            self.size = file.metadata()?.len();
+            self.file = Some(file);
+        }
+        Ok(self.file.as_mut().unwrap())
+    }
+
+    pub fn write(&mut self, level: Level, feature: &str, message: &str) -> std::io::Result<()> {
+        if !self.enabled(level, feature) {
+            return Ok(());
+        }
+        let line = LogLine {
+            time: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            level,
+            feature,
+            message,
+        };
+        let line = if self.config.json {
+            serde_json::to_string(&line).unwrap_or_default()
+        } else {
+            format!("{} [{}] {feature} :: {message}", line.time, level.label())
+        };
+
+        self.open()?;
+        if self.size > 0 && self.size + line.len() as u64 + 1 > self.config.max_size {
+            self.rotate()?;
+        }
+        let file = self.open()?;
+        writeln!(file, "{line}")?;
+        self.size += line.len() as u64 + 1;
+        Ok(())
+    }
+}
+
+/// Logger of the log file, once set up.
+static FILE_LOGGER: Lazy<Mutex<Option<FileLogger>>> = Lazy::new(|| Mutex::new(None));
+
+/// Writes every message logged from now on to `path` too.
+pub fn init_file_logging(config: LogConfig, path: PathBuf) {
+    *FILE_LOGGER.lock().unwrap() = Some(FileLogger::new(config, path));
+}
+
+/// Prints a message in debug mode, and writes it to the log file.
+fn log(level: Level, feature: &str, message: &str) {
+    if debug_mode() {
+        match level {
+            Level::Debug => println!("\x1b[34m[DEBUG] \x1b[0m {feature} :: {message}"),
+            Level::Info => println!("\x1b[32m[INFO]\x1b[0m {feature} :: {message}"),
+            Level::Warning => eprintln!("\x1b[33m[WARNING] \x1b[0m {feature} :: {message}"),
+            Level::Error => eprintln!("\x1b[31m[ERROR] \x1b[0m {feature} :: {message}"),
+        }
+    }
+    if let Some(logger) = FILE_LOGGER.lock().unwrap().as_mut()
+        && let Err(e) = logger.write(level, feature, message)
+        && debug_mode()
+    {
+        eprintln!("Failed to write to the log file: {e}");
+    }
+}
+
 /// Logger for debugging elements.
 /// Args:
 ///     `feature` - the feature/command/module name
@@ -12,9 +206,7 @@ pub fn debug_mode() -> bool {
 ///     log_debug("go", "Parsing arguments: ... ");
 ///     log_debug("info_reader", "Reading info from file: ...");
 pub fn log_debug(feature: &str, message: &str) {
-    if debug_mode() {
-        println!("\x1b[34m[DEBUG] \x1b[0m {feature} :: {message}");
-    }
+    log(Level::Debug, feature, message);
 }
 
 /// Logger for general info
@@ -25,9 +217,7 @@ pub fn log_debug(feature: &str, message: &str) {
 ///     log_info("go", "You have entered the directory: ...");
 ///     log_info("info_reader", "Successfully read info from file: ...");
 pub fn log_info(feature: &str, message: &str) {
-    if debug_mode() {
-        println!("\x1b[32m[INFO]\x1b[0m {feature} :: {message}");
-    }
+    log(Level::Info, feature, message);
 }
 
 /// Logger for warnings
@@ -38,9 +228,7 @@ pub fn log_info(feature: &str, message: &str) {
 ///     log_warning("go", "Attempted to go to a file instead of a directory: ...");
 ///     log_warning("info_reader", "The info.json contains incorrect fields: ...");
 pub fn log_warning(feature: &str, message: &str) {
-    if debug_mode() {
-        eprintln!("\x1b[33m[WARNING] \x1b[0m {feature} :: {message}");
-    }
+    log(Level::Warning, feature, message);
 }
 
 /// Logger for errors
@@ -51,9 +239,7 @@ pub fn log_warning(feature: &str, message: &str) {
 ///     log_error("go", "Failed to change directory: ...");
 ///     log_error("info_reader", "Failed to parse: ...");
 pub fn log_error(feature: &str, message: &str) {
-    if debug_mode() {
-        eprintln!("\x1b[31m[ERROR] \x1b[0m {feature} :: {message}");
-    }
+    log(Level::Error, feature, message);
 }
 
 /// Common Result Logger for operations that return Result<(), E> where E: Display
@@ -65,3 +251,39 @@ pub fn log_result<E: std::fmt::Display>(feature: &str, result: Result<(), E>, me
         Err(e) => log_warning(feature, &format!("Failed: {message} - {e}")),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_logger() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("deemak.log");
+        let config = LogConfig {
+            level: Level::Warning,
+            features: BTreeMap::from([("unlock".to_string(), Level::Debug)]),
+            json: true,
+            max_size: 200,
+            max_files: 2,
+        };
+        let mut logger = FileLogger::new(config, path.clone());
+        logger.write(Level::Info, "go", "filtered out").unwrap();
+        logger.write(Level::Debug, "unlock", "kept").unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().count(), 1);
+        let line: serde_json::Value = serde_json::from_str(contents.trim()).unwrap();
+        assert_eq!(line["level"], "debug");
+        assert_eq!(line["feature"], "unlock");
+
+        for _ in 0..10 {
+            logger
+                .write(Level::Error, "save", "a long enough message")
+                .unwrap();
+        }
+        assert!(FileLogger::rotated(&path, 1).exists());
+        assert!(FileLogger::rotated(&path, 2).exists());
+        assert!(!FileLogger::rotated(&path, 3).exists());
+        assert!(fs::metadata(&path).unwrap().len() <= 200);
+    }
+}
@@ -10,12 +10,44 @@ pub mod globals;
 
 pub mod shell_history;
 
+pub mod caller;
+
+pub mod alias;
+
+pub mod variables;
+
 pub mod wrapit;
 
+pub mod style;
+
+pub mod theme;
+
+pub mod keymap;
+
+pub mod line_editor;
+
+pub mod scrollback;
+
+pub mod layout;
+
+pub mod room_panel;
+
+pub mod inventory;
+
+pub mod progress;
+
+pub mod events;
+
+pub mod session;
+
 pub mod prompt;
 
 pub mod auth;
 
+pub mod audit;
+
+pub mod rate_limit;
+
 pub mod test_utils;
 
 pub mod config;
@@ -0,0 +1,132 @@
+//! What a player has achieved in a sekai: levels solved, locks opened, hidden objects
+//! revealed, triggers fired, flags set by triggers, conversations with NPCs and objectives
+//! met. Kept per user, as the sekai itself is shared.
+
+use crate::utils::log;
+use crate::utils::shell_history::{get_user_data_path, user_data_lock};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Progress {
+    /// Flags obtained with `solve`, by level path relative to HOME.
+    pub solved: BTreeMap<String, String>,
+    /// Levels and chests opened with `unlock`, relative to HOME.
+    pub unlocked: BTreeSet<String>,
+    /// Named flags set by sekai triggers.
+    pub flags: BTreeSet<String>,
+    /// Hidden objects revealed to the player, relative to HOME.
+    pub revealed: BTreeSet<String>,
+    /// Triggers that have fired, as `<object>#<event>`.
+    pub fired: BTreeSet<String>,
+    /// Node each ongoing conversation waits at, by NPC path relative to HOME.
+    pub conversations: BTreeMap<String, String>,
+    /// Choices made in conversations, as `<npc>#<node>` to the text of the choice.
+    pub decisions: BTreeMap<String, String>,
+    /// Ids of the sekai objectives met.
+    pub objectives: BTreeSet<String>,
+}
+
+impl Progress {
+    /// Reads the progress of `user` in the current sekai. A missing file is an empty one.
+    pub fn load(user: &str) -> Self {
+        let path = get_user_data_path("progress", user);
+        match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                log::log_warning(
+                    "progress",
+                    &format!("Ignoring invalid {}: {e}", path.display()),
+                );
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self, user: &str) -> Result<(), String> {
+        let path = get_user_data_path("progress", user);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(&path, json).map_err(|e| e.to_string())
+    }
+
+    /// Loads the progress of `user`, lets `f` change it and saves it back if it changed.
+    /// Updates of a user run one at a time.
+    pub fn update<T>(user: &str, f: impl FnOnce(&mut Self) -> T) -> T {
+        let lock = user_data_lock("progress", user);
+        let _guard = lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut progress = Self::load(user);
+        let before = progress.clone();
+        let result = f(&mut progress);
+        if progress != before
+            && let Err(e) = progress.save(user)
+        {
+            log::log_error("progress", &format!("Failed to save progress: {e}"));
+        }
+        result
+    }
+}
+
+/// Files of a player kept apart from the sekai, which `save` and `restore` cover too.
+const PLAYER_DATA: [&str; 2] = ["progress", "inventory"];
+
+/// Runs `f` on every player file of `user`, holding its lock.
+fn for_player_data(user: &str, f: impl Fn(&str) -> io::Result<()>) -> Result<(), String> {
+    for kind in PLAYER_DATA {
+        let lock = user_data_lock(kind, user);
+        let _guard = lock.lock().unwrap_or_else(|e| e.into_inner());
+        f(kind).map_err(|e| format!("Failed to update the {kind} of {user}: {e}"))?;
+    }
+    Ok(())
+}
+
+/// Copies `source` over `target`. A missing `source` is an empty file: `target` is removed.
+fn copy_or_remove(source: &Path, target: &Path) -> io::Result<()> {
+    if source.is_file() {
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(source, target).map(|_| ())
+    } else {
+        remove(target)
+    }
+}
+
+fn remove(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Saves the progress and inventory of `user`, for `restore`.
+pub fn save_player(user: &str) -> Result<(), String> {
+    for_player_data(user, |kind| {
+        copy_or_remove(
+            &get_user_data_path(kind, user),
+            &get_user_data_path(&format!("{kind}.save"), user),
+        )
+    })
+}
+
+/// Puts back the progress and inventory of `user` as they were at their last `save`. A
+/// player who never saved starts over.
+pub fn restore_player(user: &str) -> Result<(), String> {
+    for_player_data(user, |kind| {
+        copy_or_remove(
+            &get_user_data_path(&format!("{kind}.save"), user),
+            &get_user_data_path(kind, user),
+        )
+    })
+}
+
+/// Erases the progress and inventory of `user`, for `restore --force`.
+pub fn reset_player(user: &str) -> Result<(), String> {
+    for_player_data(user, |kind| remove(&get_user_data_path(kind, user)))
+}
@@ -1,9 +1,15 @@
+use std::collections::VecDeque;
+
 pub struct DummyPrompter;
 pub trait UserPrompter {
     /// Returns true if user confirms (yes), false otherwise.
     fn confirm(&mut self, message: &str) -> bool;
     /// Returns a prompt message for the user.
     fn input(&mut self, message: &str) -> String;
+    /// Asks for a flag or an answer, which must not be kept anywhere.
+    fn secret(&mut self, message: &str) -> String {
+        self.input(message)
+    }
 }
 impl UserPrompter for DummyPrompter {
     fn confirm(&mut self, _message: &str) -> bool {
@@ -15,3 +21,119 @@ impl UserPrompter for DummyPrompter {
         String::new()
     }
 }
+
+/// Prompter that answers prompts from a script instead of asking the user.
+/// Answers are consumed in order. When they run out, `confirm` answers no and
+/// `input` answers with an empty string.
+/// Every prompt and its answer is recorded in `transcript`.
+#[derive(Default)]
+pub struct ScriptPrompter {
+    answers: VecDeque<String>,
+    pub transcript: Vec<String>,
+}
+
+impl ScriptPrompter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues answers for the prompts of the next command.
+    pub fn push_answers<I: IntoIterator<Item = String>>(&mut self, answers: I) {
+        self.answers.extend(answers);
+    }
+
+    /// Drops answers that were not used by the last command.
+    /// Returns how many were dropped.
+    pub fn clear_answers(&mut self) -> usize {
+        let unused = self.answers.len();
+        self.answers.clear();
+        unused
+    }
+}
+
+impl UserPrompter for ScriptPrompter {
+    fn confirm(&mut self, message: &str) -> bool {
+        let answer = self.answers.pop_front().unwrap_or_default();
+        let yes = matches!(answer.trim().to_lowercase().as_str(), "y" | "yes");
+        self.transcript.push(format!(
+            "{message} [y/N] {}",
+            if yes { "yes" } else { "no" }
+        ));
+        yes
+    }
+    fn input(&mut self, message: &str) -> String {
+        let answer = self.answers.pop_front().unwrap_or_default();
+        self.transcript.push(format!("{message}: {answer}"));
+        answer
+    }
+    fn secret(&mut self, message: &str) -> String {
+        let answer = self.answers.pop_front().unwrap_or_default();
+        self.transcript.push(format!("{message}: ****"));
+        answer
+    }
+}
+
+/// Prompter of a web request. The browser cannot be asked in the middle of a command, so
+/// prompts are answered from `answers`, sent along with the command. The first prompt left
+/// without an answer is kept in `unanswered`, for the browser to ask it and send the command
+/// again with one more answer. Confirmations are always yes, as with `DummyPrompter`.
+#[derive(Default)]
+pub struct WebPrompter {
+    answers: VecDeque<String>,
+    pub unanswered: Option<String>,
+}
+
+impl WebPrompter {
+    pub fn new(answers: Vec<String>) -> Self {
+        Self {
+            answers: answers.into(),
+            unanswered: None,
+        }
+    }
+}
+
+impl UserPrompter for WebPrompter {
+    fn confirm(&mut self, _message: &str) -> bool {
+        true
+    }
+    fn input(&mut self, message: &str) -> String {
+        self.answers.pop_front().unwrap_or_else(|| {
+            self.unanswered.get_or_insert_with(|| message.to_string());
+            String::new()
+        })
+    }
+}
+
+/// Passes prompts on to another prompter and keeps the answers.
+pub struct RecordingPrompter<'a> {
+    inner: &'a mut dyn UserPrompter,
+    pub answers: Vec<String>,
+}
+
+impl<'a> RecordingPrompter<'a> {
+    pub fn new(inner: &'a mut dyn UserPrompter) -> Self {
+        Self {
+            inner,
+            answers: Vec::new(),
+        }
+    }
+}
+
+impl UserPrompter for RecordingPrompter<'_> {
+    fn confirm(&mut self, message: &str) -> bool {
+        let yes = self.inner.confirm(message);
+        self.answers.push(if yes { "y" } else { "n" }.to_string());
+        yes
+    }
+    fn input(&mut self, message: &str) -> String {
+        let answer = self.inner.input(message);
+        self.answers.push(answer.clone());
+        answer
+    }
+    /// Secrets are kept as empty answers, so they are neither recorded nor replayed.
+    fn secret(&mut self, message: &str) -> String {
+        let answer = self.inner.secret(message);
+        self.answers.push(String::new());
+        answer
+    }
+}
@@ -0,0 +1,377 @@
+//! Rate limits of logins, registrations and flag guesses, per user and per IP.
+//!
+//! Every key (`user:<name>` or `ip:<address>`) may fail `attempts` times in `window_secs`
+//! seconds. One more failure locks it out for `lockout_secs`, doubled by every lockout in a
+//! row up to `max_lockout_secs`. Lockouts are in a row when the next one starts less than
+//! `window_secs` after the last ended. A success clears the failures and lockouts of its
+//! user only: those of an IP age out, so one good login does not let it guess again.
+//! Registrations all count, so accounts cannot be created in bulk. The limits are set in
+//! `config.json`:
+//!
+//! ```json
+//! "rate_limit": { "login": { "attempts": 5, "window_secs": 60 }, "lockout_secs": 30 }
+//! ```
+//!
+//! Settings left out keep their default, even within a limit.
+
+use crate::utils::config::load_config;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    Login,
+    Register,
+    /// Flags given to `unlock`. `solve` is refused too while locked out, but its answers are
+    /// not counted: a wrong one only gives a wrong flag, which `unlock` counts.
+    Flag,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Limit {
+    /// Failures allowed in the window.
+    pub attempts: u32,
+    pub window_secs: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(from = "RateLimitSettings")]
+pub struct RateLimitConfig {
+    pub login: Limit,
+    pub register: Limit,
+    pub flag: Limit,
+    /// First lockout, doubled by every lockout in a row.
+    pub lockout_secs: u64,
+    pub max_lockout_secs: u64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            login: Limit {
+                attempts: 5,
+                window_secs: 60,
+            },
+            register: Limit {
+                attempts: 3,
+                window_secs: 600,
+            },
+            flag: Limit {
+                attempts: 5,
+                window_secs: 60,
+            },
+            lockout_secs: 30,
+            max_lockout_secs: 3600,
+        }
+    }
+}
+
+/// A limit as written in `config.json`, where any field may be left out.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct LimitSettings {
+    attempts: Option<u32>,
+    window_secs: Option<u64>,
+}
+
+impl LimitSettings {
+    fn or(self, default: Limit) -> Limit {
+        Limit {
+            attempts: self.attempts.unwrap_or(default.attempts),
+            window_secs: self.window_secs.unwrap_or(default.window_secs),
+        }
+    }
+}
+
+/// `RateLimitConfig` as written in `config.json`, merged into the defaults.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct RateLimitSettings {
+    login: LimitSettings,
+    register: LimitSettings,
+    flag: LimitSettings,
+    lockout_secs: Option<u64>,
+    max_lockout_secs: Option<u64>,
+}
+
+impl From<RateLimitSettings> for RateLimitConfig {
+    fn from(settings: RateLimitSettings) -> Self {
+        let default = Self::default();
+        Self {
+            login: settings.login.or(default.login),
+            register: settings.register.or(default.register),
+            flag: settings.flag.or(default.flag),
+            lockout_secs: settings.lockout_secs.unwrap_or(default.lockout_secs),
+            max_lockout_secs: settings
+                .max_lockout_secs
+                .unwrap_or(default.max_lockout_secs),
+        }
+    }
+}
+
+impl RateLimitConfig {
+    fn limit(&self, action: Action) -> Limit {
+        match action {
+            Action::Login => self.login,
+            Action::Register => self.register,
+            Action::Flag => self.flag,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Attempts {
+    failures: Vec<Instant>,
+    lockouts: u32,
+    locked_until: Option<Instant>,
+}
+
+impl Attempts {
+    /// Whether nothing is left to remember: no failure in the window, and no lockout that
+    /// ended less than a window ago.
+    fn is_stale(&self, window: Duration, now: Instant) -> bool {
+        self.failures
+            .iter()
+            .all(|failure| now.duration_since(*failure) >= window)
+            && self.locked_until.is_none_or(|until| until + window <= now)
+    }
+}
+
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    attempts: HashMap<(Action, String), Attempts>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            attempts: HashMap::new(),
+        }
+    }
+
+    /// Time left before `key` may try `action` again, if it is locked out.
+    pub fn locked_for(&self, action: Action, key: &str, now: Instant) -> Option<Duration> {
+        let attempts = self.attempts.get(&(action, key.to_string()))?;
+        attempts
+            .locked_until
+            .filter(|until| *until > now)
+            .map(|until| until - now)
+    }
+
+    /// Forgets the keys that have nothing left to remember.
+    fn prune(&mut self, now: Instant) {
+        let config = &self.config;
+        self.attempts.retain(|(action, _), attempts| {
+            let window = Duration::from_secs(config.limit(*action).window_secs);
+            !attempts.is_stale(window, now)
+        });
+    }
+
+    /// Records an attempt of `key`. Returns the lockout it caused, if any.
+    pub fn record(
+        &mut self,
+        action: Action,
+        key: &str,
+        success: bool,
+        now: Instant,
+    ) -> Option<Duration> {
+        self.prune(now);
+        if success && action != Action::Register {
+            if key.starts_with("user:") {
+                self.attempts.remove(&(action, key.to_string()));
+            }
+            return None;
+        }
+        let limit = self.config.limit(action);
+        let window = Duration::from_secs(limit.window_secs);
+        let attempts = self.attempts.entry((action, key.to_string())).or_default();
+        attempts
+            .failures
+            .retain(|failure| now.duration_since(*failure) < window);
+        attempts.failures.push(now);
+        if attempts.failures.len() as u32 <= limit.attempts {
+            return None;
+        }
+
+        let factor = 2u64.saturating_pow(attempts.lockouts);
+        let secs = self
+            .config
+            .lockout_secs
+            .saturating_mul(factor)
+            .min(self.config.max_lockout_secs);
+        let lockout = Duration::from_secs(secs);
+        attempts.failures.clear();
+        attempts.lockouts += 1;
+        attempts.locked_until = Some(now + lockout);
+        Some(lockout)
+    }
+}
+
+/// Limiter of this process, with the limits of `config.json`.
+static LIMITER: Lazy<Mutex<RateLimiter>> =
+    Lazy::new(|| Mutex::new(RateLimiter::new(load_config().rate_limit)));
+
+pub fn user_key(username: &str) -> String {
+    format!("user:{username}")
+}
+
+pub fn ip_key(ip: IpAddr) -> String {
+    format!("ip:{ip}")
+}
+
+/// Keys of a request: its user, and its IP when known.
+pub fn keys(username: &str, ip: Option<IpAddr>) -> Vec<String> {
+    let mut keys = vec![user_key(username)];
+    keys.extend(ip.map(ip_key));
+    keys
+}
+
+/// `1h 5m`, `2m 30s` or `45s`.
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs().max(1);
+    match (secs / 3600, secs % 3600 / 60, secs % 60) {
+        (0, 0, s) => format!("{s}s"),
+        (0, m, 0) => format!("{m}m"),
+        (0, m, s) => format!("{m}m {s}s"),
+        (h, 0, _) => format!("{h}h"),
+        (h, m, _) => format!("{h}h {m}m"),
+    }
+}
+
+fn lockout_message(lockout: Duration) -> String {
+    format!(
+        "Too many attempts. Try again in {}.",
+        format_duration(lockout)
+    )
+}
+
+/// Fails with a message for the player if any of `keys` is locked out of `action`.
+pub fn check(action: Action, keys: &[String]) -> Result<(), String> {
+    let limiter = LIMITER.lock().unwrap_or_else(|e| e.into_inner());
+    let now = Instant::now();
+    match keys
+        .iter()
+        .filter_map(|key| limiter.locked_for(action, key, now))
+        .max()
+    {
+        Some(lockout) => Err(lockout_message(lockout)),
+        None => Ok(()),
+    }
+}
+
+/// Records an attempt of all `keys`. Returns a message for the player if it locked them out.
+pub fn record(action: Action, keys: &[String], success: bool) -> Option<String> {
+    let mut limiter = LIMITER.lock().unwrap_or_else(|e| e.into_inner());
+    let now = Instant::now();
+    keys.iter()
+        .filter_map(|key| limiter.record(action, key, success, now))
+        .max()
+        .map(lockout_message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter() {
+        let mut limiter = RateLimiter::new(RateLimitConfig {
+            lockout_secs: 10,
+            max_lockout_secs: 25,
+            ..Default::default()
+        });
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+
+        // Failures out of the window are forgotten
+        for secs in 0..5 {
+            assert_eq!(
+                limiter.record(Action::Login, "user:ana", false, at(secs)),
+                None
+            );
+        }
+        assert_eq!(
+            limiter.record(Action::Login, "user:ana", false, at(65)),
+            None
+        );
+        assert_eq!(limiter.locked_for(Action::Login, "user:ana", at(65)), None);
+
+        // The lockout doubles, up to the maximum
+        let lock = |limiter: &mut RateLimiter, from: u64| {
+            (from..from + 6)
+                .filter_map(|secs| limiter.record(Action::Flag, "ip:1.2.3.4", false, at(secs)))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(lock(&mut limiter, 0), [Duration::from_secs(10)]);
+        assert_eq!(
+            limiter.locked_for(Action::Flag, "ip:1.2.3.4", at(7)),
+            Some(Duration::from_secs(8))
+        );
+        assert_eq!(limiter.locked_for(Action::Login, "ip:1.2.3.4", at(7)), None);
+        assert_eq!(lock(&mut limiter, 20), [Duration::from_secs(20)]);
+        assert_eq!(lock(&mut limiter, 50), [Duration::from_secs(25)]);
+
+        // A success does not unlock an IP, and its lockouts are in a row until a window
+        // after the last
+        limiter.record(Action::Flag, "ip:1.2.3.4", true, at(60));
+        assert!(
+            limiter
+                .locked_for(Action::Flag, "ip:1.2.3.4", at(60))
+                .is_some()
+        );
+        assert_eq!(lock(&mut limiter, 80), [Duration::from_secs(25)]);
+        assert_eq!(lock(&mut limiter, 200), [Duration::from_secs(10)]);
+
+        // A success starts a user over
+        for secs in 300..306 {
+            limiter.record(Action::Flag, "user:ana", false, at(secs));
+        }
+        assert!(
+            limiter
+                .locked_for(Action::Flag, "user:ana", at(306))
+                .is_some()
+        );
+        limiter.record(Action::Flag, "user:ana", true, at(306));
+        assert_eq!(limiter.locked_for(Action::Flag, "user:ana", at(306)), None);
+
+        // Keys with nothing left to remember are forgotten
+        limiter.record(Action::Login, "user:bo", false, at(1000));
+        assert_eq!(limiter.attempts.len(), 1);
+
+        // Registrations all count
+        for secs in 0..3 {
+            limiter.record(Action::Register, "ip:1.2.3.4", true, at(secs));
+        }
+        assert!(
+            limiter
+                .record(Action::Register, "ip:1.2.3.4", true, at(3))
+                .is_some()
+        );
+
+        assert_eq!(format_duration(Duration::from_secs(150)), "2m 30s");
+        assert_eq!(format_duration(Duration::from_secs(3900)), "1h 5m");
+    }
+
+    #[test]
+    fn test_partial_config() {
+        let config: RateLimitConfig =
+            serde_json::from_str(r#"{ "register": { "attempts": 10 }, "lockout_secs": 5 }"#)
+                .unwrap();
+        let default = RateLimitConfig::default();
+        assert_eq!(
+            config.register,
+            Limit {
+                attempts: 10,
+                window_secs: default.register.window_secs,
+            }
+        );
+        assert_eq!(config.login, default.login);
+        assert_eq!(config.lockout_secs, 5);
+        assert_eq!(config.max_lockout_secs, default.max_lockout_secs);
+    }
+}
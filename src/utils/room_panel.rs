@@ -0,0 +1,181 @@
+//! The side panel of the GUI: the current objective, where the player is, what the room is
+//! about, the rooms around it and what the player has solved so far.
+
+use crate::commands::ls::list_directory_entries;
+use crate::commands::whereami;
+use crate::metainfo::hidden::Visibility;
+use crate::metainfo::info_reader::read_validate_info;
+use crate::metainfo::lock_perm::read_player_lock_perm;
+use crate::metainfo::objectives::{Objective, current, read_objectives};
+use crate::utils::progress::Progress;
+use crate::utils::style;
+use std::path::Path;
+
+/// A room on the mini-map.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapRoom {
+    pub name: String,
+    pub locked: bool,
+}
+
+impl MapRoom {
+    fn new(dir: &Path, name: String, root_dir: &Path, progress: &Progress) -> Self {
+        let locked = matches!(
+            read_player_lock_perm(&dir.join(&name), root_dir, progress),
+            Ok((_, true))
+        );
+        Self { name, locked }
+    }
+
+    fn label(&self, prefix: &str) -> String {
+        if self.locked {
+            format!("{prefix}{}/ {}", self.name, style::dim("[locked]"))
+        } else {
+            format!("{prefix}{}/", self.name)
+        }
+    }
+}
+
+/// What the panel shows about the current room. Rebuilt whenever the player changes room.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RoomPanel {
+    pub location: String,
+    pub about: String,
+    /// Name of the current room, `None` at HOME.
+    pub current: Option<String>,
+    /// Rooms next to the current one, itself included. Empty at HOME.
+    pub siblings: Vec<MapRoom>,
+    pub children: Vec<MapRoom>,
+    /// Objectives of the sekai.
+    pub objectives: Vec<Objective>,
+}
+
+impl RoomPanel {
+    pub fn new(current_dir: &Path, root_dir: &Path, visibility: &Visibility) -> Self {
+        let about = read_validate_info(&current_dir.join(".dir_info/info.json"))
+            .map(|info| info.about)
+            .unwrap_or_default();
+        let rooms = |dir: &Path| -> Vec<MapRoom> {
+            let (_, directories) = list_directory_entries(dir, root_dir, visibility);
+            directories
+                .into_iter()
+                .map(|name| MapRoom::new(dir, name, root_dir, &visibility.progress))
+                .collect()
+        };
+        let (current, siblings) = match current_dir.parent() {
+            Some(parent) if current_dir != root_dir => (
+                current_dir
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned()),
+                rooms(parent),
+            ),
+            _ => (None, Vec::new()),
+        };
+        Self {
+            location: whereami(current_dir, root_dir),
+            about,
+            current,
+            siblings,
+            children: rooms(current_dir),
+            objectives: read_objectives(root_dir),
+        }
+    }
+
+    /// Styled lines of the panel, before wrapping.
+    pub fn lines(&self, progress: &Progress) -> Vec<String> {
+        let mut lines = Vec::new();
+        if !self.objectives.is_empty() {
+            lines.push(style::bold("Objective"));
+            match current(&self.objectives, progress) {
+                Some(objective) => lines.push(objective.task.clone()),
+                None => lines.push(style::dim("All objectives complete!")),
+            }
+            lines.push(String::new());
+        }
+
+        lines.push(style::bold("Location"));
+        lines.push(self.location.clone());
+
+        lines.push(String::new());
+        lines.push(style::bold("About"));
+        if self.about.trim().is_empty() {
+            lines.push(style::dim("Nothing is known about this room."));
+        } else {
+            lines.extend(self.about.lines().map(str::to_string));
+        }
+
+        lines.push(String::new());
+        lines.push(style::bold("Map"));
+        let children = self.children.iter().map(|room| room.label("  |- "));
+        match &self.current {
+            None => {
+                lines.push(style::bold("HOME"));
+                lines.extend(children);
+            }
+            Some(current) => {
+                lines.push("..".to_string());
+                for room in &self.siblings {
+                    if &room.name == current {
+                        lines.push(style::bold(&room.label("|- ")));
+                        lines.extend(children.clone());
+                    } else {
+                        lines.push(room.label("|- "));
+                    }
+                }
+            }
+        }
+
+        let solved: Vec<String> = progress.solved.keys().cloned().collect();
+        let flags: Vec<String> = progress.solved.values().cloned().collect();
+        for (title, items) in [("Solved levels", solved), ("Flags", flags)] {
+            lines.push(String::new());
+            lines.push(style::bold(title));
+            if items.is_empty() {
+                lines.push(style::dim("None yet."));
+            } else {
+                lines.extend(items);
+            }
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::setup_test_dir;
+
+    #[test]
+    fn test_room_panel() {
+        let (_temp, root) = setup_test_dir(true);
+        let visibility = Visibility::default();
+        let home = RoomPanel::new(&root, &root, &visibility);
+        assert_eq!(home.location, "HOME");
+        assert_eq!(home.current, None);
+        let names: Vec<&str> = home.children.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["subdir1", "subdir2"]);
+
+        let panel = RoomPanel::new(&root.join("subdir1"), &root, &visibility);
+        assert_eq!(panel.location, "HOME/subdir1");
+        assert_eq!(panel.current.as_deref(), Some("subdir1"));
+        assert_eq!(panel.siblings.len(), 2);
+        assert_eq!(panel.children[0].name, "nested1");
+
+        let mut progress = Progress::default();
+        progress
+            .solved
+            .insert("level_1".to_string(), "f1a9".to_string());
+
+        let lines: Vec<String> = panel
+            .lines(&progress)
+            .iter()
+            .map(|l| style::strip(l))
+            .collect();
+        let map = lines.iter().position(|l| l == "Map").unwrap();
+        assert_eq!(
+            lines[map + 1..map + 5],
+            ["..", "|- subdir1/", "  |- nested1/", "|- subdir2/"]
+        );
+        assert!(lines.ends_with(&["Flags".to_string(), "f1a9".to_string()]));
+    }
+}
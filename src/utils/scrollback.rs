@@ -0,0 +1,167 @@
+//! Scrollback of the GUI terminal: searching it, bounding its size, and paging long output.
+
+use crate::utils::style;
+
+/// Lines kept in the scrollback when the config does not say otherwise.
+pub const DEFAULT_SCROLLBACK_LINES: usize = 5000;
+
+/// An occurrence of the search query. Offsets count visible characters, styling excluded.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Match {
+    pub line: usize,
+    pub start: usize,
+    pub len: usize,
+}
+
+/// Finds every occurrence of `query` in `lines`, oldest first.
+/// The search ignores case unless the query has an uppercase letter.
+pub fn find_matches(lines: &[String], query: &str) -> Vec<Match> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let ignore_case = !query.chars().any(char::is_uppercase);
+    // Folds char by char, so offsets still count the characters on screen
+    let fold = |text: &str| -> Vec<char> {
+        text.chars()
+            .map(|c| {
+                if ignore_case {
+                    c.to_lowercase().next().unwrap_or(c)
+                } else {
+                    c
+                }
+            })
+            .collect()
+    };
+    let needle = fold(query);
+
+    let mut matches = Vec::new();
+    for (line_no, line) in lines.iter().enumerate() {
+        let haystack = fold(&style::strip(line));
+        let mut start = 0;
+        while start + needle.len() <= haystack.len() {
+            if haystack[start..start + needle.len()] == needle[..] {
+                matches.push(Match {
+                    line: line_no,
+                    start,
+                    len: needle.len(),
+                });
+                start += needle.len();
+            } else {
+                start += 1;
+            }
+        }
+    }
+    matches
+}
+
+/// Drops the oldest lines so at most `limit` remain. Returns how many were dropped.
+pub fn trim_scrollback(lines: &mut Vec<String>, limit: usize) -> usize {
+    let excess = lines.len().saturating_sub(limit.max(1));
+    lines.drain(..excess);
+    excess
+}
+
+/// A `less`-like view of output too long for the window.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pager {
+    pub title: String,
+    /// Lines of the text, already wrapped to the window width.
+    pub lines: Vec<String>,
+    /// Index of the first line shown.
+    pub top: usize,
+}
+
+impl Pager {
+    pub fn new(title: impl Into<String>, lines: Vec<String>) -> Self {
+        Self {
+            title: title.into(),
+            lines,
+            top: 0,
+        }
+    }
+
+    fn max_top(&self, height: usize) -> usize {
+        self.lines.len().saturating_sub(height)
+    }
+
+    /// Scrolls by `delta` lines, staying within the text.
+    pub fn scroll(&mut self, delta: isize, height: usize) {
+        let top = self.top.saturating_add_signed(delta);
+        self.top = top.min(self.max_top(height));
+    }
+
+    pub fn page_down(&mut self, height: usize) {
+        self.scroll(height.max(1) as isize, height);
+    }
+
+    pub fn page_up(&mut self, height: usize) {
+        self.scroll(-(height.max(1) as isize), height);
+    }
+
+    pub fn home(&mut self) {
+        self.top = 0;
+    }
+
+    pub fn end(&mut self, height: usize) {
+        self.top = self.max_top(height);
+    }
+
+    /// Lines shown in a window of `height` lines.
+    pub fn visible(&self, height: usize) -> &[String] {
+        let end = (self.top + height).min(self.lines.len());
+        &self.lines[self.top.min(end)..end]
+    }
+
+    /// Status line, like `note.txt lines 1-20/42 (48%)`.
+    pub fn status(&self, height: usize) -> String {
+        let total = self.lines.len().max(1);
+        let last = (self.top + height).min(self.lines.len());
+        let percent = last * 100 / total;
+        format!(
+            "{} lines {}-{last}/{} ({percent}%)  q: quit  Space/b: page  arrows: scroll",
+            self.title,
+            self.top + 1,
+            self.lines.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scrollback() {
+        let mut lines: Vec<String> = vec![
+            "Objects: note.txt".to_string(),
+            style::bold("NOTE: read the Note"),
+            "nothing".to_string(),
+        ];
+        let matches = find_matches(&lines, "note");
+        assert_eq!(matches.len(), 3);
+        assert_eq!(
+            matches[1],
+            Match {
+                line: 1,
+                start: 0,
+                len: 4
+            }
+        );
+        assert_eq!(find_matches(&lines, "Note").len(), 1);
+        assert!(find_matches(&lines, "").is_empty());
+
+        assert_eq!(trim_scrollback(&mut lines, 2), 1);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(trim_scrollback(&mut lines, 2), 0);
+
+        let mut pager = Pager::new("note.txt", (1..=10).map(|i| i.to_string()).collect());
+        pager.page_down(4);
+        assert_eq!(pager.visible(4), ["5", "6", "7", "8"]);
+        pager.page_down(4);
+        pager.page_down(4);
+        assert_eq!(pager.top, 6);
+        assert!(pager.status(4).starts_with("note.txt lines 7-10/10 (100%)"));
+        pager.scroll(-10, 4);
+        assert_eq!(pager.top, 0);
+    }
+}
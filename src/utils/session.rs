@@ -0,0 +1,264 @@
+//! Recording and replay of play sessions.
+//!
+//! A recording is a file of JSON lines, one per command typed by the player, with when it
+//! was typed, the answers given to its prompts and what it printed:
+//!
+//! ```json
+//! {"time":5120,"dir":"","command":"go mess","status":0,"output":"...","new_dir":"mess"}
+//! ```
+//!
+//! `time` is in milliseconds since the recording started, and directories are relative to
+//! HOME. Flags and answers to levels are not recorded. The lines come from the
+//! `LineEntered` events of the shell, which the recording subscribes to. A replay runs the
+//! commands again with the recorded answers, and reports the ones whose output changed.
+//! Replays change the sekai and the player's progress like the original session did, so
+//! they are best run on a freshly restored sekai.
+
+use crate::commands::cmds::{CommandResult, cmd_manager};
+use crate::utils::events::{self, EventRecord, ShellEvent};
+use crate::utils::log;
+use crate::utils::prompt::ScriptPrompter;
+use crate::utils::style;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SessionEntry {
+    /// Milliseconds since the recording started.
+    pub time: u64,
+    /// Room the command was typed in.
+    pub dir: String,
+    pub command: String,
+    /// Answers to the prompts of the command, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub answers: Vec<String>,
+    pub status: i32,
+    /// What the command printed, without styling.
+    pub output: String,
+    /// Room the player was in after it.
+    pub new_dir: String,
+}
+
+/// Text printed for the result of a command, without styling.
+pub fn result_text(result: &CommandResult) -> String {
+    match result {
+        CommandResult::Output(output) | CommandResult::ChangeDirectory(_, output) => {
+            style::strip(&output.text())
+        }
+        CommandResult::NotFound => "Command not found. Try `help`.".to_string(),
+        CommandResult::Clear | CommandResult::Exit => String::new(),
+    }
+}
+
+struct Recording {
+    file: File,
+    started: Instant,
+    /// Id of the subscriber writing to it.
+    subscription: usize,
+}
+
+/// The session being recorded, if any.
+static RECORDING: Lazy<Mutex<Option<Recording>>> = Lazy::new(|| Mutex::new(None));
+
+/// Records the lines typed from now on to `path`, replacing its contents.
+pub fn start_recording(path: &Path) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let recording = Recording {
+        file,
+        started: Instant::now(),
+        subscription: events::subscribe(record_line),
+    };
+    if let Some(previous) = RECORDING.lock().unwrap().replace(recording) {
+        events::unsubscribe(previous.subscription);
+    }
+    Ok(())
+}
+
+pub fn stop_recording() {
+    if let Some(recording) = RECORDING.lock().unwrap().take() {
+        events::unsubscribe(recording.subscription);
+    }
+}
+
+/// Subscriber writing the lines typed by the player to the recording.
+fn record_line(record: &EventRecord) {
+    let ShellEvent::LineEntered(line) = &record.event else {
+        return;
+    };
+    let mut recording = RECORDING.lock().unwrap();
+    let Some(recording) = recording.as_mut() else {
+        return;
+    };
+    let entry = SessionEntry {
+        time: recording.started.elapsed().as_millis() as u64,
+        dir: line.dir.clone(),
+        command: line.command.clone(),
+        answers: line.answers.clone(),
+        status: line.status,
+        output: line.output.clone(),
+        new_dir: line.new_dir.clone(),
+    };
+    let line = serde_json::to_string(&entry).unwrap_or_default();
+    if let Err(e) = writeln!(recording.file, "{line}") {
+        log::log_error("session", &format!("Failed to record: {e}"));
+    }
+}
+
+/// How fast a session is replayed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pace {
+    /// With the pauses of the original session.
+    Original,
+    /// One command at a time, when asked for the next.
+    Step,
+    /// Without pauses.
+    Instant,
+}
+
+/// A recorded session being replayed.
+#[derive(Clone, Debug)]
+pub struct Replay {
+    pub entries: Vec<SessionEntry>,
+    pub next: usize,
+    pub pace: Pace,
+}
+
+impl Replay {
+    pub fn new(entries: Vec<SessionEntry>, pace: Pace) -> Self {
+        Self {
+            entries,
+            next: 0,
+            pace,
+        }
+    }
+
+    pub fn load(path: &Path, pace: Pace) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let entries = contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line)
+                    .map_err(|e| format!("{}: line {}: {e}", path.display(), i + 1))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self::new(entries, pace))
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.next >= self.entries.len()
+    }
+
+    /// Pause before the next command at the original pace.
+    pub fn delay(&self) -> Duration {
+        let Some(next) = self.entries.get(self.next) else {
+            return Duration::ZERO;
+        };
+        let previous = match self.next {
+            0 => 0,
+            i => self.entries[i - 1].time,
+        };
+        Duration::from_millis(next.time.saturating_sub(previous))
+    }
+
+    /// Takes the next command to replay, with a prompter giving its recorded answers.
+    pub fn next_entry(&mut self) -> Option<(SessionEntry, ScriptPrompter)> {
+        let entry = self.entries.get(self.next)?.clone();
+        self.next += 1;
+        let mut prompter = ScriptPrompter::new();
+        prompter.push_answers(entry.answers.clone());
+        Some((entry, prompter))
+    }
+}
+
+/// Replays a session without a window, starting at HOME. Prints every command with its
+/// output through `print`, and returns how many commands printed something else than when
+/// they were recorded.
+pub fn replay_headless(replay: &mut Replay, root_dir: &Path, mut print: impl FnMut(&str)) -> usize {
+    let root_dir = root_dir.to_path_buf();
+    let mut current_dir = root_dir.clone();
+    let mut mismatches = 0;
+    while !replay.is_done() {
+        match replay.pace {
+            Pace::Original => thread::sleep(replay.delay()),
+            Pace::Step => {
+                print(&style::dim("Press Enter for the next command."));
+                let mut line = String::new();
+                if matches!(io::stdin().lock().read_line(&mut line), Ok(0) | Err(_)) {
+                    break;
+                }
+            }
+            Pace::Instant => {}
+        }
+        let Some((entry, mut prompter)) = replay.next_entry() else {
+            break;
+        };
+        print(&format!("> {}", entry.command));
+        let parts: Vec<&str> = entry.command.split_whitespace().collect();
+        let result = cmd_manager(&parts, &current_dir, &root_dir, &mut prompter);
+        for line in prompter.transcript.drain(..) {
+            print(&line);
+        }
+
+        let output = result_text(&result);
+        match &result {
+            CommandResult::Output(output) => print(&output.text()),
+            CommandResult::ChangeDirectory(new_dir, output) => {
+                current_dir = new_dir.clone();
+                print(&output.text());
+            }
+            _ => print(&output),
+        }
+        if output != entry.output {
+            mismatches += 1;
+            print(&style::error(
+                "The output differs from the recording, which was:",
+            ));
+            print(&entry.output);
+        }
+        if let CommandResult::Exit = result {
+            break;
+        }
+    }
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::cmds::run_line;
+    use crate::utils::test_utils::setup_test_dir;
+
+    #[test]
+    fn test_record_and_replay() {
+        let (temp, root) = setup_test_dir(true);
+        let session = temp.path().join("session.jsonl");
+        start_recording(&session).unwrap();
+        let mut prompter = ScriptPrompter::new();
+        let result = run_line("go subdir1", &root, &root, &mut prompter);
+        let CommandResult::ChangeDirectory(dir, _) = result else {
+            panic!("expected a directory change");
+        };
+        run_line("ls", &dir, &root, &mut prompter);
+        stop_recording();
+
+        let mut replay = Replay::load(&session, Pace::Instant).unwrap();
+        assert_eq!(replay.entries.len(), 2);
+        assert_eq!(replay.entries[0].new_dir, "subdir1");
+        assert_eq!(replay.entries[1].dir, "subdir1");
+        assert!(replay.entries[1].output.contains("file2.txt"));
+        assert_eq!(replay_headless(&mut replay, &root, |_| {}), 0);
+        assert!(replay.is_done());
+
+        replay.entries[1].output = "something else".to_string();
+        replay.next = 0;
+        assert_eq!(replay_headless(&mut replay, &root, |_| {}), 1);
+    }
+}
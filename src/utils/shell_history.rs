@@ -1,22 +1,310 @@
-use crate::utils::globals::SHELL_HISTORY;
+use crate::utils::caller::Caller;
+use crate::utils::config::{get_config_path, get_data_dir};
+use crate::utils::globals::{SHELL_HISTORY, get_world_dir};
+use crate::utils::log;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
-// To add to history:
-pub fn add_to_history(input: &str) {
-    if let Ok(mut history) = SHELL_HISTORY.lock() {
-        // if the current input is same as the last one, do not add it again
-        if let Some(last) = history.last() {
-            if last == input {
-                return;
-            }
+/// Maximum number of entries kept per history file.
+pub const MAX_HISTORY: usize = 1000;
+
+/// User name under which history is stored when nobody is logged in.
+pub const GUEST_USER: &str = "guest";
+
+/// Returns the user the commands run for: the caller of a web request, or the logged-in
+/// user of the local shell.
+pub fn current_user() -> String {
+    Caller::current().user
+}
+
+/// File name for `name`: lowercase letters, digits, `-` and `_` are kept, and every other
+/// byte is written as `%XX`. Different names never share a file, even on file systems that
+/// ignore case, and an empty name is `%`.
+fn encode_name(name: &str) -> String {
+    if name.is_empty() {
+        return "%".to_string();
+    }
+    name.bytes()
+        .map(|b| match b {
+            b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' => (b as char).to_string(),
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+/// Path of the history file of `user` for the current sekai.
+/// History lives in the data dir: `~/.local/share/deemak/history/<sekai>/<user>`
+pub fn get_history_path(user: &str) -> PathBuf {
+    get_user_data_path("history", user)
+}
+
+/// Path of a file holding data of `user` for the current sekai:
+/// `~/.local/share/deemak/<kind>/<sekai>/<user>`. Files of older versions, kept next to the
+/// config, are moved there the first time.
+pub fn get_user_data_path(kind: &str, user: &str) -> PathBuf {
+    let world_dir = get_world_dir();
+    let sekai = world_dir
+        .file_name()
+        .map(|s| encode_name(&s.to_string_lossy()))
+        .unwrap_or_else(|| "default".to_string());
+    let relative = PathBuf::from(kind).join(sekai).join(encode_name(user));
+
+    let path = get_data_dir().join(&relative);
+    let mut legacy = get_config_path();
+    legacy.pop();
+    let legacy = legacy.join(&relative);
+    if !path.exists() && legacy.is_file() {
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
         }
-        history.push(input.to_string());
+        if let Err(e) = fs::rename(&legacy, &path) {
+            log::log_warning(
+                "history",
+                &format!(
+                    "Could not move {} to {}: {}",
+                    legacy.display(),
+                    path.display(),
+                    e
+                ),
+            );
+        }
+    }
+    path
+}
+
+/// Locks of the data files being updated, by kind and user.
+type UserDataLocks = HashMap<(String, String), Arc<Mutex<()>>>;
+static USER_DATA_LOCKS: Lazy<Mutex<UserDataLocks>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Lock to hold while reading, changing and writing back the data file of `kind` for
+/// `user`, so that concurrent requests of a user do not undo each other's changes.
+pub fn user_data_lock(kind: &str, user: &str) -> Arc<Mutex<()>> {
+    let mut locks = USER_DATA_LOCKS.lock().unwrap_or_else(|e| e.into_inner());
+    locks
+        .entry((kind.to_string(), user.to_string()))
+        .or_default()
+        .clone()
+}
+
+/// Reads the history file of `user`, oldest entry first.
+fn load_history(user: &str) -> Vec<String> {
+    let path = get_history_path(user);
+    match fs::read_to_string(&path) {
+        Ok(contents) => contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.to_string())
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Overwrites the history file of `user` with `history`.
+fn write_history(user: &str, history: &[String]) {
+    let path = get_history_path(user);
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let mut contents = history.join("\n");
+    if !contents.is_empty() {
+        contents.push('\n');
+    }
+    if let Err(e) = fs::write(&path, contents) {
+        log::log_warning(
+            "history",
+            &format!("Failed to write {}: {e}", path.display()),
+        );
+    }
+}
+
+/// Appends a single entry to the history file of `user`.
+fn append_history(user: &str, input: &str) {
+    let path = get_history_path(user);
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let result = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| writeln!(file, "{input}"));
+    if let Err(e) = result {
+        log::log_warning(
+            "history",
+            &format!("Failed to append {}: {e}", path.display()),
+        );
+    }
+}
+
+// To add to history of a given user:
+pub fn add_to_user_history(user: &str, input: &str) {
+    let input = input.trim();
+    if input.is_empty() || input.contains('\n') {
+        return;
+    }
+    let mut cache = SHELL_HISTORY.lock().unwrap_or_else(|e| e.into_inner());
+    let history = cache
+        .entry(get_history_path(user))
+        .or_insert_with(|| load_history(user));
+
+    // if the current input is same as the last one, do not add it again
+    if history.last().is_some_and(|last| last == input) {
+        return;
+    }
+    history.push(input.to_string());
+
+    if history.len() > MAX_HISTORY {
+        let excess = history.len() - MAX_HISTORY;
+        history.drain(..excess);
+        write_history(user, history);
+    } else {
+        append_history(user, input);
     }
 }
 
+// To read history of a given user:
+pub fn get_user_history(user: &str) -> Vec<String> {
+    let mut cache = SHELL_HISTORY.lock().unwrap_or_else(|e| e.into_inner());
+    cache
+        .entry(get_history_path(user))
+        .or_insert_with(|| load_history(user))
+        .clone()
+}
+
+// To clear history of a given user:
+pub fn clear_user_history(user: &str) {
+    let mut cache = SHELL_HISTORY.lock().unwrap_or_else(|e| e.into_inner());
+    cache.insert(get_history_path(user), Vec::new());
+    write_history(user, &[]);
+}
+
+// To add to history:
+pub fn add_to_history(input: &str) {
+    add_to_user_history(&current_user(), input);
+}
+
 // To read history:
 pub fn get_history() -> Vec<String> {
-    SHELL_HISTORY
-        .lock()
-        .unwrap_or_else(|e| e.into_inner())
-        .clone()
+    get_user_history(&current_user())
+}
+
+/// Expands history references in `input`:
+/// - `!!`  : the last command
+/// - `!n`  : the n-th command as numbered by `history`
+/// - `!-n` : the n-th last command
+/// - `!str`: the most recent command starting with `str`
+///
+/// Returns the expanded line, or an error message if an event is not found.
+pub fn expand_history(input: &str, history: &[String]) -> Result<String, String> {
+    if !input.contains('!') {
+        return Ok(input.to_string());
+    }
+
+    let mut expanded = String::new();
+    let mut rest = input;
+    while let Some(pos) = rest.find('!') {
+        expanded.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+
+        // A lone `!` or `!` followed by whitespace/`=` is left untouched
+        let Some(next) = after.chars().next() else {
+            expanded.push('!');
+            rest = after;
+            break;
+        };
+        if next.is_whitespace() || next == '=' {
+            expanded.push('!');
+            rest = after;
+            continue;
+        }
+
+        let (event, consumed) = if next == '!' {
+            ("!", 1)
+        } else {
+            let len = after
+                .find(|c: char| c.is_whitespace() || c == '!')
+                .unwrap_or(after.len());
+            (&after[..len], len)
+        };
+
+        let found = if event == "!" {
+            history.last()
+        } else if let Some(n) = event
+            .strip_prefix('-')
+            .and_then(|n| n.parse::<usize>().ok())
+        {
+            history.len().checked_sub(n).and_then(|i| history.get(i))
+        } else if let Ok(n) = event.parse::<usize>() {
+            n.checked_sub(1).and_then(|i| history.get(i))
+        } else {
+            history.iter().rev().find(|cmd| cmd.starts_with(event))
+        };
+
+        match found {
+            Some(cmd) => expanded.push_str(cmd),
+            None => return Err(format!("!{event}: event not found")),
+        }
+        rest = &after[consumed..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Finds the most recent history entry containing `query`, searching backwards
+/// from (and excluding) index `before`. Used by the reverse incremental search.
+pub fn reverse_search(history: &[String], query: &str, before: usize) -> Option<usize> {
+    if query.is_empty() {
+        return None;
+    }
+    let end = before.min(history.len());
+    history[..end].iter().rposition(|cmd| cmd.contains(query))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<String> {
+        vec!["ls".into(), "go room_1".into(), "read dog.txt".into()]
+    }
+
+    #[test]
+    fn test_expand_history() {
+        let history = sample();
+        assert_eq!(expand_history("!!", &history).unwrap(), "read dog.txt");
+        assert_eq!(expand_history("!2", &history).unwrap(), "go room_1");
+        assert_eq!(expand_history("!-3", &history).unwrap(), "ls");
+        assert_eq!(expand_history("!go", &history).unwrap(), "go room_1");
+        assert_eq!(
+            expand_history("echo !1 !!", &history).unwrap(),
+            "echo ls read dog.txt"
+        );
+        assert_eq!(expand_history("echo hi !", &history).unwrap(), "echo hi !");
+        assert!(expand_history("!9", &history).is_err());
+        assert!(expand_history("!!", &[]).is_err());
+    }
+
+    #[test]
+    fn test_encode_name() {
+        assert_eq!(encode_name("ana_2-b"), "ana_2-b");
+        assert_eq!(encode_name("ana."), "ana%2E");
+        assert_eq!(encode_name("ana!"), "ana%21");
+        assert_eq!(encode_name("Ana"), "%41na");
+        assert_eq!(encode_name("ü"), "%C3%BC");
+        assert_eq!(encode_name("../x"), "%2E%2E%2Fx");
+        assert_eq!(encode_name(""), "%");
+    }
+
+    #[test]
+    fn test_reverse_search() {
+        let history = sample();
+        assert_eq!(reverse_search(&history, "o", history.len()), Some(2));
+        assert_eq!(reverse_search(&history, "o", 2), Some(1));
+        assert_eq!(reverse_search(&history, "o", 1), None);
+        assert_eq!(reverse_search(&history, "", 3), None);
+    }
 }
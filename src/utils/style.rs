@@ -0,0 +1,316 @@
+//! Styling for command output.
+//!
+//! Commands style their output with a small subset of ANSI escape codes (colors, bold and dim),
+//! so the TTY front-end can print it as is. The GUI draws the styled spans itself, and the web
+//! front-end gets the output translated to HTML.
+
+const ESC: char = '\x1b';
+const RESET: &str = "\x1b[0m";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Color {
+    #[default]
+    Default,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    Gray,
+}
+
+impl Color {
+    fn code(self) -> Option<u8> {
+        match self {
+            Color::Default => None,
+            Color::Red => Some(31),
+            Color::Green => Some(32),
+            Color::Yellow => Some(33),
+            Color::Blue => Some(34),
+            Color::Magenta => Some(35),
+            Color::Cyan => Some(36),
+            Color::Gray => Some(90),
+        }
+    }
+
+    fn from_code(code: u8) -> Option<Self> {
+        match code {
+            31 | 91 => Some(Color::Red),
+            32 | 92 => Some(Color::Green),
+            33 | 93 => Some(Color::Yellow),
+            34 | 94 => Some(Color::Blue),
+            35 | 95 => Some(Color::Magenta),
+            36 | 96 => Some(Color::Cyan),
+            90 => Some(Color::Gray),
+            30 | 37 | 39 | 97 => Some(Color::Default),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Color::Default => "default",
+            Color::Red => "red",
+            Color::Green => "green",
+            Color::Yellow => "yellow",
+            Color::Blue => "blue",
+            Color::Magenta => "magenta",
+            Color::Cyan => "cyan",
+            Color::Gray => "gray",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Style {
+    pub color: Color,
+    pub bold: bool,
+    pub dim: bool,
+}
+
+impl Style {
+    pub fn is_plain(&self) -> bool {
+        *self == Style::default()
+    }
+
+    /// Escape code that switches to this style, empty for the plain style.
+    fn code(&self) -> String {
+        let mut params: Vec<String> = Vec::new();
+        if self.bold {
+            params.push("1".to_string());
+        }
+        if self.dim {
+            params.push("2".to_string());
+        }
+        if let Some(code) = self.color.code() {
+            params.push(code.to_string());
+        }
+        if params.is_empty() {
+            String::new()
+        } else {
+            format!("{ESC}[{}m", params.join(";"))
+        }
+    }
+
+    /// Applies the parameters of an escape code like `\x1b[1;31m`.
+    fn apply(&mut self, params: &str) {
+        for param in params.split(';') {
+            match param.parse::<u8>().unwrap_or(0) {
+                0 => *self = Style::default(),
+                1 => self.bold = true,
+                2 => self.dim = true,
+                22 => {
+                    self.bold = false;
+                    self.dim = false;
+                }
+                code => {
+                    if let Some(color) = Color::from_code(code) {
+                        self.color = color;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Styles every line of `text`. Styles do not nest: the inner one ends the outer one.
+pub fn paint(text: &str, style: Style) -> String {
+    let code = style.code();
+    if code.is_empty() {
+        return text.to_string();
+    }
+    text.split('\n')
+        .map(|line| {
+            if line.is_empty() {
+                String::new()
+            } else {
+                format!("{code}{line}{RESET}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn colored(text: &str, color: Color) -> String {
+    paint(
+        text,
+        Style {
+            color,
+            ..Default::default()
+        },
+    )
+}
+
+pub fn bold(text: &str) -> String {
+    paint(
+        text,
+        Style {
+            bold: true,
+            ..Default::default()
+        },
+    )
+}
+
+pub fn dim(text: &str) -> String {
+    paint(
+        text,
+        Style {
+            dim: true,
+            ..Default::default()
+        },
+    )
+}
+
+/// Style used for error messages.
+pub fn error(text: &str) -> String {
+    colored(text, Color::Red)
+}
+
+/// Splits styled text into runs of the same style. Unknown escape codes are dropped.
+pub fn spans(text: &str) -> Vec<(Style, String)> {
+    let mut spans: Vec<(Style, String)> = Vec::new();
+    let mut style = Style::default();
+    let mut current = String::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != ESC {
+            current.push(c);
+            continue;
+        }
+        if chars.peek() != Some(&'[') {
+            continue;
+        }
+        chars.next();
+        let mut params = String::new();
+        let mut end = None;
+        for n in chars.by_ref() {
+            if n.is_ascii_digit() || n == ';' {
+                params.push(n);
+            } else {
+                end = Some(n);
+                break;
+            }
+        }
+        if end == Some('m') {
+            if !current.is_empty() {
+                spans.push((style, std::mem::take(&mut current)));
+            }
+            style.apply(&params);
+        }
+    }
+    if !current.is_empty() {
+        spans.push((style, current));
+    }
+    spans
+}
+
+/// Removes all styling from text.
+pub fn strip(text: &str) -> String {
+    if !text.contains(ESC) {
+        return text.to_string();
+    }
+    spans(text).into_iter().map(|(_, text)| text).collect()
+}
+
+/// Wraps a styled line into lines of at most `width` visible characters.
+/// Each line is styled on its own, so it can be drawn independently.
+pub fn wrap(line: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = vec![String::new()];
+    let mut count = 0;
+
+    for (style, text) in spans(line) {
+        let code = style.code();
+        let mut open = false;
+        for c in text.chars() {
+            if count == width {
+                if open {
+                    lines.last_mut().unwrap().push_str(RESET);
+                    open = false;
+                }
+                lines.push(String::new());
+                count = 0;
+            }
+            let current = lines.last_mut().unwrap();
+            if !open && !code.is_empty() {
+                current.push_str(&code);
+                open = true;
+            }
+            current.push(c);
+            count += 1;
+        }
+        if open {
+            lines.last_mut().unwrap().push_str(RESET);
+        }
+    }
+    lines
+}
+
+/// Translates styled text to HTML, escaping it. Styles become `ansi-*` classes.
+pub fn to_html(text: &str) -> String {
+    let mut html = String::new();
+    for (style, text) in spans(text) {
+        let escaped = escape_html(&text);
+        if style.is_plain() {
+            html.push_str(&escaped);
+            continue;
+        }
+        let mut classes = Vec::new();
+        if style.color != Color::Default {
+            classes.push(format!("ansi-{}", style.color.name()));
+        }
+        if style.bold {
+            classes.push("ansi-bold".to_string());
+        }
+        if style.dim {
+            classes.push("ansi-dim".to_string());
+        }
+        html.push_str(&format!(
+            "<span class=\"{}\">{escaped}</span>",
+            classes.join(" ")
+        ));
+    }
+    html
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_styled_text() {
+        let text = format!("{} {}", bold("Objects:"), colored("room_1", Color::Blue));
+        assert_eq!(strip(&text), "Objects: room_1");
+        assert_eq!(
+            to_html(&text),
+            "<span class=\"ansi-bold\">Objects:</span> <span class=\"ansi-blue\">room_1</span>"
+        );
+        assert_eq!(to_html("<b>"), "&lt;b&gt;");
+
+        let wrapped = wrap(&error("abcde"), 2);
+        assert_eq!(wrapped.len(), 3);
+        assert!(
+            wrapped
+                .iter()
+                .all(|line| spans(line)[0].0.color == Color::Red)
+        );
+        assert_eq!(strip(&wrapped.concat()), "abcde");
+    }
+}
@@ -1,6 +1,331 @@
-use crate::commands::cmds::normalize_path;
+use crate::commands::cmds::{check_dir_info, normalize_path};
+use crate::commands::ls::list_directory_entries;
+use crate::metainfo::hidden::Visibility;
+use crate::metainfo::lock_perm::read_player_lock_perm;
+use crate::utils::alias::{expand_chain, get_aliases, get_functions};
+use crate::utils::inventory::Inventory;
+use crate::utils::shell_history::current_user;
+use crate::utils::variables::{BUILTIN_VARS, get_vars};
 use std::path::Path;
 
+/// What the arguments of a command refer to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArgKind {
+    None,
+    Command,
+    Dir,
+    File,
+    Path,
+    Locked,
+    Item,
+    Alias,
+    Function,
+    Variable,
+}
+
+/// Flags and argument kind of a command, used for completion.
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub flags: &'static [&'static str],
+    pub args: ArgKind,
+}
+
+/// All commands understood by `cmd_manager`.
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "echo",
+        flags: &[],
+        args: ArgKind::None,
+    },
+    CommandSpec {
+        name: "whoami",
+        flags: &[],
+        args: ArgKind::None,
+    },
+    CommandSpec {
+        name: "go",
+        flags: &[],
+        args: ArgKind::Dir,
+    },
+    CommandSpec {
+        name: "ls",
+        flags: &["-a", "--all"],
+        args: ArgKind::Dir,
+    },
+    CommandSpec {
+        name: "read",
+        flags: &[],
+        args: ArgKind::File,
+    },
+    CommandSpec {
+        name: "copy",
+        flags: &["-x", "--cut", "-r", "--recursive", "-f", "--force"],
+        args: ArgKind::Path,
+    },
+    CommandSpec {
+        name: "tap",
+        flags: &["-d", "--dir"],
+        args: ArgKind::Path,
+    },
+    CommandSpec {
+        name: "del",
+        flags: &["-d", "--dir", "-f", "--force"],
+        args: ArgKind::Path,
+    },
+    CommandSpec {
+        name: "whereami",
+        flags: &[],
+        args: ArgKind::None,
+    },
+    CommandSpec {
+        name: "help",
+        flags: &[],
+        args: ArgKind::Command,
+    },
+    CommandSpec {
+        name: "history",
+        flags: &["-c", "--clear"],
+        args: ArgKind::None,
+    },
+    CommandSpec {
+        name: "alias",
+        flags: &[],
+        args: ArgKind::Alias,
+    },
+    CommandSpec {
+        name: "unalias",
+        flags: &["-a"],
+        args: ArgKind::Alias,
+    },
+    CommandSpec {
+        name: "function",
+        flags: &[],
+        args: ArgKind::Function,
+    },
+    CommandSpec {
+        name: "unfunction",
+        flags: &[],
+        args: ArgKind::Function,
+    },
+    CommandSpec {
+        name: "set",
+        flags: &[],
+        args: ArgKind::Variable,
+    },
+    CommandSpec {
+        name: "unset",
+        flags: &[],
+        args: ArgKind::Variable,
+    },
+    CommandSpec {
+        name: "env",
+        flags: &[],
+        args: ArgKind::None,
+    },
+    CommandSpec {
+        name: "run",
+        flags: &[],
+        args: ArgKind::File,
+    },
+    CommandSpec {
+        name: "take",
+        flags: &[],
+        args: ArgKind::File,
+    },
+    CommandSpec {
+        name: "drop",
+        flags: &[],
+        args: ArgKind::Item,
+    },
+    CommandSpec {
+        name: "talk",
+        flags: &[],
+        args: ArgKind::File,
+    },
+    CommandSpec {
+        name: "objectives",
+        flags: &[],
+        args: ArgKind::None,
+    },
+    CommandSpec {
+        name: "inventory",
+        flags: &["-a", "--all"],
+        args: ArgKind::None,
+    },
+    CommandSpec {
+        name: "clear",
+        flags: &[],
+        args: ArgKind::None,
+    },
+    CommandSpec {
+        name: "exit",
+        flags: &[],
+        args: ArgKind::None,
+    },
+    CommandSpec {
+        name: "restore",
+        flags: &["-f", "--force"],
+        args: ArgKind::None,
+    },
+    CommandSpec {
+        name: "save",
+        flags: &[],
+        args: ArgKind::None,
+    },
+    CommandSpec {
+        name: "solve",
+        flags: &[],
+        args: ArgKind::Locked,
+    },
+    CommandSpec {
+        name: "unlock",
+        flags: &["-l", "--level", "-c", "--chest"],
+        args: ArgKind::Locked,
+    },
+];
+
+/// Splits an input line into the parts used for completion.
+/// If the input ends with a space, an empty last part is added so that the next word is completed.
+pub fn split_for_completion(input: &str) -> Vec<&str> {
+    let mut parts: Vec<&str> = input.split_whitespace().collect();
+    if input.is_empty() || input.ends_with(char::is_whitespace) {
+        parts.push("");
+    }
+    parts
+}
+
+/// Returns the possible completions for the last of `parts`, sorted.
+/// The first word completes to commands and aliases, later words depend on the command:
+/// flags for `-..`, variables for `$..`, and otherwise the command's argument kind.
+pub fn completions(parts: &[&str], current_dir: &Path, root_dir: &Path) -> Vec<String> {
+    let Some((&word, previous)) = parts.split_last() else {
+        return Vec::new();
+    };
+
+    let mut matches: Vec<String> = if previous.is_empty() {
+        command_names()
+    } else if word.starts_with("${") {
+        variable_names()
+            .into_iter()
+            .map(|var| format!("${{{var}}}"))
+            .collect()
+    } else if word.starts_with('$') {
+        variable_names()
+            .into_iter()
+            .map(|var| format!("${var}"))
+            .collect()
+    } else {
+        // The arguments go to the last command of an alias holding a chain
+        let command = expand_chain(previous[0])
+            .pop()
+            .and_then(|(command, _)| command.split_whitespace().next().map(str::to_string))
+            .unwrap_or_default();
+        let spec = COMMANDS.iter().find(|spec| spec.name == command);
+        match spec {
+            Some(spec) if word.starts_with('-') => spec
+                .flags
+                .iter()
+                .chain(&["-h", "--help"])
+                .map(|flag| flag.to_string())
+                .collect(),
+            Some(spec) => match spec.args {
+                ArgKind::None => Vec::new(),
+                ArgKind::Command => command_names(),
+                ArgKind::Alias => get_aliases().into_keys().collect(),
+                ArgKind::Function => get_functions().into_keys().collect(),
+                ArgKind::Variable => variable_names(),
+                ArgKind::Item => Inventory::load(&current_user())
+                    .carried()
+                    .map(|item| item.name.clone())
+                    .collect(),
+                kind => path_completions(word, kind, current_dir, root_dir),
+            },
+            None => path_completions(word, ArgKind::Path, current_dir, root_dir),
+        }
+    };
+
+    matches.retain(|m| m.starts_with(word));
+    matches.sort();
+    matches.dedup();
+    matches
+}
+
+fn command_names() -> Vec<String> {
+    COMMANDS
+        .iter()
+        .map(|spec| spec.name.to_string())
+        .chain(get_aliases().into_keys())
+        .chain(get_functions().into_keys())
+        .collect()
+}
+
+fn variable_names() -> Vec<String> {
+    BUILTIN_VARS
+        .iter()
+        .map(|name| name.to_string())
+        .chain(get_vars().into_keys())
+        .collect()
+}
+
+/// Completes a (possibly nested) path like `room_8/di` inside the sekai.
+/// Directories end with `/` so that the next Tab continues into them.
+fn path_completions(word: &str, kind: ArgKind, current_dir: &Path, root_dir: &Path) -> Vec<String> {
+    let (dir_part, name) = match word.rfind('/') {
+        Some(i) => word.split_at(i + 1),
+        None => ("", word),
+    };
+    let dir = normalize_path(&current_dir.join(dir_part));
+    if !dir.starts_with(root_dir) || check_dir_info(&dir) {
+        return Vec::new();
+    }
+
+    // Nothing inside a hidden directory is offered, as `ls` would not list it
+    let visibility = Visibility::current();
+    if visibility.is_hidden(&dir, root_dir) {
+        return Vec::new();
+    }
+    let (files, dirs) = list_directory_entries(&dir, root_dir, &visibility);
+    let is_locked = |entry: &str| {
+        matches!(
+            read_player_lock_perm(&dir.join(entry), root_dir, &visibility.progress),
+            Ok((_, true))
+        )
+    };
+    let mut matches = Vec::new();
+    for entry in dirs {
+        if kind == ArgKind::Locked && is_locked(&entry) {
+            matches.push(format!("{dir_part}{entry}"));
+        } else {
+            matches.push(format!("{dir_part}{entry}/"));
+        }
+    }
+    for entry in files {
+        let wanted = match kind {
+            ArgKind::Dir => false,
+            ArgKind::Locked => is_locked(&entry),
+            _ => true,
+        };
+        if wanted {
+            matches.push(format!("{dir_part}{entry}"));
+        }
+    }
+
+    // Hidden entries only show up once the name starts with a `.`
+    if !name.starts_with('.') {
+        matches.retain(|m| !m[dir_part.len()..].starts_with('.'));
+    }
+    matches
+}
+
+/// Name shown when listing a completion, i.e. its last path component.
+fn display_name(completion: &str) -> &str {
+    let trimmed = completion.trim_end_matches('/');
+    match trimmed.rfind('/') {
+        Some(i) => &completion[i + 1..],
+        None => completion,
+    }
+}
+
 // Helper types and functions
 pub enum TabCompletionResult {
     SingleMatch(String),
@@ -21,12 +346,7 @@ pub fn process_tab_completion(
     current_input: &str,
     prompt: Option<&str>,
 ) -> TabCompletionResult {
-    let last_part_str = parts.last().unwrap();
-
-    // Convert to normalized path and back to String
-    let last_part = normalize_path(Path::new(last_part_str))
-        .to_string_lossy()
-        .into_owned();
+    let last_part = parts.last().copied().unwrap_or_default();
 
     if matches.len() == 1 {
         // Single match - complete it
@@ -66,6 +386,11 @@ pub fn process_tab_completion(
                 format!("> {current_input}")
             };
 
+            let matches: Vec<String> = matches
+                .iter()
+                .map(|m| display_name(m).to_string())
+                .collect();
+
             // Calculate optimal column display
             let max_len = matches.iter().map(|s| s.len()).max().unwrap_or(0) + 2;
             let cols = (term_width / max_len).max(1);
@@ -103,3 +428,27 @@ pub fn process_tab_completion(
         TabCompletionResult::NoAction
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::setup_test_dir;
+
+    #[test]
+    fn test_completions() {
+        let (_temp_dir, root_path) = setup_test_dir(true);
+        let complete =
+            |input: &str| completions(&split_for_completion(input), &root_path, &root_path);
+
+        assert_eq!(complete("wh"), ["whereami", "whoami"]);
+        assert_eq!(complete("ls --"), ["--all", "--help"]);
+        assert_eq!(complete("go "), ["subdir1/", "subdir2/"]);
+        assert_eq!(
+            complete("read subdir1/"),
+            ["subdir1/file2.txt", "subdir1/file3.txt", "subdir1/nested1/"]
+        );
+        assert_eq!(complete("go subdir2/ne"), ["subdir2/nested2/"]);
+        assert!(complete("read .dir").is_empty());
+        assert!(complete("read ../").is_empty());
+    }
+}
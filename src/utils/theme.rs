@@ -0,0 +1,251 @@
+use crate::utils::config::{get_config_path, load_config, save_config};
+use crate::utils::globals::THEME;
+use crate::utils::log;
+use raylib::prelude::Color;
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// A color, written as `#rrggbb` in theme files.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    pub fn color(self) -> Color {
+        self.alpha(255)
+    }
+
+    pub fn alpha(self, a: u8) -> Color {
+        Color::new(self.0, self.1, self.2, a)
+    }
+
+    /// Same color, for the raw raylib functions.
+    pub fn ffi(self) -> raylib::ffi::Color {
+        raylib::ffi::Color {
+            r: self.0,
+            g: self.1,
+            b: self.2,
+            a: 255,
+        }
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+impl FromStr for Rgb {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("invalid color '{s}', expected #rrggbb"));
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+        Ok(Rgb(channel(0), channel(2), channel(4)))
+    }
+}
+
+impl Serialize for Rgb {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Rgb {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Colors of the GUI.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Theme {
+    pub name: String,
+    pub background: Rgb,
+    pub foreground: Rgb,
+    /// Highlighted menu entries and headings.
+    pub accent: Rgb,
+    pub error: Rgb,
+    /// Background of selected text.
+    pub selection: Rgb,
+    pub prompt: Rgb,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        builtin_themes().remove(0)
+    }
+}
+
+/// Themes shipped with deemak. The first one is the default.
+pub fn builtin_themes() -> Vec<Theme> {
+    let theme = |name: &str, colors: [Rgb; 6]| Theme {
+        name: name.to_string(),
+        background: colors[0],
+        foreground: colors[1],
+        accent: colors[2],
+        error: colors[3],
+        selection: colors[4],
+        prompt: colors[5],
+    };
+    vec![
+        theme(
+            "Classic",
+            [
+                Rgb(0, 0, 0),
+                Rgb(255, 255, 255),
+                Rgb(255, 203, 0),
+                Rgb(255, 95, 95),
+                Rgb(64, 96, 128),
+                Rgb(255, 255, 255),
+            ],
+        ),
+        theme(
+            "Solarized Dark",
+            [
+                Rgb(0, 43, 54),
+                Rgb(147, 161, 161),
+                Rgb(181, 137, 0),
+                Rgb(220, 50, 47),
+                Rgb(7, 54, 66),
+                Rgb(38, 139, 210),
+            ],
+        ),
+        theme(
+            "Dracula",
+            [
+                Rgb(40, 42, 54),
+                Rgb(248, 248, 242),
+                Rgb(189, 147, 249),
+                Rgb(255, 85, 85),
+                Rgb(68, 71, 90),
+                Rgb(80, 250, 123),
+            ],
+        ),
+        theme(
+            "Gruvbox",
+            [
+                Rgb(40, 40, 40),
+                Rgb(235, 219, 178),
+                Rgb(250, 189, 47),
+                Rgb(251, 73, 52),
+                Rgb(80, 73, 69),
+                Rgb(184, 187, 38),
+            ],
+        ),
+        theme(
+            "Phosphor",
+            [
+                Rgb(5, 12, 5),
+                Rgb(51, 255, 51),
+                Rgb(180, 255, 180),
+                Rgb(255, 80, 80),
+                Rgb(20, 80, 20),
+                Rgb(51, 255, 51),
+            ],
+        ),
+    ]
+}
+
+/// Directory with the user's own themes, one JSON file per theme.
+pub fn get_themes_dir() -> PathBuf {
+    get_config_path().with_file_name("themes")
+}
+
+/// Loads the user's themes. Invalid files are skipped with a warning.
+pub fn user_themes() -> Vec<Theme> {
+    let Ok(entries) = fs::read_dir(get_themes_dir()) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let theme = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|contents| {
+                    serde_json::from_str::<Theme>(&contents).map_err(|e| e.to_string())
+                });
+            match theme {
+                Ok(theme) => Some(theme),
+                Err(e) => {
+                    log::log_warning("theme", &format!("Skipping theme {}: {e}", path.display()));
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Built-in themes followed by the user's. A user theme with the name of a built-in replaces it.
+pub fn available_themes() -> Vec<Theme> {
+    let mut themes = builtin_themes();
+    for theme in user_themes() {
+        match themes.iter_mut().find(|t| t.name == theme.name) {
+            Some(existing) => *existing = theme,
+            None => themes.push(theme),
+        }
+    }
+    themes
+}
+
+/// Theme selected in the config, or the default one.
+pub fn load_theme() -> Theme {
+    let name = load_config().theme;
+    available_themes()
+        .into_iter()
+        .find(|theme| theme.name == name)
+        .unwrap_or_default()
+}
+
+/// The theme in use.
+pub fn current_theme() -> Theme {
+    THEME
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(load_theme)
+        .clone()
+}
+
+/// Uses a theme until the next change, without saving it. Used for previews.
+pub fn set_theme(theme: Theme) {
+    *THEME.lock().unwrap_or_else(|e| e.into_inner()) = Some(theme);
+}
+
+/// Uses a theme and saves it as the user's choice.
+pub fn save_theme(theme: Theme) {
+    let mut cfg = load_config();
+    cfg.theme = theme.name.clone();
+    save_config(&cfg);
+    set_theme(theme);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_json() {
+        let theme = Theme::default();
+        let json = serde_json::to_string(&theme).unwrap();
+        assert!(json.contains("\"background\":\"#000000\""));
+        assert_eq!(serde_json::from_str::<Theme>(&json).unwrap(), theme);
+
+        assert_eq!("#ff8000".parse::<Rgb>(), Ok(Rgb(255, 128, 0)));
+        assert!("#ff80".parse::<Rgb>().is_err());
+        assert!("#gg0000".parse::<Rgb>().is_err());
+    }
+}
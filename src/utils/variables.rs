@@ -0,0 +1,267 @@
+use crate::commands::display_relative_path;
+use crate::metainfo::lock_perm::read_lock_perm;
+use crate::utils::globals::SHELL_VARS;
+use crate::utils::shell_history::current_user;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Variables derived from the shell state. They can be read but not set.
+pub const BUILTIN_VARS: [&str; 4] = ["HOME", "PWD", "USER", "LEVEL"];
+
+/// Variable names start with a letter or `_`, followed by letters, digits or `_`
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Returns the name of the level the player is in, i.e. the nearest directory
+/// (starting from `current_dir`) marked as a level. Empty if not inside a level.
+pub fn current_level(current_dir: &Path, root_dir: &Path) -> String {
+    let mut dir = current_dir;
+    while dir.starts_with(root_dir) && dir != root_dir {
+        if let Ok((true, _)) = read_lock_perm(dir) {
+            return dir
+                .file_name()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+        }
+        match dir.parent() {
+            Some(parent) => dir = parent,
+            None => break,
+        }
+    }
+    String::new()
+}
+
+/// Value of a built-in variable, or None if `name` is not a built-in.
+pub fn get_builtin(name: &str, current_dir: &Path, root_dir: &Path) -> Option<String> {
+    match name {
+        "HOME" => Some(display_relative_path(root_dir, root_dir)),
+        "PWD" => Some(display_relative_path(current_dir, root_dir)),
+        "USER" => Some(current_user()),
+        "LEVEL" => Some(current_level(current_dir, root_dir)),
+        _ => None,
+    }
+}
+
+/// Value of a variable set with `set` by the current user.
+pub fn get_var(name: &str) -> Option<String> {
+    SHELL_VARS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&current_user())
+        .and_then(|vars| vars.get(name).cloned())
+}
+
+/// Sets a session variable of the current user. Fails for invalid names and built-ins.
+pub fn set_var(name: &str, value: &str) -> Result<(), String> {
+    if !is_valid_name(name) {
+        return Err(format!("'{name}': not a valid variable name"));
+    }
+    if BUILTIN_VARS.contains(&name) {
+        return Err(format!("{name}: read-only variable"));
+    }
+    SHELL_VARS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(current_user())
+        .or_default()
+        .insert(name.to_string(), value.to_string());
+    Ok(())
+}
+
+/// Removes a session variable of the current user. Returns false if it was not set.
+pub fn unset_var(name: &str) -> bool {
+    SHELL_VARS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_mut(&current_user())
+        .is_some_and(|vars| vars.remove(name).is_some())
+}
+
+/// Returns all session variables of the current user, sorted by name.
+pub fn get_vars() -> BTreeMap<String, String> {
+    SHELL_VARS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&current_user())
+        .map(|vars| vars.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+        .unwrap_or_default()
+}
+
+/// Expands `$NAME` and `${NAME}` in a command line and splits it into words.
+/// Unset variables expand to nothing. Text in single quotes and `\$` are left as is, and
+/// text in quotes stays one word. The quotes themselves are removed.
+pub fn expand_vars(input: &str, current_dir: &Path, root_dir: &Path) -> Vec<String> {
+    expand_with(input, |name| {
+        get_builtin(name, current_dir, root_dir).or_else(|| get_var(name))
+    })
+}
+
+/// Splits a command line at the `&&`, `||` and `;` out of quotes. Each command is returned
+/// as written, with the operator that follows it, if any.
+pub fn split_chain(input: &str) -> Vec<(String, Option<&'static str>)> {
+    let mut commands = Vec::new();
+    let mut current = String::new();
+    let mut chars = input.chars().peekable();
+    let mut quote: Option<char> = None;
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '&' | '|') if chars.peek() == Some(&c) => {
+                chars.next();
+                let operator = if c == '&' { "&&" } else { "||" };
+                commands.push((std::mem::take(&mut current), Some(operator)));
+                continue;
+            }
+            (None, ';') => {
+                commands.push((std::mem::take(&mut current), Some(";")));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    commands.push((current, None));
+    commands
+}
+
+/// Words of a command line being split.
+#[derive(Default)]
+struct Words {
+    words: Vec<String>,
+    current: String,
+    /// Whether the current word exists even if empty, like `''`.
+    started: bool,
+}
+
+impl Words {
+    fn push(&mut self, c: char) {
+        self.current.push(c);
+        self.started = true;
+    }
+
+    fn end_word(&mut self) {
+        if self.started {
+            self.words.push(std::mem::take(&mut self.current));
+            self.started = false;
+        }
+    }
+
+    /// Adds expanded text. Out of quotes, its whitespace separates words.
+    fn push_expansion(&mut self, value: &str, quoted: bool) {
+        for c in value.chars() {
+            if c.is_whitespace() && !quoted {
+                self.end_word();
+            } else {
+                self.push(c);
+            }
+        }
+    }
+}
+
+fn expand_with(input: &str, lookup: impl Fn(&str) -> Option<String>) -> Vec<String> {
+    let mut words = Words::default();
+    let mut chars = input.chars().peekable();
+    let mut quote: Option<char> = None;
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => {
+                quote = None;
+                words.started = true;
+            }
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                words.started = true;
+            }
+            (None, c) if c.is_whitespace() => words.end_word(),
+            (Some('\''), c) => words.push(c),
+            (_, '\\') if chars.peek() == Some(&'$') => {
+                words.push('$');
+                chars.next();
+            }
+            (_, '$') => {
+                let braced = chars.peek() == Some(&'{');
+                if braced {
+                    chars.next();
+                }
+                let mut name = String::new();
+                while let Some(&n) = chars.peek() {
+                    if n.is_ascii_alphanumeric() || n == '_' {
+                        name.push(n);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                if braced {
+                    if chars.peek() == Some(&'}') && is_valid_name(&name) {
+                        chars.next();
+                    } else {
+                        // Not a valid `${NAME}`, keep the text
+                        words.push_expansion(&format!("${{{name}"), true);
+                        continue;
+                    }
+                }
+                if is_valid_name(&name) {
+                    let value = lookup(&name).unwrap_or_default();
+                    words.push_expansion(&value, quote.is_some());
+                } else {
+                    words.push_expansion(&format!("${name}"), true);
+                }
+            }
+            (_, c) => words.push(c),
+        }
+    }
+    words.end_word();
+    words.words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_vars() {
+        let lookup = |name: &str| match name {
+            "PWD" => Some("HOME/room_1".to_string()),
+            "KEY" => Some("42".to_string()),
+            _ => None,
+        };
+        assert_eq!(expand_with("echo $PWD", lookup), ["echo", "HOME/room_1"]);
+        assert_eq!(expand_with("echo ${KEY}x $KEYx", lookup), ["echo", "42x"]);
+        assert_eq!(
+            expand_with("echo '$KEY' \\$KEY", lookup),
+            ["echo", "$KEY", "$KEY"]
+        );
+        assert_eq!(
+            expand_with("echo $ $1 ${KEY", lookup),
+            ["echo", "$", "$1", "${KEY"]
+        );
+        assert_eq!(
+            expand_with("set A=\"x  $KEY\" '' b'c d'", lookup),
+            ["set", "A=x  42", "", "bc d"]
+        );
+    }
+
+    #[test]
+    fn test_split_chain() {
+        assert_eq!(
+            split_chain("go room&&ls || echo '&&' \"a||b\""),
+            [
+                ("go room".to_string(), Some("&&")),
+                ("ls ".to_string(), Some("||")),
+                (" echo '&&' \"a||b\"".to_string(), None),
+            ]
+        );
+        assert_eq!(split_chain("ls &&").len(), 2);
+        assert_eq!(
+            split_chain("go ..; ls"),
+            [("go ..".to_string(), Some(";")), (" ls".to_string(), None),]
+        );
+    }
+}
@@ -15,8 +15,8 @@ let registered              = false;
 let exitConfirmationPending = false;
 
 let currentDir    = "";
-let commandHistory = [];
-let historyIndex   = -1;
+let serverHistory = [];   // snapshot of the history kept by the server
+let historyIndex  = -1;
 
 async function checkSessionOnLoad() {
   const token = localStorage.getItem('token')
@@ -56,6 +56,20 @@ async function verifySession(existingToken) {
 }
 
 
+async function loadHistory() {
+  const token = localStorage.getItem('token') || "";
+  try {
+    const response = await fetch(
+      `${window.BACKEND_URL}/backend/history?token=${encodeURIComponent(token)}`
+    );
+    serverHistory = await response.json();
+  } catch (error) {
+    console.error("History error:", error);
+    serverHistory = [];
+  }
+  historyIndex = serverHistory.length;
+}
+
 function showAuthScreen(errorMsg = "") {
   authenticated = false;
   registered    = false;
@@ -232,19 +246,21 @@ function backToMenu() {
   clearTerminal();
 }
 
-function clearTerminal() {
+function resetTerminal() {
   terminal.innerHTML = `        
     <div class="terminal_line">
       <h4>Type commands and press Enter. Try <code>help</code> for more info.</h4>
     </div>`;
+}
+
+function clearTerminal() {
+  resetTerminal();
   addNewInput();
   return;
 }
 
 async function processCommand(input) {
   const command = input.value.trim();
-  commandHistory.push(command);
-  historyIndex = commandHistory.length;
   input.insertAdjacentHTML('afterend', `<div class="previous_input">${command}</div>`);
   document.getElementById("terminal_input").remove();
 
@@ -283,12 +299,33 @@ async function processCommand(input) {
     exitConfirmationPending = false;
   }
 
+  await runCommand(command, []);
+}
+
+// Runs a command on the server. When it asks for something (like a flag), the answer is
+// asked here and the command is sent again with all the answers so far.
+async function runCommand(command, answers) {
+  const output = document.createElement('div');
+  output.classList.add("response");
+
   try {
-    const response = await fetch(
-      `${window.BACKEND_URL}/backend/run?command=${encodeURIComponent(command)}&current_dir=${encodeURIComponent(currentDir)}`
-    );
+    const token = localStorage.getItem('token') || "";
+    const body = new URLSearchParams({ command, current_dir: currentDir, token });
+    answers.forEach(answer => body.append('answer', answer));
+    const response = await fetch(`${window.BACKEND_URL}/backend/run`, {
+      method: 'POST',
+      headers: { 'Content-Type': 'application/x-www-form-urlencoded' },
+      body: body.toString()
+    });
     const result = await response.json();
-    output.innerText = result.output;
+    if (result.prompt) {
+      askAnswer(command, answers, result.prompt);
+      return;
+    }
+    if (result.clear) {
+      resetTerminal();
+    }
+    output.innerHTML = result.html;
     if (result.new_current_dir) {
       currentDir = result.new_current_dir;
     }
@@ -301,6 +338,51 @@ async function processCommand(input) {
   addNewInput();
 }
 
+function askAnswer(command, answers, question) {
+  const line = document.createElement('div');
+  line.className = 'terminal_line';
+
+  const prompt = document.createElement('div');
+  prompt.textContent = question;
+
+  const input = document.createElement('input');
+  input.type = 'password';
+  input.id   = 'terminal_input';
+  input.autocomplete = 'off';
+
+  input.addEventListener('keydown', function (e) {
+    if (e.key === 'Enter') {
+      const answer = input.value;
+      input.remove();
+      runCommand(command, [...answers, answer]);
+    }
+  });
+
+  line.appendChild(prompt);
+  line.appendChild(input);
+  terminal.appendChild(line);
+  input.focus();
+}
+
+async function completeCommand(input) {
+  try {
+    const token = localStorage.getItem('token') || "";
+    const response = await fetch(
+      `${window.BACKEND_URL}/backend/complete?input=${encodeURIComponent(input.value)}&current_dir=${encodeURIComponent(currentDir)}&token=${encodeURIComponent(token)}`
+    );
+    const result = await response.json();
+    if (result.completions.length > 0) {
+      const options = document.createElement('div');
+      options.classList.add("response");
+      options.innerText = result.completions.join("\n");
+      input.parentElement.insertAdjacentElement('beforebegin', options);
+    }
+    input.value = result.input;
+  } catch (error) {
+    console.error("Error:", error);
+  }
+}
+
 function addNewInput() {
   const line = document.createElement('div');
   line.className = 'terminal_line';
@@ -316,16 +398,20 @@ function addNewInput() {
   input.addEventListener('keydown', function (e) {
     if (e.key === 'Enter') {
       processCommand(input);
+    } else if (e.key === 'Tab') {
+      e.preventDefault();
+      completeCommand(input);
     } else if (e.key === 'ArrowUp') {
       if (historyIndex > 0) {
         historyIndex--;
-        input.value = commandHistory[historyIndex];
+        input.value = serverHistory[historyIndex];
       }
     } else if (e.key === 'ArrowDown') {
-      if (historyIndex < commandHistory.length - 1) {
+      if (historyIndex < serverHistory.length - 1) {
         historyIndex++;
-        input.value = commandHistory[historyIndex];
+        input.value = serverHistory[historyIndex];
       } else {
+        historyIndex = serverHistory.length;
         input.value = "";
       }
     }
@@ -337,6 +423,7 @@ function addNewInput() {
 
   input.focus();
   input.autocomplete = 'off';
+  loadHistory();
 }
 
 window.startTerminal  = startTerminal;